@@ -20,3 +20,50 @@ pub struct Content {
   pub id: ContentId,
   pub title: String,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_graphql::{InputType, OutputType, Value};
+  use deque_loader::Key;
+  use std::collections::{BTreeSet, HashSet};
+
+  fn assert_key<K: Key>() {}
+
+  #[test]
+  fn it_derives_key_traits() {
+    assert_key::<ContentId>();
+
+    let id = ContentId::from(2);
+    let copied = id;
+
+    assert_eq!(id, copied);
+    assert!(ContentId::from(1) < id);
+    assert_eq!(
+      vec![ContentId::from(3), ContentId::from(1), ContentId::from(3)]
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>(),
+      vec![ContentId::from(1), ContentId::from(3)]
+    );
+    assert_eq!(
+      vec![id, copied].into_iter().collect::<HashSet<_>>().len(),
+      1
+    );
+    assert_eq!(id.to_string(), "2");
+    let inner: i32 = id.into();
+    assert_eq!(inner, 2);
+  }
+
+  #[test]
+  fn it_converts_to_graphql_scalars() {
+    assert_eq!(<ContentId as InputType>::type_name(), "ContentID");
+    assert_eq!(
+      ContentId::parse(Some(Value::from(4))).ok(),
+      Some(ContentId::from(4))
+    );
+    assert_eq!(InputType::to_value(&ContentId::from(4)), Value::from(4));
+    assert_eq!(<ContentId as OutputType>::type_name(), "ContentID");
+  }
+}
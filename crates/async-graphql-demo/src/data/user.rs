@@ -57,6 +57,7 @@ impl User {
     Ok(bookmarks)
   }
 }
+#[graphql_loader(field_name = "user")]
 #[derive(Loader)]
 #[data_loader(handler = "DieselHandler<UserLoader>")]
 pub struct UserLoader;
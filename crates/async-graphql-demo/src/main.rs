@@ -29,7 +29,7 @@ async fn main() {
   booter::boot();
 
   let schema: Schema<QueryRoot, MutationRoot, EmptySubscription> =
-    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+    Schema::build(QueryRoot::default(), MutationRoot, EmptySubscription)
       .extension(ApolloTracing)
       .finish();
 
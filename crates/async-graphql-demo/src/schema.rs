@@ -1,4 +1,4 @@
-pub use async_graphql::{EmptyMutation, EmptySubscription};
+pub use async_graphql::EmptySubscription;
 
 mod mutation_root;
 mod query_root;
@@ -1,14 +1,17 @@
 use std::sync::Arc;
 
 use crate::data::*;
-use async_graphql::{Context, ErrorExtensions, FieldResult, Object};
+use async_graphql::{Context, ErrorExtensions, FieldResult, MergedObject, Object};
 use deque_loader::{diesel::SimpleDieselError, LoadBy};
 
+#[derive(MergedObject, Default)]
+pub struct QueryRoot(ContentQuery, UserLoaderQuery);
+
 #[derive(Default)]
-pub struct QueryRoot;
+pub struct ContentQuery;
 
 #[Object]
-impl QueryRoot {
+impl ContentQuery {
   async fn status(&self) -> i32 {
     200
   }
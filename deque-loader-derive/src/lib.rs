@@ -3,15 +3,16 @@ extern crate syn;
 
 use darling::FromMeta;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use std::vec;
-use syn::{parse_macro_input, Attribute, DeriveInput};
+use syn::{
+  parse_macro_input, spanned::Spanned, Attribute, AttributeArgs, DeriveInput, ItemFn, ItemStruct,
+};
 
 #[derive(FromMeta)]
 struct DataLoaderAttr {
   handler: syn::Path,
-  #[darling(default)]
-  cached: bool,
+  cached: Option<bool>,
 }
 
 fn parse_data_loaders(attrs: &[Attribute]) -> darling::Result<Vec<DataLoaderAttr>> {
@@ -56,7 +57,7 @@ pub fn load_by(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
   let handler: Vec<&syn::Path> = loaders
     .iter()
     .filter_map(|loader| {
-      if loader.cached {
+      if loader.cached.unwrap_or_default() {
         None
       } else {
         Some(&loader.handler)
@@ -68,7 +69,7 @@ pub fn load_by(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     .iter()
     .filter_map(|loader| {
       let DataLoaderAttr { handler, cached } = loader;
-      if *cached {
+      if cached.unwrap_or_default() {
         Some(quote! {
           deque_loader::redis::RedisCacheAdapter<#handler>
         })
@@ -94,7 +95,7 @@ pub fn load_by(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
           rx.recv().await
         }
 
-        async fn cached_load_by<RequestCache: Send + Sync + AsRef<deque_loader::request::ContextCache<#handler>>>(
+        async fn cached_load_by<RequestCache: Send + Sync + deque_loader::request::KeyedCache<#handler>>(
           key: <#handler as deque_loader::task::TaskHandler>::Key,
           request_cache: &RequestCache
         ) -> Result<Option<std::sync::Arc<<#handler as deque_loader::task::TaskHandler>::Value>>, Self::Error> {
@@ -121,7 +122,7 @@ pub fn load_by(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
           rx.recv().await
         }
 
-        async fn cached_load_by<RequestCache: Send + Sync + AsRef<deque_loader::request::ContextCache<#cached_handler>>>(
+        async fn cached_load_by<RequestCache: Send + Sync + deque_loader::request::KeyedCache<#cached_handler>>(
           key: <#cached_handler as deque_loader::task::TaskHandler>::Key,
           request_cache: &RequestCache
         ) -> Result<Option<std::sync::Arc<<#cached_handler as deque_loader::task::TaskHandler>::Value>>, Self::Error> {
@@ -138,6 +139,92 @@ pub fn load_by(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
   proc_macro::TokenStream::from(expanded)
 }
 
+/// Derive the traits required of [`Key`](deque_loader::Key) on a newtype struct by delegating to its inner type. Derive macros can't add attributes, so annotate with `#[repr(transparent)]` where a zero-cost layout is needed
+///
+/// ```rust
+/// #[derive(Key, Debug)]
+/// #[repr(transparent)]
+/// pub struct UserId(i32);
+///
+/// assert!(UserId(1) < UserId(2));
+/// ```
+#[proc_macro_derive(Key)]
+pub fn key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let ident = &input.ident;
+
+  let fields = match &input.data {
+    syn::Data::Struct(data) if data.fields.len() == 1 => &data.fields,
+    _ => {
+      return syn::Error::new_spanned(&input, "Key can only be derived for newtype structs")
+        .to_compile_error()
+        .into()
+    }
+  };
+
+  let field = fields.iter().next().unwrap();
+  let inner = &field.ty;
+
+  let (member, construct) = match &field.ident {
+    Some(name) => (quote!(#name), quote!(#ident { #name: value })),
+    None => (quote!(0), quote!(#ident(value))),
+  };
+
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let mut predicates: Vec<TokenStream> = where_clause
+    .map(|where_clause| {
+      where_clause
+        .predicates
+        .iter()
+        .map(|predicate| quote!(#predicate))
+        .collect()
+    })
+    .unwrap_or_default();
+
+  // Bounding each impl by the inner type reports unmet bounds against the inner type rather than within generated code
+  predicates.push(quote_spanned!(inner.span()=> #inner: deque_loader::Key));
+
+  let where_clause = quote!(where #(#predicates),*);
+
+  let expanded = quote! {
+    impl #impl_generics ::std::clone::Clone for #ident #ty_generics #where_clause {
+      fn clone(&self) -> Self {
+        let value = ::std::clone::Clone::clone(&self.#member);
+        #construct
+      }
+    }
+
+    impl #impl_generics ::std::cmp::PartialEq for #ident #ty_generics #where_clause {
+      fn eq(&self, other: &Self) -> bool {
+        ::std::cmp::PartialEq::eq(&self.#member, &other.#member)
+      }
+    }
+
+    impl #impl_generics ::std::cmp::Eq for #ident #ty_generics #where_clause {}
+
+    impl #impl_generics ::std::cmp::PartialOrd for #ident #ty_generics #where_clause {
+      fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+        ::std::option::Option::Some(::std::cmp::Ord::cmp(self, other))
+      }
+    }
+
+    impl #impl_generics ::std::cmp::Ord for #ident #ty_generics #where_clause {
+      fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+        ::std::cmp::Ord::cmp(&self.#member, &other.#member)
+      }
+    }
+
+    impl #impl_generics ::std::hash::Hash for #ident #ty_generics #where_clause {
+      fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+        ::std::hash::Hash::hash(&self.#member, state)
+      }
+    }
+  };
+
+  proc_macro::TokenStream::from(expanded)
+}
+
 #[proc_macro_derive(Loader, attributes(data_loader))]
 pub fn local_loader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
   let input = parse_macro_input!(input as DeriveInput);
@@ -154,7 +241,7 @@ pub fn local_loader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     .iter()
     .filter_map(|loader| {
       let DataLoaderAttr { handler, cached } = loader;
-      if *cached {
+      if cached.unwrap_or_default() {
         Some(quote! {
           deque_loader::redis::RedisCacheAdapter<#handler>
         })
@@ -166,6 +253,8 @@ pub fn local_loader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
   let expanded = quote! {
     #(
+      const _: () = deque_loader::task::assert_valid_constants::<#handler>();
+
       impl deque_loader::loader::LocalLoader<deque_loader::loader::DataStore> for #loader {
         type Handler = #handler;
         fn loader() -> &'static std::thread::LocalKey<deque_loader::loader::DataLoader<Self::Handler>> {
@@ -195,3 +284,189 @@ pub fn local_loader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
   proc_macro::TokenStream::from(expanded)
 }
+
+/// Wrap an async test with a `deque_loader::testing::BatchRecorder` of the given `MockBackend`, asserting recorded batches match the insta snapshot named after the test. Requires the `testing-insta` feature of deque-loader
+#[proc_macro_attribute]
+pub fn loader_snapshot_test(
+  attr: proc_macro::TokenStream,
+  item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+  let backend = parse_macro_input!(attr as syn::Path);
+  let ItemFn {
+    attrs,
+    vis,
+    sig,
+    block,
+  } = parse_macro_input!(item as ItemFn);
+
+  let name = &sig.ident;
+
+  let expanded = quote! {
+    #[tokio::test]
+    #(#attrs)*
+    #vis #sig {
+      let recorder = deque_loader::testing::BatchRecorder::<#backend>::start();
+      let result = async move #block.await;
+      deque_loader::assert_batch_snapshot!(recorder, stringify!(#name));
+      result
+    }
+  };
+
+  proc_macro::TokenStream::from(expanded)
+}
+
+#[derive(FromMeta)]
+struct GraphqlLoaderAttr {
+  field_name: Option<String>,
+}
+
+/// Generate async-graphql wiring for a loader deriving [`Loader`]: an `async_graphql::dataloader::Loader` impl, a resolver `async fn load_by_id(ctx: &Context<'_>, id: Key) -> async_graphql::Result<Option<Arc<Value>>>` named by `field_name`, a `{Loader}Query` object exposing that resolver as a field, and registration of the request [`ContextCache`](deque_loader::request::ContextCache) for use with `insert_loader_caches`. Requires the `graphql-dataloader` feature of deque-loader
+#[proc_macro_attribute]
+pub fn graphql_loader(
+  attr: proc_macro::TokenStream,
+  item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+  let args = parse_macro_input!(attr as AttributeArgs);
+  let item = parse_macro_input!(item as ItemStruct);
+
+  let GraphqlLoaderAttr { field_name } = match GraphqlLoaderAttr::from_list(&args) {
+    Ok(attr) => attr,
+    Err(err) => return proc_macro::TokenStream::from(err.write_errors()),
+  };
+
+  let loader = &item.ident;
+  let vis = &item.vis;
+  let query = format_ident!("{}Query", loader);
+  let field_name = format_ident!(
+    "{}",
+    field_name.unwrap_or_else(|| String::from("load_by_id"))
+  );
+
+  let handler = quote! {
+    <#loader as deque_loader::loader::LocalLoader<deque_loader::loader::DataStore>>::Handler
+  };
+
+  let expanded = quote! {
+    #item
+
+    #[deque_loader::async_trait::async_trait]
+    impl async_graphql::dataloader::Loader<<#handler as deque_loader::task::TaskHandler>::Key> for #loader {
+      type Value = std::sync::Arc<<#handler as deque_loader::task::TaskHandler>::Value>;
+      type Error = <#handler as deque_loader::task::TaskHandler>::Error;
+
+      async fn load(
+        &self,
+        keys: &[<#handler as deque_loader::task::TaskHandler>::Key],
+      ) -> Result<std::collections::HashMap<<#handler as deque_loader::task::TaskHandler>::Key, Self::Value>, Self::Error> {
+        deque_loader::graphql::load_many::<#loader>(keys).await
+      }
+    }
+
+    impl #loader {
+      pub async fn #field_name(
+        ctx: &async_graphql::Context<'_>,
+        id: <#handler as deque_loader::task::TaskHandler>::Key,
+      ) -> async_graphql::Result<Option<std::sync::Arc<<#handler as deque_loader::task::TaskHandler>::Value>>> {
+        deque_loader::graphql::load_by_context::<#loader>(ctx, id).await
+      }
+    }
+
+    #[derive(Default)]
+    #vis struct #query;
+
+    #[async_graphql::Object]
+    impl #query {
+      async fn #field_name(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        id: <#handler as deque_loader::task::TaskHandler>::Key,
+      ) -> async_graphql::Result<Option<std::sync::Arc<<#handler as deque_loader::task::TaskHandler>::Value>>> {
+        #loader::#field_name(ctx, id).await
+      }
+    }
+
+    deque_loader::register_cache_factory!(#handler);
+  };
+
+  proc_macro::TokenStream::from(expanded)
+}
+
+#[derive(FromMeta)]
+struct TaskHandlerDecoratorAttr {
+  handle_task: Option<syn::Path>,
+}
+
+/// Implement [`TaskHandler`](deque_loader::task::TaskHandler) and [`LocalLoader`](deque_loader::loader::LocalLoader) for a tuple struct wrapping another task handler as its only field, forwarding every associated type, constant and function to the inner handler. Tasks are handled by the async fn given as `handle_task`, such as `handle_task = "Self::handle_logged"`, and otherwise by the inner handler
+#[proc_macro_attribute]
+pub fn task_handler_decorator(
+  attr: proc_macro::TokenStream,
+  item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+  let args = parse_macro_input!(attr as AttributeArgs);
+  let item = parse_macro_input!(item as ItemStruct);
+
+  let TaskHandlerDecoratorAttr { handle_task } = match TaskHandlerDecoratorAttr::from_list(&args) {
+    Ok(attr) => attr,
+    Err(err) => return proc_macro::TokenStream::from(err.write_errors()),
+  };
+
+  let inner = match &item.fields {
+    syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+    _ => {
+      return proc_macro::TokenStream::from(
+        syn::Error::new(
+          item.span(),
+          "task_handler_decorator expects a tuple struct wrapping a TaskHandler",
+        )
+        .to_compile_error(),
+      );
+    }
+  };
+
+  let ident = &item.ident;
+  let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+
+  let handle_task = match handle_task {
+    Some(handle_task) => quote! { #handle_task(task).await },
+    None => quote! { <#inner as deque_loader::task::TaskHandler>::handle_task(task).await },
+  };
+
+  let mut store_generics = item.generics.clone();
+  store_generics
+    .params
+    .push(syn::parse_quote!(DequeLoaderStore: deque_loader::loader::StoreType));
+  store_generics
+    .make_where_clause()
+    .predicates
+    .push(syn::parse_quote!(#inner: deque_loader::loader::LocalLoader<DequeLoaderStore>));
+  let (store_impl_generics, _, store_where_clause) = store_generics.split_for_impl();
+
+  let handler = quote! { <#inner as deque_loader::task::TaskHandler> };
+
+  let expanded = quote! {
+    #item
+
+    #[deque_loader::async_trait::async_trait]
+    impl #impl_generics deque_loader::task::TaskHandler for #ident #ty_generics #where_clause {
+      type Key = #handler::Key;
+      type Value = #handler::Value;
+      type Error = #handler::Error;
+      deque_loader::forward_task_handler!(#inner);
+
+      async fn handle_task(
+        task: deque_loader::task::Task<deque_loader::task::PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+      ) -> deque_loader::task::Task<deque_loader::task::CompletionReceipt> {
+        #handle_task
+      }
+    }
+
+    impl #store_impl_generics deque_loader::loader::LocalLoader<DequeLoaderStore> for #ident #ty_generics #store_where_clause {
+      type Handler = <#inner as deque_loader::loader::LocalLoader<DequeLoaderStore>>::Handler;
+      fn loader() -> &'static std::thread::LocalKey<deque_loader::loader::DataLoader<Self::Handler>> {
+        <#inner as deque_loader::loader::LocalLoader<DequeLoaderStore>>::loader()
+      }
+    }
+  };
+
+  proc_macro::TokenStream::from(expanded)
+}
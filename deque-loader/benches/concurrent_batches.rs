@@ -0,0 +1,76 @@
+//! Compares handling each batch as a whole against splitting it into concurrently handled sub-batches via `ConcurrentBatchLoader`, against a simulated backend whose latency grows with the number of keys queried. Run with `cargo bench --bench concurrent_batches`
+use deque_loader::{
+  batch::{BatchHandler, BatchLoader},
+  concurrent::ConcurrentBatchLoader,
+  loader::DataLoader,
+  task::TaskHandler,
+};
+use std::{
+  collections::HashMap,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 20;
+
+// Simulated query latency per key, as of a backend scanning rows sequentially
+const LATENCY_PER_KEY: Duration = Duration::from_micros(20);
+
+#[derive(Debug, Clone)]
+pub struct Unreachable;
+
+impl std::fmt::Display for Unreachable {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("unreachable")
+  }
+}
+
+impl std::error::Error for Unreachable {}
+
+pub struct SimulatedLoader;
+
+#[async_trait::async_trait]
+impl BatchLoader for SimulatedLoader {
+  type Key = i32;
+  type Value = i32;
+  type Error = Unreachable;
+
+  async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, Unreachable> {
+    tokio::time::sleep(LATENCY_PER_KEY * keys.len() as u32).await;
+
+    Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+  }
+}
+
+async fn bench<T: TaskHandler<Key = i32, Value = i32>>(size: i32) -> Duration {
+  let loader: DataLoader<T> = DataLoader::default();
+  let start = Instant::now();
+
+  for _ in 0..ITERATIONS {
+    let receivers: Vec<_> = (0..size).map(|key| loader.load_by(key)).collect();
+
+    futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+  }
+
+  start.elapsed() / ITERATIONS
+}
+
+fn main() {
+  let rt = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  rt.block_on(async {
+    for &size in &[10, 100, 1000] {
+      let sequential = bench::<BatchHandler<SimulatedLoader>>(size).await;
+      let two = bench::<ConcurrentBatchLoader<BatchHandler<SimulatedLoader>>>(size).await;
+      let four = bench::<ConcurrentBatchLoader<BatchHandler<SimulatedLoader>, 4>>(size).await;
+
+      println!(
+        "{:>4} keys: sequential {:>10?}  2 concurrent {:>10?}  4 concurrent {:>10?}",
+        size, sequential, two, four
+      );
+    }
+  });
+}
@@ -0,0 +1,102 @@
+//! Compares the latency of loads by a `DieselLoader` that acquires a connection per batch against one pipelining batches over a shared connection, under a moderate load of concurrent small batches against a pool of few connections. Requires a Postgres database; run with `DATABASE_URL=postgresql://localhost:5432 MAX_DB_CONNECTIONS=4 cargo bench --bench diesel_pipeline`
+use deque_loader::{
+  diesel::{DieselError, DieselHandler, DieselLoader, PipelineConnection},
+  diesel_connection::PooledConnection,
+  loader::DataLoader,
+};
+use diesel::{connection::SimpleConnection, PgConnection};
+use std::{
+  collections::HashMap,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+const ROUNDS: usize = 200;
+const CONCURRENT_LOADS: i32 = 32;
+
+fn query(conn: &mut PgConnection, keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+  conn.batch_execute("SELECT 1")?;
+
+  Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+}
+
+pub struct PerBatchLoader;
+
+impl DieselLoader for PerBatchLoader {
+  type Key = i32;
+  type Value = i32;
+  const MAX_BATCH_SIZE: Option<usize> = Some(4);
+
+  fn load(
+    mut conn: PooledConnection,
+    keys: Vec<i32>,
+  ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+    query(&mut conn, keys)
+  }
+}
+
+pub struct PipelinedLoader;
+
+impl DieselLoader for PipelinedLoader {
+  type Key = i32;
+  type Value = i32;
+  const MAX_BATCH_SIZE: Option<usize> = Some(4);
+  const PIPELINE_DEPTH: usize = 4;
+
+  fn load(
+    mut conn: PooledConnection,
+    keys: Vec<i32>,
+  ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+    query(&mut conn, keys)
+  }
+
+  fn load_pipelined(
+    conn: &mut PipelineConnection,
+    keys: Vec<i32>,
+  ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+    query(conn.get()?, keys)
+  }
+}
+
+async fn bench<T: DieselLoader<Key = i32, Value = i32>>() -> (Duration, Duration) {
+  let loader: DataLoader<DieselHandler<T>> = DataLoader::default();
+  let mut latencies = Vec::with_capacity(ROUNDS * CONCURRENT_LOADS as usize);
+
+  for _ in 0..ROUNDS {
+    let receivers: Vec<_> = (0..CONCURRENT_LOADS)
+      .map(|key| (Instant::now(), loader.load_by(key)))
+      .collect();
+
+    for (start, rx) in receivers {
+      rx.recv().await.unwrap();
+      latencies.push(start.elapsed());
+    }
+  }
+
+  latencies.sort();
+
+  (
+    latencies[latencies.len() / 2],
+    latencies[latencies.len() * 99 / 100],
+  )
+}
+
+fn main() {
+  if std::env::var("DATABASE_URL").is_err() {
+    println!("DATABASE_URL is unset; skipping");
+    return;
+  }
+
+  let rt = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  rt.block_on(async {
+    let (p50, p99) = bench::<PerBatchLoader>().await;
+    println!("connection per batch: p50 {:>10?}  p99 {:>10?}", p50, p99);
+
+    let (p50, p99) = bench::<PipelinedLoader>().await;
+    println!("pipeline depth of 4:  p50 {:>10?}  p99 {:>10?}", p50, p99);
+  });
+}
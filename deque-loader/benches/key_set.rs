@@ -0,0 +1,43 @@
+//! Compares deduplicating batch keys with a `HashSet` against a `KeySet`. Run with `cargo bench --bench key_set`
+use deque_loader::KeySet;
+use std::{
+  collections::HashSet,
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 10_000;
+
+// Half of the keys of each batch are duplicates, as when many resolvers load the same rows
+fn batch(size: usize) -> Vec<i32> {
+  (0..size).map(|i| (i / 2) as i32).rev().collect()
+}
+
+fn bench<F: Fn(&[i32]) -> usize>(keys: &[i32], f: F) -> Duration {
+  let start = Instant::now();
+
+  for _ in 0..ITERATIONS {
+    black_box(f(black_box(keys)));
+  }
+
+  start.elapsed() / ITERATIONS
+}
+
+fn main() {
+  for &size in &[5, 10, 50, 500] {
+    let keys = batch(size);
+
+    let hash_set = bench(&keys, |keys| {
+      keys.iter().cloned().collect::<HashSet<_>>().len()
+    });
+
+    let key_set = bench(&keys, |keys| {
+      keys.iter().cloned().collect::<KeySet<_>>().len()
+    });
+
+    println!(
+      "batch size {:>3}: HashSet {:>10?}  KeySet {:>10?}",
+      size, hash_set, key_set
+    );
+  }
+}
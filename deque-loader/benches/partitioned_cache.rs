@@ -0,0 +1,80 @@
+//! Compares lookups of a single `ContextCache` shared by many threads against a `PartitionedCache` of 16 shards. Every key is warmed ahead of time, so that lookups measure contention on the cache rather than loading. Run with `cargo bench --bench partitioned_cache`
+use deque_loader::{
+  loader::DataLoader,
+  request::{ContextCache, KeyedCache, PartitionedCache},
+  task::{CompletionReceipt, PendingAssignment, Task, TaskHandler},
+};
+use std::{
+  hint::black_box,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+const OPERATIONS: usize = 1_000_000;
+const KEYS: i32 = 10_000;
+
+pub struct WarmedLoader;
+
+#[derive(Debug, Clone)]
+pub struct Unreachable;
+
+impl std::fmt::Display for Unreachable {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("unreachable")
+  }
+}
+
+impl std::error::Error for Unreachable {}
+
+#[async_trait::async_trait]
+impl TaskHandler for WarmedLoader {
+  type Key = i32;
+  type Value = i32;
+  type Error = Unreachable;
+
+  async fn handle_task(
+    _task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    unreachable!("every key is warmed")
+  }
+}
+
+fn bench<C: KeyedCache<WarmedLoader> + Send + Sync>(cache: &C, threads: usize) -> Duration {
+  let ops_per_thread = OPERATIONS / threads;
+  let start = Instant::now();
+
+  std::thread::scope(|scope| {
+    for thread in 0..threads {
+      scope.spawn(move || {
+        let loader: DataLoader<WarmedLoader> = DataLoader::default();
+
+        for i in 0..ops_per_thread {
+          let key = ((thread * ops_per_thread + i) % KEYS as usize) as i32;
+          black_box(loader.cached_load_by(key, cache));
+        }
+      });
+    }
+  });
+
+  start.elapsed()
+}
+
+fn main() {
+  let context_cache: ContextCache<WarmedLoader> = ContextCache::new();
+  let partitioned_cache: PartitionedCache<WarmedLoader, 16> = PartitionedCache::new();
+
+  for key in 0..KEYS {
+    context_cache.warm(key, Arc::new(key));
+    partitioned_cache.warm(key, Arc::new(key));
+  }
+
+  for &threads in &[1, 4, 8, 16] {
+    let single = bench(&context_cache, threads);
+    let partitioned = bench(&partitioned_cache, threads);
+
+    println!(
+      "{:>2} threads: ContextCache {:>10?}  PartitionedCache<16> {:>10?}",
+      threads, single, partitioned
+    );
+  }
+}
@@ -0,0 +1,191 @@
+//! Overload detection for upstream code, such that callers can shed load rather than queue loads without bound
+//!
+//! ```rust
+//! let signal = UserLoader::loader().with(|loader| {
+//!   loader.set_backpressure(1024, 256);
+//!   loader.backpressure_signal()
+//! });
+//!
+//! if signal.is_overloaded() {
+//!   return Err(StatusCode::SERVICE_UNAVAILABLE);
+//! }
+//! ```
+use std::sync::{
+  atomic::{AtomicBool, AtomicUsize, Ordering},
+  Arc,
+};
+use tokio::sync::watch;
+
+/// A handle on whether a [`crate::loader::DataLoader`] is overloaded, as per the water marks of [`crate::loader::DataLoader::set_backpressure`]. A loader becomes overloaded once its loads pending resolution reach the high water mark, and remains so until they fall to the low water mark
+#[derive(Clone)]
+pub struct BackpressureSignal {
+  backpressure: Arc<Backpressure>,
+  rx: watch::Receiver<bool>,
+}
+
+impl BackpressureSignal {
+  /// Whether the loader is overloaded, as a lock-free atomic read
+  pub fn is_overloaded(&self) -> bool {
+    self.backpressure.overloaded.load(Ordering::Acquire)
+  }
+
+  /// Wait until the loader is no longer overloaded, returning immediately if it isn't
+  pub async fn wait_until_ready(&self) {
+    let mut rx = self.rx.clone();
+
+    // The sender lives as long as this signal, and so waiting can't fail
+    rx.wait_for(|overloaded| !overloaded).await.ok();
+  }
+}
+
+// Counts loads pending resolution, transitioning between overloaded and ready upon crossing water marks. Transitions are published by reading the atomic state while holding the watch lock, such that the last publish sees the latest state however transitions of concurrent threads interleave. Loads are only counted once water marks are set, and until then the loader is never overloaded
+pub(crate) struct Backpressure {
+  pending: AtomicUsize,
+  high_water_mark: AtomicUsize,
+  low_water_mark: AtomicUsize,
+  overloaded: AtomicBool,
+  tx: watch::Sender<bool>,
+}
+
+impl Default for Backpressure {
+  fn default() -> Self {
+    let (tx, _) = watch::channel(false);
+
+    Backpressure {
+      pending: AtomicUsize::new(0),
+      high_water_mark: AtomicUsize::new(usize::MAX),
+      low_water_mark: AtomicUsize::new(0),
+      overloaded: AtomicBool::new(false),
+      tx,
+    }
+  }
+}
+
+impl Backpressure {
+  pub(crate) fn set_water_marks(&self, high_water_mark: usize, low_water_mark: usize) {
+    self.low_water_mark.store(low_water_mark, Ordering::Release);
+    self
+      .high_water_mark
+      .store(high_water_mark, Ordering::Release);
+
+    // Loads already pending may have crossed the new water marks
+    let pending = self.pending.load(Ordering::Acquire);
+
+    if pending >= high_water_mark {
+      self.transition(true);
+    } else if pending <= low_water_mark {
+      self.transition(false);
+    }
+  }
+
+  pub(crate) fn is_enabled(&self) -> bool {
+    self.high_water_mark.load(Ordering::Acquire) != usize::MAX
+  }
+
+  pub(crate) fn signal(self: &Arc<Self>) -> BackpressureSignal {
+    BackpressureSignal {
+      backpressure: self.clone(),
+      rx: self.tx.subscribe(),
+    }
+  }
+
+  // Track a load until the returned guard is dropped upon the load resolving
+  pub(crate) fn enqueued(self: &Arc<Self>) -> PendingLoad {
+    let pending = self.pending.fetch_add(1, Ordering::AcqRel) + 1;
+
+    if pending >= self.high_water_mark.load(Ordering::Acquire) {
+      self.transition(true);
+    }
+
+    PendingLoad(self.clone())
+  }
+
+  fn transition(&self, overloaded: bool) {
+    if self
+      .overloaded
+      .compare_exchange(!overloaded, overloaded, Ordering::AcqRel, Ordering::Acquire)
+      .is_ok()
+    {
+      self
+        .tx
+        .send_modify(|state| *state = self.overloaded.load(Ordering::Acquire));
+    }
+  }
+}
+
+/// A load counted towards the backpressure of its loader until dropped
+pub struct PendingLoad(Arc<Backpressure>);
+
+impl Drop for PendingLoad {
+  fn drop(&mut self) {
+    let pending = self.0.pending.fetch_sub(1, Ordering::AcqRel) - 1;
+
+    if pending <= self.0.low_water_mark.load(Ordering::Acquire) {
+      self.0.transition(false);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::loader::{DataStore, LocalLoader};
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use std::{collections::HashMap, sync::Arc};
+  use tokio::sync::Notify;
+
+  static RELEASE: Notify = Notify::const_new();
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<GatedLoader>")]
+  pub struct GatedLoader;
+
+  #[async_trait::async_trait]
+  impl MockBackend for GatedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn before_load(_keys: &[i32]) {
+      RELEASE.notified().await;
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_signals_overload_until_below_low_water_mark() {
+    let loader = <GatedLoader as LocalLoader<DataStore>>::loader();
+
+    // Signals obtained before water marks are set observe them once set
+    let signal = loader.with(|loader| loader.backpressure_signal());
+    loader.with(|loader| loader.set_backpressure(4, 1));
+
+    let mut receivers: Vec<_> = (0..3)
+      .map(|key| loader.with(|loader| loader.load_by(key)))
+      .collect();
+    assert!(!signal.is_overloaded());
+
+    receivers.push(loader.with(|loader| loader.load_by(3)));
+    assert!(signal.is_overloaded());
+
+    let ready = tokio::spawn({
+      let signal = signal.clone();
+      async move { signal.wait_until_ready().await }
+    });
+
+    tokio::task::yield_now().await;
+    assert!(!ready.is_finished());
+
+    RELEASE.notify_one();
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    ready.await.unwrap();
+    assert!(!signal.is_overloaded());
+  }
+}
@@ -1,6 +1,9 @@
 use crate::{
   loader::{DataLoader, LocalLoader, StoreType},
-  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+  task::{
+    task_handler_defaults, CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskError,
+    TaskHandler,
+  },
   Key,
 };
 use std::{collections::HashMap, sync::Arc};
@@ -10,9 +13,9 @@ use std::{collections::HashMap, sync::Arc};
 pub trait BatchLoader: Sized + Send + Sync + 'static {
   type Key: Key;
   type Value: Send + Sync + Clone + 'static;
-  type Error: Send + Sync + Clone + 'static;
-  const CORES_PER_WORKER_GROUP: usize = 4;
-  const MAX_BATCH_SIZE: Option<usize> = None;
+  type Error: TaskError;
+  task_handler_defaults!(Self::Error);
+
   async fn load(keys: Vec<Self::Key>) -> Result<HashMap<Self::Key, Arc<Self::Value>>, Self::Error>;
 }
 
@@ -26,13 +29,17 @@ where
   type Key = T::Key;
   type Value = T::Value;
   type Error = T::Error;
-  const CORES_PER_WORKER_GROUP: usize = T::CORES_PER_WORKER_GROUP;
-  const MAX_BATCH_SIZE: Option<usize> = T::MAX_BATCH_SIZE;
+  crate::forward_task_handler!(T);
 
   async fn handle_task(
     task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
   ) -> Task<CompletionReceipt> {
-    match task.get_assignment::<Self>().await {
+    let assignment = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.validate::<Self>(),
+      assignment => assignment,
+    };
+
+    match assignment {
       TaskAssignment::LoadBatch(task) => {
         let keys = task.keys();
         let result = T::load(keys).await;
@@ -57,6 +64,7 @@ where
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::testing::TestError;
   use crate::LoadBy;
   use deque_loader_derive::{Loadable, Loader};
   use std::{collections::HashMap, iter};
@@ -74,7 +82,7 @@ mod tests {
   impl BatchLoader for BatchSizeLoader {
     type Key = i32;
     type Value = BatchSize;
-    type Error = ();
+    type Error = TestError;
     async fn load(
       keys: Vec<Self::Key>,
     ) -> Result<HashMap<Self::Key, Arc<Self::Value>>, Self::Error> {
@@ -87,7 +95,7 @@ mod tests {
   }
 
   #[tokio::test]
-  async fn it_loads() -> Result<(), ()> {
+  async fn it_loads() -> Result<(), TestError> {
     let data = BatchSize::load_by(1_i32).await?;
 
     assert!(data.is_some());
@@ -96,7 +104,7 @@ mod tests {
   }
 
   #[tokio::test]
-  async fn it_auto_batches() -> Result<(), ()> {
+  async fn it_auto_batches() -> Result<(), TestError> {
     let a = BatchSize::load_by(2_i32);
 
     let b = BatchSize::load_by(3_i32);
@@ -0,0 +1,224 @@
+use crate::{key::Key, request::Request};
+use std::collections::HashMap;
+
+/// Requests partitioned into batches such that each bucket respects a given budget. Requests sharing a key are always kept within the same bucket so that deduplication by key is preserved
+pub(crate) struct RequestBuckets<
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+> {
+  buckets: Vec<Vec<Request<K, V, E>>>,
+}
+
+impl<K, V, E> RequestBuckets<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  pub(crate) fn new(requests: Vec<Request<K, V, E>>) -> Self {
+    RequestBuckets {
+      buckets: vec![requests],
+    }
+  }
+
+  /// Split buckets such that no bucket contains more than `max_batch_size` unique keys
+  pub(crate) fn split_by_size(self, max_batch_size: usize) -> Self {
+    self.split(max_batch_size, |_| 1)
+  }
+
+  /// Split buckets such that the estimated size of unique keys in each bucket does not exceed `max_batch_bytes`. A key whose size alone exceeds the budget is placed within a bucket of its own
+  pub(crate) fn split_by_bytes<F>(self, max_batch_bytes: usize, key_size_bytes: F) -> Self
+  where
+    F: Fn(&K) -> usize,
+  {
+    self.split(max_batch_bytes, key_size_bytes)
+  }
+
+  /// Split buckets such that the total weight of unique keys in each bucket does not exceed `max_batch_weight`. A key whose weight alone exceeds the budget is placed within a bucket of its own
+  pub(crate) fn split_by_weight<F>(self, max_batch_weight: usize, key_weight: F) -> Self
+  where
+    F: Fn(&K) -> usize,
+  {
+    self.split(max_batch_weight, key_weight)
+  }
+
+  fn split<F>(self, budget: usize, weight_fn: F) -> Self
+  where
+    F: Fn(&K) -> usize,
+  {
+    let mut buckets: Vec<Vec<Request<K, V, E>>> = vec![];
+
+    for requests in self.buckets.into_iter() {
+      let offset = buckets.len();
+      let mut bucket_index: HashMap<K, usize> = HashMap::new();
+      let mut current_weight = 0;
+
+      for req in requests.into_iter() {
+        if let Some(idx) = bucket_index.get(req.key()) {
+          buckets[*idx].push(req);
+          continue;
+        }
+
+        let weight = weight_fn(req.key());
+
+        if buckets.len().eq(&offset) || current_weight + weight > budget {
+          buckets.push(vec![]);
+          current_weight = 0;
+        }
+
+        current_weight += weight;
+
+        let idx = buckets.len() - 1;
+        bucket_index.insert(req.key().to_owned(), idx);
+        buckets[idx].push(req);
+      }
+    }
+
+    RequestBuckets { buckets }
+  }
+}
+
+impl<K, V, E> IntoIterator for RequestBuckets<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  type Item = Vec<Request<K, V, E>>;
+  type IntoIter = std::vec::IntoIter<Vec<Request<K, V, E>>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.buckets.into_iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loadable::LoadBy;
+  use crate::testing::{BatchRecorder, MockBackend, MockHandler, TestError};
+  use deque_loader_derive::{Loadable, Loader};
+  use std::{collections::HashMap, iter, sync::Arc};
+
+  fn requests(keys: &[&str]) -> Vec<Request<String, (), TestError>> {
+    keys
+      .iter()
+      .map(|key| Request::new_oneshot(key.to_string()).0)
+      .collect()
+  }
+
+  fn bucket_keys(buckets: RequestBuckets<String, (), TestError>) -> Vec<Vec<String>> {
+    buckets
+      .into_iter()
+      .map(|bucket| bucket.iter().map(|req| req.key().to_owned()).collect())
+      .collect()
+  }
+
+  #[test]
+  fn it_splits_by_size() {
+    let buckets = RequestBuckets::new(requests(&["a", "b", "c", "a", "d"])).split_by_size(2);
+
+    assert_eq!(
+      bucket_keys(buckets),
+      vec![vec!["a", "b", "a"], vec!["c", "d"]]
+    );
+  }
+
+  #[test]
+  fn it_splits_by_bytes() {
+    let buckets = RequestBuckets::new(requests(&["aaaa", "bb", "cccccc", "d", "bb", "eeeeeeeeee"]))
+      .split_by_bytes(7, |key| key.len());
+
+    assert_eq!(
+      bucket_keys(buckets),
+      vec![
+        vec!["aaaa", "bb", "bb"],
+        vec!["cccccc", "d"],
+        vec!["eeeeeeeeee"]
+      ]
+    );
+  }
+
+  #[test]
+  fn it_splits_by_weight() {
+    let weight = |key: &String| match key.as_str() {
+      "popular" => 10_000,
+      "active" => 500,
+      _ => 1,
+    };
+
+    let buckets = RequestBuckets::new(requests(&[
+      "a", "active", "b", "popular", "active", "c", "active2", "d",
+    ]))
+    .split_by_weight(1_000, weight);
+
+    let buckets = bucket_keys(buckets);
+
+    assert_eq!(
+      buckets,
+      vec![
+        vec!["a", "active", "b", "active"],
+        vec!["popular"],
+        vec!["c", "active2", "d"]
+      ]
+    );
+
+    for bucket in buckets.iter().filter(|bucket| bucket.len().gt(&1)) {
+      let keys: std::collections::HashSet<&String> = bucket.iter().collect();
+      assert!(keys.into_iter().map(weight).sum::<usize>().le(&1_000));
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<ByteBudgetLoader>")]
+  pub struct ByteBudgetLoader {}
+
+  #[derive(Clone, Debug, PartialEq, Eq, Loadable)]
+  #[data_loader(handler = "MockHandler<ByteBudgetLoader>")]
+  pub struct BatchBytes(usize);
+
+  impl MockBackend for ByteBudgetLoader {
+    type Key = String;
+    type Value = BatchBytes;
+    type Error = TestError;
+    const MAX_BATCH_BYTES: Option<usize> = Some(16);
+
+    fn key_size_bytes(key: &Self::Key) -> usize {
+      key.len()
+    }
+
+    fn load(keys: &[String]) -> Result<HashMap<String, Arc<BatchBytes>>, TestError> {
+      let bytes = Arc::new(BatchBytes(keys.iter().map(String::len).sum()));
+
+      Ok(keys.iter().cloned().zip(iter::repeat(bytes)).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_respects_byte_budget() -> Result<(), TestError> {
+    let recorder = BatchRecorder::<ByteBudgetLoader>::start();
+    let keys = ["a", "bbbbbbbb", "ccc", "dddddddddddd", "ee", "ffffff", "g"];
+
+    let results = futures_util::future::try_join_all(
+      keys.iter().map(|key| BatchBytes::load_by(key.to_string())),
+    )
+    .await?;
+
+    for result in results.iter() {
+      assert!(result.as_ref().unwrap().0.le(&16));
+    }
+
+    let batch_bytes: Vec<usize> = recorder
+      .batches()
+      .iter()
+      .map(|keys| keys.iter().map(String::len).sum())
+      .collect();
+
+    assert!(batch_bytes.len().gt(&1));
+    assert!(batch_bytes.iter().all(|bytes| bytes.le(&16)));
+    assert_eq!(batch_bytes.iter().sum::<usize>(), 33);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,111 @@
+use crate::{
+  loader::{DataLoader, LocalLoader, StoreType},
+  task::{CompletionReceipt, PendingAssignment, Task, TaskHandler},
+};
+
+/// A [`TaskHandler`] wrapper that splits each batch into `N` sub-batches, 2 by default, of roughly equal numbers of unique keys and handles them concurrently with the inner handler, for backends that benefit from concurrent queries such as with a transaction per batch. Requests sharing a key are kept within the same sub-batch, and every sub-batch completes before the batch does
+///
+/// ```rust
+/// #[derive(Loader)]
+/// #[data_loader(handler = "ConcurrentBatchLoader<BatchHandler<UserLoader>, 4>")]
+/// pub struct UserLoader;
+///
+/// #[async_trait::async_trait]
+/// impl BatchLoader for UserLoader {
+///   type Key = i32;
+///   type Value = User;
+///   type Error = ApiError;
+///
+///   async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<User>>, ApiError> {
+///     api::get_users(keys).await
+///   }
+/// }
+/// ```
+pub struct ConcurrentBatchLoader<T: TaskHandler, const N: usize = 2>(T);
+
+#[async_trait::async_trait]
+impl<T, const N: usize> TaskHandler for ConcurrentBatchLoader<T, N>
+where
+  T: TaskHandler,
+{
+  type Key = T::Key;
+  type Value = T::Value;
+  type Error = T::Error;
+  crate::forward_task_handler!(T);
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let tasks = task.split_into(N).await;
+
+    futures_util::future::join_all(tasks.into_iter().map(T::handle_task)).await;
+
+    Task::completion_receipt()
+  }
+}
+
+impl<Loader, Store, const N: usize> LocalLoader<Store> for ConcurrentBatchLoader<Loader, N>
+where
+  Loader: TaskHandler + LocalLoader<Store>,
+  Store: StoreType,
+{
+  type Handler = <Loader as LocalLoader<Store>>::Handler;
+  fn loader() -> &'static std::thread::LocalKey<DataLoader<Self::Handler>> {
+    Loader::loader()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{BatchRecorder, MockBackend, MockHandler, TestError};
+  use std::{collections::HashMap, sync::Arc, time::Duration};
+  use tokio::time::Instant;
+
+  pub struct SleepyLoader;
+
+  #[async_trait::async_trait]
+  impl MockBackend for SleepyLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn before_load(_keys: &[i32]) {
+      tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_handles_sub_batches_concurrently() {
+    tokio::time::pause();
+
+    let recorder = BatchRecorder::<SleepyLoader>::start();
+    let loader: DataLoader<ConcurrentBatchLoader<MockHandler<SleepyLoader>, 4>> =
+      DataLoader::default();
+
+    let start = Instant::now();
+
+    let receivers: Vec<_> = vec![1, 2, 3, 4, 4, 5, 6, 7, 8]
+      .into_iter()
+      .map(|key| loader.load_by(key))
+      .collect();
+
+    let results = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert!(recorder.batches().iter().map(Vec::len).eq([2, 2, 2, 2]));
+    assert_eq!(
+      results,
+      vec![1, 2, 3, 4, 4, 5, 6, 7, 8]
+        .into_iter()
+        .map(|key| Ok(Some(Arc::new(key))))
+        .collect::<Vec<_>>()
+    );
+
+    tokio::time::resume();
+  }
+}
@@ -0,0 +1,302 @@
+//! Runtime settings of a [`DataLoader`] gathered into one struct, such as for applications configured via environment variables
+//!
+//! ```rust
+//! let config = LoaderConfig::from_env()?.batch_stats(true);
+//!
+//! UserLoader::loader().with(|loader| loader.apply_config(&config));
+//! ```
+//!
+//! [`LoaderConfig::from_env`] reads the following variables, leaving settings of those unset at their defaults:
+//!
+//! | Variable | Setting |
+//! |---|---|
+//! | `DEQUE_LOADER_WORKER_STARTUP_TIMEOUT_MS` | [`DataLoader::set_worker_startup_timeout`] in milliseconds |
+//! | `DEQUE_LOADER_IN_FLIGHT_DEDUPLICATION` | [`DataLoader::enable_in_flight_deduplication`], `true` or `false` |
+//! | `DEQUE_LOADER_BACKPRESSURE_HIGH_WATER_MARK` | [`DataLoader::set_backpressure`], set together with the low water mark |
+//! | `DEQUE_LOADER_BACKPRESSURE_LOW_WATER_MARK` | [`DataLoader::set_backpressure`], set together with the high water mark |
+//! | `DEQUE_LOADER_DEBUG_LOGGING` | [`DataLoader::set_debug_logging`], `true` or `false` |
+//! | `DEQUE_LOADER_QUEUED_KEY_TRACKING` | [`DataLoader::enable_queued_key_tracking`], `true` or `false` |
+//! | `DEQUE_LOADER_BATCH_STATS` | [`DataLoader::enable_batch_stats`], `true` or `false` |
+//! | `DEQUE_LOADER_PREEMPTIVE_LOADING_TTL_MS` | [`DataLoader::enable_preemptive_loading`] in milliseconds |
+//!
+//! Use [`LoaderConfig::from_env_prefixed`] to configure loaders individually under a prefix other than `DEQUE_LOADER`. There are no variables for batch size nor batch latency: batch sizes are the compile-time constants of [`TaskHandler`], such as [`TaskHandler::MAX_BATCH_SIZE`], and batches are dispatched as soon as a task handler begins assignment rather than after a fixed latency
+use crate::{loader::DataLoader, task::TaskHandler};
+use std::{env, fmt::Debug, str::FromStr, time::Duration};
+use thiserror::Error;
+
+/// Prefix of the environment variables read by [`LoaderConfig::from_env`]
+pub const ENV_PREFIX: &str = "DEQUE_LOADER";
+
+/// An environment variable that couldn't be read as a setting of [`LoaderConfig`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+  #[error("invalid value {value:?} of {var}")]
+  Invalid { var: String, value: String },
+  #[error("{0} is set without {1}")]
+  Missing(String, String),
+  #[error("the low water mark must be below the high water mark")]
+  WaterMarks,
+}
+
+/// Settings of a [`DataLoader`] applied via [`DataLoader::apply_config`]. The default leaves a loader as constructed: every opt-in disabled and no startup timeout
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoaderConfig {
+  pub worker_startup_timeout: Option<Duration>,
+  pub in_flight_deduplication: bool,
+  /// High and low water marks
+  pub backpressure: Option<(usize, usize)>,
+  pub debug_logging: bool,
+  pub queued_key_tracking: bool,
+  pub batch_stats: bool,
+  /// Time to hold each preemptive load awaiting consumption
+  pub preemptive_loading: Option<Duration>,
+}
+
+impl LoaderConfig {
+  pub fn new() -> Self {
+    LoaderConfig::default()
+  }
+
+  /// Read settings from the environment variables prefixed by [`ENV_PREFIX`], as listed in the [module docs](crate::config)
+  pub fn from_env() -> Result<Self, ConfigError> {
+    LoaderConfig::from_env_prefixed(ENV_PREFIX)
+  }
+
+  /// Read settings from the environment variables listed in the [module docs](crate::config), prefixed by `prefix` rather than [`ENV_PREFIX`]
+  pub fn from_env_prefixed(prefix: &str) -> Result<Self, ConfigError> {
+    let defaults = LoaderConfig::default();
+
+    let high_water_mark = env_var::<usize>(prefix, "BACKPRESSURE_HIGH_WATER_MARK")?;
+    let low_water_mark = env_var::<usize>(prefix, "BACKPRESSURE_LOW_WATER_MARK")?;
+
+    let backpressure = match (high_water_mark, low_water_mark) {
+      (Some(high), Some(low)) if low < high => Some((high, low)),
+      (Some(_), Some(_)) => return Err(ConfigError::WaterMarks),
+      (Some(_), None) => {
+        return Err(ConfigError::Missing(
+          env_name(prefix, "BACKPRESSURE_HIGH_WATER_MARK"),
+          env_name(prefix, "BACKPRESSURE_LOW_WATER_MARK"),
+        ))
+      }
+      (None, Some(_)) => {
+        return Err(ConfigError::Missing(
+          env_name(prefix, "BACKPRESSURE_LOW_WATER_MARK"),
+          env_name(prefix, "BACKPRESSURE_HIGH_WATER_MARK"),
+        ))
+      }
+      (None, None) => defaults.backpressure,
+    };
+
+    Ok(LoaderConfig {
+      worker_startup_timeout: env_var(prefix, "WORKER_STARTUP_TIMEOUT_MS")?
+        .map(Duration::from_millis)
+        .or(defaults.worker_startup_timeout),
+      in_flight_deduplication: env_var(prefix, "IN_FLIGHT_DEDUPLICATION")?
+        .unwrap_or(defaults.in_flight_deduplication),
+      backpressure,
+      debug_logging: env_var(prefix, "DEBUG_LOGGING")?.unwrap_or(defaults.debug_logging),
+      queued_key_tracking: env_var(prefix, "QUEUED_KEY_TRACKING")?
+        .unwrap_or(defaults.queued_key_tracking),
+      batch_stats: env_var(prefix, "BATCH_STATS")?.unwrap_or(defaults.batch_stats),
+      preemptive_loading: env_var(prefix, "PREEMPTIVE_LOADING_TTL_MS")?
+        .map(Duration::from_millis)
+        .or(defaults.preemptive_loading),
+    })
+  }
+
+  pub fn worker_startup_timeout(mut self, startup_timeout: Option<Duration>) -> Self {
+    self.worker_startup_timeout = startup_timeout;
+    self
+  }
+
+  pub fn in_flight_deduplication(mut self, enabled: bool) -> Self {
+    self.in_flight_deduplication = enabled;
+    self
+  }
+
+  /// See [`DataLoader::set_backpressure`]
+  pub fn backpressure(mut self, high_water_mark: usize, low_water_mark: usize) -> Self {
+    assert!(
+      low_water_mark < high_water_mark,
+      "the low water mark must be below the high water mark"
+    );
+
+    self.backpressure = Some((high_water_mark, low_water_mark));
+    self
+  }
+
+  pub fn debug_logging(mut self, enabled: bool) -> Self {
+    self.debug_logging = enabled;
+    self
+  }
+
+  pub fn queued_key_tracking(mut self, enabled: bool) -> Self {
+    self.queued_key_tracking = enabled;
+    self
+  }
+
+  pub fn batch_stats(mut self, enabled: bool) -> Self {
+    self.batch_stats = enabled;
+    self
+  }
+
+  pub fn preemptive_loading(mut self, ttl: Option<Duration>) -> Self {
+    self.preemptive_loading = ttl;
+    self
+  }
+}
+
+fn env_name(prefix: &str, name: &str) -> String {
+  format!("{}_{}", prefix, name)
+}
+
+fn env_var<V: FromStr>(prefix: &str, name: &str) -> Result<Option<V>, ConfigError> {
+  let var = env_name(prefix, name);
+
+  match env::var(&var) {
+    Ok(value) => match value.trim().parse() {
+      Ok(parsed) => Ok(Some(parsed)),
+      Err(_) => Err(ConfigError::Invalid { var, value }),
+    },
+    Err(env::VarError::NotPresent) => Ok(None),
+    Err(env::VarError::NotUnicode(value)) => Err(ConfigError::Invalid {
+      var,
+      value: value.to_string_lossy().into_owned(),
+    }),
+  }
+}
+
+impl<T> DataLoader<T>
+where
+  T: TaskHandler,
+{
+  /// Apply each setting of `config` as by its setter. The startup timeout and debug logging are set outright, whereas the other settings are applied only when enabled by `config`, as these can't be disabled once enabled
+  pub fn apply_config(&self, config: &LoaderConfig)
+  where
+    T::Key: Debug,
+  {
+    self.set_worker_startup_timeout(config.worker_startup_timeout);
+    self.set_debug_logging(config.debug_logging);
+
+    if config.in_flight_deduplication {
+      self.enable_in_flight_deduplication();
+    }
+
+    if let Some((high_water_mark, low_water_mark)) = config.backpressure {
+      self.set_backpressure(high_water_mark, low_water_mark);
+    }
+
+    if config.queued_key_tracking {
+      self.enable_queued_key_tracking();
+    }
+
+    if config.batch_stats {
+      self.enable_batch_stats();
+    }
+
+    if let Some(ttl) = config.preemptive_loading {
+      self.enable_preemptive_loading(ttl);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use std::{collections::HashMap, sync::Arc};
+
+  pub struct ConfiguredLoader;
+
+  impl MockBackend for ConfiguredLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  // Each test reads variables of its own prefix, as tests share the environment of the process
+  #[test]
+  fn it_reads_settings_from_env() {
+    env::set_var("CONFIGURED_WORKER_STARTUP_TIMEOUT_MS", "250");
+    env::set_var("CONFIGURED_IN_FLIGHT_DEDUPLICATION", "true");
+    env::set_var("CONFIGURED_BACKPRESSURE_HIGH_WATER_MARK", "1024");
+    env::set_var("CONFIGURED_BACKPRESSURE_LOW_WATER_MARK", "256");
+    env::set_var("CONFIGURED_BATCH_STATS", " true ");
+
+    assert_eq!(
+      LoaderConfig::from_env_prefixed("CONFIGURED"),
+      Ok(
+        LoaderConfig::new()
+          .worker_startup_timeout(Some(Duration::from_millis(250)))
+          .in_flight_deduplication(true)
+          .backpressure(1024, 256)
+          .batch_stats(true)
+      )
+    );
+
+    assert_eq!(
+      LoaderConfig::from_env_prefixed("UNCONFIGURED"),
+      Ok(LoaderConfig::default())
+    );
+  }
+
+  #[test]
+  fn it_rejects_invalid_settings() {
+    env::set_var("INVALID_DEBUG_LOGGING", "yes");
+
+    assert_eq!(
+      LoaderConfig::from_env_prefixed("INVALID"),
+      Err(ConfigError::Invalid {
+        var: "INVALID_DEBUG_LOGGING".into(),
+        value: "yes".into()
+      })
+    );
+
+    env::set_var("PARTIAL_BACKPRESSURE_HIGH_WATER_MARK", "8");
+
+    assert_eq!(
+      LoaderConfig::from_env_prefixed("PARTIAL"),
+      Err(ConfigError::Missing(
+        "PARTIAL_BACKPRESSURE_HIGH_WATER_MARK".into(),
+        "PARTIAL_BACKPRESSURE_LOW_WATER_MARK".into()
+      ))
+    );
+
+    env::set_var("INVERTED_BACKPRESSURE_HIGH_WATER_MARK", "8");
+    env::set_var("INVERTED_BACKPRESSURE_LOW_WATER_MARK", "8");
+
+    assert_eq!(
+      LoaderConfig::from_env_prefixed("INVERTED"),
+      Err(ConfigError::WaterMarks)
+    );
+  }
+
+  #[tokio::test]
+  async fn it_applies_config() {
+    let loader: DataLoader<MockHandler<ConfiguredLoader>> = DataLoader::default();
+    let signal = loader.backpressure_signal();
+
+    loader.apply_config(
+      &LoaderConfig::new()
+        .backpressure(2, 1)
+        .queued_key_tracking(true)
+        .batch_stats(true),
+    );
+
+    let receivers: Vec<_> = (0..2).map(|key| loader.load_by(key)).collect();
+
+    assert!(signal.is_overloaded());
+    assert_eq!(loader.peek_queued_keys(), vec![0, 1]);
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    assert!(!signal.is_overloaded());
+    assert_eq!(loader.batch_stats().batches_dispatched, 1);
+  }
+}
@@ -0,0 +1,409 @@
+//! Task handlers that wrap another [`TaskHandler`] to add behavior around its loads, composing as `LoggingDecorator<RetryDecorator<CachingDecorator<BatchHandler<UserLoader>>>>`. Decorators forward every constant of the handler they wrap, and so are configured by the constants of the innermost handler, whereas settings of a decorator itself are const generics of it such as `RetryDecorator<T, 3, 100>`
+//!
+//! ```rust
+//! #[derive(Loader)]
+//! #[data_loader(handler = "LoggingDecorator<RetryDecorator<BatchHandler<UserLoader>>>")]
+//! pub struct UserLoader;
+//! ```
+//!
+//! Custom decorators derive the forwarding boilerplate via [`crate::task_handler_decorator`]
+//!
+//! ```rust
+//! #[task_handler_decorator(handle_task = "Self::handle_audited")]
+//! pub struct AuditDecorator<T: TaskHandler>(T);
+//!
+//! impl<T: TaskHandler> AuditDecorator<T> {
+//!   async fn handle_audited(
+//!     task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+//!   ) -> Task<CompletionReceipt> {
+//!     audit::record(task.request_count());
+//!     T::handle_task(task).await
+//!   }
+//! }
+//! ```
+use crate::{
+  request::{ContextCache, RecvCancelled, Request},
+  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+  task_handler_decorator,
+};
+use std::{
+  any::{Any, TypeId},
+  collections::HashMap,
+  sync::{Arc, Mutex, OnceLock},
+  time::Duration,
+};
+use swap_queue::Stealer;
+use tokio::time::Instant;
+
+type Outcome<T> = Result<Option<Arc<<T as TaskHandler>::Value>>, <T as TaskHandler>::Error>;
+
+static CACHES: OnceLock<Mutex<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>> = OnceLock::new();
+
+// Load `keys` by the inner handler `T` against requests of its own, so that outcomes can be inspected before resolving the requests of the batch being decorated. Keys the inner handler cancels have no outcome
+async fn load_by_inner<T: TaskHandler>(keys: Vec<T::Key>) -> HashMap<T::Key, Outcome<T>> {
+  let (requests, receivers): (Vec<_>, Vec<_>) = keys
+    .into_iter()
+    .map(|key| {
+      let (req, rx) = Request::new_oneshot(key.clone());
+      (req, (key, rx))
+    })
+    .unzip();
+
+  T::handle_task(Task::new(Stealer::Owner(requests))).await;
+
+  let outcomes = futures_util::future::join_all(
+    receivers
+      .into_iter()
+      .map(|(key, rx)| async move { (key, rx.try_recv().await) }),
+  )
+  .await;
+
+  outcomes
+    .into_iter()
+    .filter_map(|(key, outcome)| match outcome {
+      Ok(outcome) => Some((key, outcome)),
+      Err(RecvCancelled) => None,
+    })
+    .collect()
+}
+
+/// Logs each task handled by the inner handler at debug level along with how long it took, within a tracing span of the inner handler when the `tracing` feature is enabled
+#[task_handler_decorator(handle_task = "Self::handle_logged")]
+pub struct LoggingDecorator<T: TaskHandler>(T);
+
+impl<T> LoggingDecorator<T>
+where
+  T: TaskHandler,
+{
+  async fn handle_logged(
+    task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let handler = tynm::type_name::<T>();
+    let started_at = Instant::now();
+
+    log::debug!("{} handling {} requests", handler, task.request_count());
+
+    let handle_task = T::handle_task(task);
+
+    #[cfg(feature = "tracing")]
+    let handle_task = tracing::Instrument::instrument(
+      handle_task,
+      tracing::debug_span!("handle_task", handler = handler.as_str()),
+    );
+
+    let receipt = handle_task.await;
+
+    log::debug!("{} handled task in {:?}", handler, started_at.elapsed());
+
+    receipt
+  }
+}
+
+/// Reloads keys that failed to load by the inner handler up to `MAX_RETRIES` times, 2 by default, waiting `BACKOFF_MS` milliseconds, 50 by default, before the first retry and doubling the wait with each retry thereafter. Keys still failing resolve as the error of their last attempt. The inner handler loads requests of its own, and so doesn't see metadata attached to the requests of the batch
+#[task_handler_decorator(handle_task = "Self::handle_retried")]
+pub struct RetryDecorator<T: TaskHandler, const MAX_RETRIES: usize = 2, const BACKOFF_MS: u64 = 50>(
+  T,
+);
+
+impl<T, const MAX_RETRIES: usize, const BACKOFF_MS: u64> RetryDecorator<T, MAX_RETRIES, BACKOFF_MS>
+where
+  T: TaskHandler,
+{
+  async fn handle_retried(
+    task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let task = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task,
+      TaskAssignment::NoAssignment(receipt) => return receipt,
+    };
+
+    let mut outcomes = load_by_inner::<T>(task.keys()).await;
+    let mut backoff = Duration::from_millis(BACKOFF_MS);
+
+    for attempt in 1..=MAX_RETRIES {
+      let failed_keys: Vec<T::Key> = outcomes
+        .iter()
+        .filter(|(_, outcome)| outcome.is_err())
+        .map(|(key, _)| key.to_owned())
+        .collect();
+
+      if failed_keys.is_empty() {
+        break;
+      }
+
+      log::debug!(
+        "batch_id={} retrying {} failed keys (attempt {} of {})",
+        task.batch_id(),
+        failed_keys.len(),
+        attempt,
+        MAX_RETRIES
+      );
+
+      tokio::time::sleep(backoff).await;
+      backoff *= 2;
+
+      outcomes.extend(load_by_inner::<T>(failed_keys).await);
+    }
+
+    task.resolve_outcomes(outcomes, T::shutdown_error())
+  }
+}
+
+/// Caches values loaded by the inner handler for the lifetime of the program, resolving cached keys without loading them. Only values found are cached; keys not found and errors are loaded again by the next batch requesting them. Cached values are shared across every thread local loader and can be invalidated via [`CachingDecorator::cache`]
+#[task_handler_decorator(handle_task = "Self::handle_cached")]
+pub struct CachingDecorator<T: TaskHandler>(T);
+
+impl<T> CachingDecorator<T>
+where
+  T: TaskHandler,
+{
+  /// The process-wide cache of values loaded by the inner handler
+  pub fn cache() -> &'static ContextCache<T> {
+    let mut caches = CACHES.get_or_init(Default::default).lock().unwrap();
+
+    let cache = *caches
+      .entry(TypeId::of::<T>())
+      .or_insert_with(|| Box::leak(Box::new(ContextCache::<T>::new())));
+
+    cache.downcast_ref::<ContextCache<T>>().unwrap()
+  }
+
+  async fn handle_cached(
+    task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let task = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task,
+      TaskAssignment::NoAssignment(receipt) => return receipt,
+    };
+
+    let cache = Self::cache();
+
+    let cached: HashMap<T::Key, Arc<T::Value>> = task
+      .keys()
+      .into_iter()
+      .filter_map(|key| match cache.peek(&key) {
+        Some(Ok(Some(value))) => Some((key, value)),
+        _ => None,
+      })
+      .collect();
+
+    let task = match task.apply_partial_results(cached) {
+      TaskAssignment::LoadBatch(task) => task,
+      TaskAssignment::NoAssignment(receipt) => return receipt,
+    };
+
+    let outcomes = load_by_inner::<T>(task.keys()).await;
+
+    for (key, outcome) in outcomes.iter() {
+      if let Ok(Some(value)) = outcome {
+        cache.warm(key.to_owned(), value.to_owned());
+      }
+    }
+
+    task.resolve_outcomes(outcomes, T::shutdown_error())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestError;
+  use crate::{
+    batch::{BatchHandler, BatchLoader},
+    loader::DataLoader,
+  };
+  use std::{sync::atomic::AtomicUsize, sync::atomic::Ordering};
+
+  static LOGGED_BATCHES: AtomicUsize = AtomicUsize::new(0);
+  static FLAKY_ATTEMPTS: Mutex<Vec<Vec<i32>>> = Mutex::new(Vec::new());
+  static FAILING_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+  static CACHED_BATCHES: Mutex<Vec<Vec<i32>>> = Mutex::new(Vec::new());
+  static COMPOSED_BATCHES: Mutex<Vec<Vec<i32>>> = Mutex::new(Vec::new());
+
+  fn load_even(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+    Ok(
+      keys
+        .into_iter()
+        .filter(|key| key % 2 == 0)
+        .map(|key| (key, Arc::new(key)))
+        .collect(),
+    )
+  }
+
+  fn record(keys: &[i32], batches: &Mutex<Vec<Vec<i32>>>) {
+    let mut keys = keys.to_vec();
+    keys.sort_unstable();
+    batches.lock().unwrap().push(keys);
+  }
+
+  pub struct LoggedLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for LoggedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      LOGGED_BATCHES.fetch_add(1, Ordering::SeqCst);
+      load_even(keys)
+    }
+  }
+
+  // Fails batches including key 1 upon the first attempt of each
+  pub struct FlakyLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for FlakyLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      record(&keys, &FLAKY_ATTEMPTS);
+
+      if keys.contains(&1) && FLAKY_ATTEMPTS.lock().unwrap().len() == 1 {
+        Err(TestError("unavailable"))
+      } else {
+        Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+      }
+    }
+  }
+
+  pub struct FailingLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for FailingLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(_keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      FAILING_ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+      Err(TestError("unavailable"))
+    }
+  }
+
+  pub struct CachedLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for CachedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      record(&keys, &CACHED_BATCHES);
+      load_even(keys)
+    }
+  }
+
+  // Fails the first batch, to be retried by the composed decorators
+  pub struct ComposedLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for ComposedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      record(&keys, &COMPOSED_BATCHES);
+
+      if COMPOSED_BATCHES.lock().unwrap().len() == 1 {
+        Err(TestError("unavailable"))
+      } else {
+        load_even(keys)
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_logs_tasks_of_the_inner_handler() {
+    let loader: DataLoader<LoggingDecorator<BatchHandler<LoggedLoader>>> = DataLoader::default();
+
+    let (first, second) = (loader.load_by(1), loader.load_by(2));
+
+    assert_eq!(first.recv().await, Ok(None));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(2))));
+    assert_eq!(LOGGED_BATCHES.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_retries_failed_keys() {
+    let loader: DataLoader<RetryDecorator<BatchHandler<FlakyLoader>>> = DataLoader::default();
+
+    let (first, second) = (loader.load_by(1), loader.load_by(2));
+
+    assert_eq!(first.recv().await, Ok(Some(Arc::new(1))));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(2))));
+
+    assert_eq!(
+      *FLAKY_ATTEMPTS.lock().unwrap(),
+      vec![vec![1, 2], vec![1, 2]]
+    );
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_resolves_the_last_error_once_retries_are_exhausted() {
+    let loader: DataLoader<RetryDecorator<BatchHandler<FailingLoader>, 3, 10>> =
+      DataLoader::default();
+
+    let started_at = Instant::now();
+
+    assert_eq!(
+      loader.load_by(1).recv().await,
+      Err(TestError("unavailable"))
+    );
+    assert_eq!(FAILING_ATTEMPTS.load(Ordering::SeqCst), 4);
+    assert!(started_at.elapsed() >= Duration::from_millis(70));
+  }
+
+  #[tokio::test]
+  async fn it_resolves_cached_keys_without_loading() {
+    let loader: DataLoader<CachingDecorator<BatchHandler<CachedLoader>>> = DataLoader::default();
+
+    let receivers = vec![loader.load_by(1), loader.load_by(2)];
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    let (first, second) = (loader.load_by(2), loader.load_by(4));
+
+    assert_eq!(first.recv().await, Ok(Some(Arc::new(2))));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(4))));
+
+    CachingDecorator::<BatchHandler<CachedLoader>>::cache().invalidate(&4);
+
+    assert_eq!(loader.load_by(4).recv().await, Ok(Some(Arc::new(4))));
+
+    assert_eq!(
+      *CACHED_BATCHES.lock().unwrap(),
+      vec![vec![1, 2], vec![4], vec![4]]
+    );
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_composes_decorators() {
+    let loader: DataLoader<
+      LoggingDecorator<RetryDecorator<CachingDecorator<BatchHandler<ComposedLoader>>>>,
+    > = DataLoader::default();
+
+    let (first, second) = (loader.load_by(1), loader.load_by(2));
+
+    assert_eq!(first.recv().await, Ok(None));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(2))));
+
+    let (first, second) = (loader.load_by(1), loader.load_by(2));
+
+    assert_eq!(first.recv().await, Ok(None));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(2))));
+
+    // The failed batch is retried, and the retried value of key 2 is thereafter cached
+    assert_eq!(
+      *COMPOSED_BATCHES.lock().unwrap(),
+      vec![vec![1, 2], vec![1, 2], vec![1]]
+    );
+  }
+}
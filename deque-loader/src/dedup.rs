@@ -0,0 +1,284 @@
+//! Process-wide deduplication of keys in flight, such that concurrent batches of different worker groups or threads share a single backend load per key. Enabled per thread local loader by [`crate::loader::DataLoader::enable_in_flight_deduplication`]
+//!
+//! Upon assignment, keys already being loaded by another batch are withheld from the batch and resolved as the broadcast of the batch loading them, whereas keys not in flight are registered for the duration of their load. A key resolving before another batch is assigned will be loaded again; this isn't a cache
+use crate::{
+  request::{LoadState, Request},
+  task::TaskHandler,
+  Key,
+};
+use flurry::HashMap;
+use std::{
+  any::{Any, TypeId},
+  sync::{Arc, Mutex, OnceLock, Weak},
+};
+use tokio::sync::watch;
+
+type InFlightMap<K, V, E> = HashMap<K, Weak<watch::Sender<LoadState<V, E>>>>;
+
+static DEDUPLICATORS: OnceLock<
+  Mutex<std::collections::HashMap<TypeId, &'static (dyn Any + Send + Sync)>>,
+> = OnceLock::new();
+
+/// The keys of `T` in flight across every thread local loader
+pub struct RequestDeduplicator<T: TaskHandler> {
+  in_flight: InFlightMap<T::Key, T::Value, T::Error>,
+}
+
+impl<T> RequestDeduplicator<T>
+where
+  T: TaskHandler,
+{
+  pub fn global() -> &'static RequestDeduplicator<T> {
+    let mut deduplicators = DEDUPLICATORS.get_or_init(Default::default).lock().unwrap();
+
+    let deduplicator = *deduplicators.entry(TypeId::of::<T>()).or_insert_with(|| {
+      Box::leak(Box::new(RequestDeduplicator::<T> {
+        in_flight: HashMap::new(),
+      }))
+    });
+
+    deduplicator
+      .downcast_ref::<RequestDeduplicator<T>>()
+      .unwrap()
+  }
+
+  /// Number of keys currently being loaded
+  pub fn in_flight_count(&self) -> usize {
+    let guard = self.in_flight.guard();
+
+    self
+      .in_flight
+      .values(&guard)
+      .filter(|tx| tx.strong_count() > 0)
+      .count()
+  }
+
+  /// Register the keys of `requests` not already in flight, returning the requests to be loaded. Requests for keys in flight, including duplicates of keys registered by this batch, are resolved as the result of the load already in progress, with a single subscription per key
+  pub(crate) fn dedup(
+    &'static self,
+    requests: Vec<Request<T::Key, T::Value, T::Error>>,
+  ) -> Vec<Request<T::Key, T::Value, T::Error>> {
+    let guard = self.in_flight.guard();
+
+    let mut followers: Followers<T::Key, T::Value, T::Error> = std::collections::HashMap::new();
+
+    let requests = requests
+      .into_iter()
+      .filter_map(|mut req| {
+        if let Some((_, reqs)) = followers.get_mut(req.key()) {
+          reqs.push(req);
+          return None;
+        }
+
+        let (tx, registered) = loop {
+          if let Some(in_flight) = self.in_flight.get(req.key(), &guard) {
+            match in_flight.upgrade() {
+              Some(tx) => break (tx, false),
+              None => {
+                // Remove the stale registration, unless since replaced, to then retry
+                self.in_flight.compute_if_present(
+                  req.key(),
+                  |_, tx| tx.upgrade().map(|tx| Arc::downgrade(&tx)),
+                  &guard,
+                );
+                continue;
+              }
+            }
+          }
+
+          let (tx, _) = watch::channel(LoadState::Pending);
+          let tx = Arc::new(tx);
+
+          match self
+            .in_flight
+            .try_insert(req.key().to_owned(), Arc::downgrade(&tx), &guard)
+          {
+            Ok(_) => break (tx, true),
+            Err(_) => continue,
+          }
+        };
+
+        // Later requests of this key within the batch follow the same subscription
+        let rx = tx.subscribe();
+
+        if registered {
+          req.set_in_flight(InFlight {
+            key: req.key().to_owned(),
+            tx,
+            in_flight: &self.in_flight,
+          });
+
+          followers.insert(req.key().to_owned(), (rx, vec![]));
+
+          Some(req)
+        } else {
+          followers.insert(req.key().to_owned(), (rx, vec![req]));
+
+          None
+        }
+      })
+      .collect();
+
+    for (rx, reqs) in followers.into_values() {
+      if !reqs.is_empty() {
+        tokio::task::spawn(follow(reqs, rx));
+      }
+    }
+
+    requests
+  }
+}
+
+type Follower<K, V, E> = (watch::Receiver<LoadState<V, E>>, Vec<Request<K, V, E>>);
+
+type Followers<K, V, E> = std::collections::HashMap<K, Follower<K, V, E>>;
+
+// Resolve as the broadcast of the request loading the same key, or cancel should it be dropped without resolving
+async fn follow<K, V, E>(reqs: Vec<Request<K, V, E>>, mut rx: watch::Receiver<LoadState<V, E>>)
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  loop {
+    let result = match &*rx.borrow_and_update() {
+      LoadState::Ready(result) => Some(result.to_owned()),
+      LoadState::Pending | LoadState::Cancelled => None,
+    };
+
+    if let Some(result) = result {
+      for req in reqs {
+        req.resolve(result.clone());
+      }
+
+      return;
+    }
+
+    if rx.changed().await.is_err() {
+      for req in reqs {
+        req.cancel(None);
+      }
+
+      return;
+    }
+  }
+}
+
+/// Registration of a key in flight, held by the request loading it and released upon being resolved or dropped
+pub struct InFlight<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
+  key: K,
+  tx: Arc<watch::Sender<LoadState<V, E>>>,
+  in_flight: &'static InFlightMap<K, V, E>,
+}
+
+impl<K, V, E> InFlight<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  pub(crate) fn broadcast(self, result: &Result<Option<Arc<V>>, E>) {
+    self.tx.send(LoadState::Ready(result.to_owned())).ok();
+  }
+}
+
+impl<K, V, E> Drop for InFlight<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn drop(&mut self) {
+    let guard = self.in_flight.guard();
+
+    // Only release this registration; the key may since have been registered by another load
+    self.in_flight.compute_if_present(
+      &self.key,
+      |_, tx| match tx.upgrade() {
+        Some(tx) if !Arc::ptr_eq(&tx, &self.tx) => Some(Arc::downgrade(&tx)),
+        _ => None,
+      },
+      &guard,
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::DataLoader;
+  use crate::testing::{BatchRecorder, MockBackend, MockHandler, TestError};
+  use std::collections::HashMap;
+  use tokio::sync::Notify;
+
+  static ASSIGNED: Notify = Notify::const_new();
+  static RELEASE: Notify = Notify::const_new();
+
+  // Holds each batch in flight once assigned until released
+  pub struct GatedLoader;
+
+  #[async_trait::async_trait]
+  impl MockBackend for GatedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn before_load(_keys: &[i32]) {
+      let release = RELEASE.notified();
+      tokio::pin!(release);
+      release.as_mut().enable();
+
+      ASSIGNED.notify_one();
+      release.await;
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(key * 10))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_shares_loads_of_keys_in_flight() {
+    let recorder = BatchRecorder::<GatedLoader>::start();
+    let first: DataLoader<MockHandler<GatedLoader>> = DataLoader::default();
+    let second: DataLoader<MockHandler<GatedLoader>> = DataLoader::default();
+
+    first.enable_in_flight_deduplication();
+    second.enable_in_flight_deduplication();
+
+    let first_receivers: Vec<_> = (1..4).map(|key| first.load_by(key)).collect();
+
+    ASSIGNED.notified().await;
+
+    assert_eq!(
+      RequestDeduplicator::<MockHandler<GatedLoader>>::global().in_flight_count(),
+      3
+    );
+
+    let second_receivers: Vec<_> = (2..5).map(|key| second.load_by(key)).collect();
+
+    // Only the key not already in flight is dispatched
+    ASSIGNED.notified().await;
+
+    assert_eq!(
+      RequestDeduplicator::<MockHandler<GatedLoader>>::global().in_flight_count(),
+      4
+    );
+
+    RELEASE.notify_waiters();
+
+    for (key, rx) in (1..4).zip(first_receivers) {
+      assert_eq!(rx.recv().await, Ok(Some(Arc::new(key * 10))));
+    }
+
+    for (key, rx) in (2..5).zip(second_receivers) {
+      assert_eq!(rx.recv().await, Ok(Some(Arc::new(key * 10))));
+    }
+
+    assert_eq!(recorder.batches(), vec![vec![1, 2, 3], vec![4]]);
+    assert_eq!(
+      RequestDeduplicator::<MockHandler<GatedLoader>>::global().in_flight_count(),
+      0
+    );
+  }
+}
@@ -0,0 +1,170 @@
+//! Collect-then-load: keys are queued up front without touching the backend, and loaded together upon [`DeferredLoader::flush`]. This suits batch-processing scripts that know which records they're about to process, in contrast to the load-as-you-go batching of [`crate::loader::DataLoader`]
+//!
+//! ```rust
+//! let loader: DeferredLoader<BatchHandler<UserLoader>> = DeferredLoader::new();
+//!
+//! let handles: Vec<(Order, DeferredHandle<BatchHandler<UserLoader>>)> = orders
+//!   .into_iter()
+//!   .map(|order| {
+//!     let handle = loader.queue(order.user_id);
+//!     (order, handle)
+//!   })
+//!   .collect();
+//!
+//! let loaded = loader.flush().await;
+//! log::info!("loaded {} users", loaded);
+//!
+//! for (order, handle) in handles {
+//!   let user = handle.await?;
+//!   invoice::send(order, user);
+//! }
+//! ```
+use crate::{
+  request::{HandlerRequest, OneshotReceiver, Request},
+  task::{Task, TaskHandler},
+};
+use futures_util::future::BoxFuture;
+use std::{future::IntoFuture, sync::Arc, sync::Mutex};
+use swap_queue::Stealer;
+
+/// An explicit queue of loads, dispatched to the task handler only upon [`DeferredLoader::flush`]. There are no worker groups or work-stealing: every key queued since the last flush is handled as a single task, subject to splitting by [`TaskHandler::MAX_BATCH_SIZE`]
+pub struct DeferredLoader<T: TaskHandler> {
+  queue: Mutex<Vec<HandlerRequest<T>>>,
+}
+
+impl<T> Default for DeferredLoader<T>
+where
+  T: TaskHandler,
+{
+  fn default() -> Self {
+    DeferredLoader::new()
+  }
+}
+
+impl<T> DeferredLoader<T>
+where
+  T: TaskHandler,
+{
+  pub fn new() -> Self {
+    DeferredLoader {
+      queue: Mutex::new(vec![]),
+    }
+  }
+
+  /// Queue `key` to be loaded by the next [`DeferredLoader::flush`], returning a handle that resolves once flushed
+  pub fn queue(&self, key: T::Key) -> DeferredHandle<T> {
+    let (req, rx) = Request::new_oneshot(key);
+
+    self.queue.lock().unwrap().push(req);
+
+    DeferredHandle(rx)
+  }
+
+  /// Number of loads queued awaiting the next flush
+  pub fn queued_count(&self) -> usize {
+    self.queue.lock().unwrap().len()
+  }
+
+  /// Dispatch every queued load to the task handler as a single task, returning the number of loads dispatched. Handles resolve as the task handler resolves their batch
+  pub async fn flush(&self) -> usize {
+    let requests = std::mem::take(&mut *self.queue.lock().unwrap());
+    let request_count = requests.len();
+
+    if request_count.gt(&0) {
+      T::handle_task(Task::new(Stealer::Owner(requests))).await;
+    }
+
+    request_count
+  }
+}
+
+/// A load queued by [`DeferredLoader::queue`]. Awaiting a handle before its load is flushed waits until it is; should the loader be dropped with the load still queued, awaiting it panics as with [`OneshotReceiver::recv`]
+pub struct DeferredHandle<T: TaskHandler>(OneshotReceiver<T::Value, T::Error>);
+
+impl<T> DeferredHandle<T>
+where
+  T: TaskHandler,
+{
+  pub async fn recv(self) -> Result<Option<Arc<T::Value>>, T::Error> {
+    self.0.recv().await
+  }
+}
+
+impl<T> IntoFuture for DeferredHandle<T>
+where
+  T: TaskHandler,
+{
+  type Output = Result<Option<Arc<T::Value>>, T::Error>;
+  type IntoFuture = BoxFuture<'static, Self::Output>;
+
+  fn into_future(self) -> Self::IntoFuture {
+    self.0.into_future()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::batch::{BatchHandler, BatchLoader};
+  use crate::testing::TestError;
+  use std::collections::HashMap;
+
+  static DEFERRED_BATCHES: Mutex<Vec<Vec<i32>>> = Mutex::new(Vec::new());
+
+  pub struct DeferredRecords;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for DeferredRecords {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(mut keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      keys.sort_unstable();
+      DEFERRED_BATCHES.lock().unwrap().push(keys.clone());
+
+      Ok(
+        keys
+          .into_iter()
+          .map(|key| (key, Arc::new(key * 10)))
+          .collect(),
+      )
+    }
+  }
+
+  #[tokio::test]
+  async fn it_loads_queued_keys_upon_flush() {
+    let loader: DeferredLoader<BatchHandler<DeferredRecords>> = DeferredLoader::new();
+
+    let handles: Vec<_> = vec![3, 1, 2, 1]
+      .into_iter()
+      .map(|key| loader.queue(key))
+      .collect();
+
+    tokio::task::yield_now().await;
+
+    assert!(DEFERRED_BATCHES.lock().unwrap().is_empty());
+    assert_eq!(loader.queued_count(), 4);
+
+    assert_eq!(loader.flush().await, 4);
+    assert_eq!(loader.flush().await, 0);
+
+    let mut values = vec![];
+
+    for handle in handles {
+      values.push(handle.await.unwrap());
+    }
+
+    assert_eq!(
+      values,
+      vec![
+        Some(Arc::new(30)),
+        Some(Arc::new(10)),
+        Some(Arc::new(20)),
+        Some(Arc::new(10))
+      ]
+    );
+
+    assert_eq!(*DEFERRED_BATCHES.lock().unwrap(), vec![vec![1, 2, 3]]);
+  }
+}
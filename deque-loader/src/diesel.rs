@@ -1,5 +1,6 @@
 mod error;
 mod loader;
+mod warm;
 
-pub use error::{DieselError, SimpleDieselError};
+pub use error::{DieselError, DieselErrorKind, SimpleDieselError};
 pub use loader::*;
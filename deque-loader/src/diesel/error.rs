@@ -50,6 +50,70 @@ pub enum SimpleDieselError {
   DatabaseError,
 }
 
+/// Categories of [`SimpleDieselError`] for matching on without enumerating every variant
+///
+/// ```rust
+/// match User::load_by(user_id).await {
+///   Ok(user) => Ok(user),
+///   Err(err) if err.is_not_found() => Ok(None),
+///   Err(err) => match err.kind() {
+///     DieselErrorKind::Connection => Err(ApiError::Unavailable),
+///     DieselErrorKind::ConstraintViolation => Err(ApiError::Conflict),
+///     _ => Err(ApiError::Internal(err.into())),
+///   },
+/// }
+/// ```
+///
+/// As [`SimpleDieselError`] implements [`std::error::Error`], it converts into `anyhow::Error` and `Box<dyn Error>` with `?`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DieselErrorKind {
+  /// [`SimpleDieselError::NotFound`]
+  NotFound,
+  /// [`SimpleDieselError::Forbidden`] or [`SimpleDieselError::Unauthorized`]
+  Permission,
+  /// [`SimpleDieselError::BadConnection`], [`SimpleDieselError::InvalidConnection`] or [`SimpleDieselError::ConnectionTimeout`]
+  Connection,
+  /// [`SimpleDieselError::RollbackTransaction`]
+  Transaction,
+  /// [`SimpleDieselError::UniqueViolation`] or [`SimpleDieselError::ForeignKeyViolation`]
+  ConstraintViolation,
+  /// [`SimpleDieselError::DatabaseError`]
+  Database,
+  /// [`SimpleDieselError::InternalServerError`]
+  Internal,
+}
+
+impl SimpleDieselError {
+  pub fn kind(&self) -> DieselErrorKind {
+    match self {
+      SimpleDieselError::NotFound => DieselErrorKind::NotFound,
+      SimpleDieselError::Forbidden | SimpleDieselError::Unauthorized => DieselErrorKind::Permission,
+      SimpleDieselError::BadConnection
+      | SimpleDieselError::InvalidConnection
+      | SimpleDieselError::ConnectionTimeout => DieselErrorKind::Connection,
+      SimpleDieselError::RollbackTransaction => DieselErrorKind::Transaction,
+      SimpleDieselError::UniqueViolation | SimpleDieselError::ForeignKeyViolation => {
+        DieselErrorKind::ConstraintViolation
+      }
+      SimpleDieselError::DatabaseError => DieselErrorKind::Database,
+      SimpleDieselError::InternalServerError => DieselErrorKind::Internal,
+    }
+  }
+
+  pub fn is_not_found(&self) -> bool {
+    self.kind().eq(&DieselErrorKind::NotFound)
+  }
+
+  pub fn is_connection_error(&self) -> bool {
+    self.kind().eq(&DieselErrorKind::Connection)
+  }
+
+  pub fn is_constraint_violation(&self) -> bool {
+    self.kind().eq(&DieselErrorKind::ConstraintViolation)
+  }
+}
+
 impl From<DieselError> for SimpleDieselError {
   fn from(err: DieselError) -> Self {
     match err {
@@ -133,3 +197,73 @@ impl async_graphql::ErrorExtensions for SimpleDieselError {
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_categorizes_errors() {
+    let cases = vec![
+      (SimpleDieselError::NotFound, DieselErrorKind::NotFound),
+      (SimpleDieselError::Forbidden, DieselErrorKind::Permission),
+      (SimpleDieselError::Unauthorized, DieselErrorKind::Permission),
+      (
+        SimpleDieselError::BadConnection,
+        DieselErrorKind::Connection,
+      ),
+      (
+        SimpleDieselError::InvalidConnection,
+        DieselErrorKind::Connection,
+      ),
+      (
+        SimpleDieselError::ConnectionTimeout,
+        DieselErrorKind::Connection,
+      ),
+      (
+        SimpleDieselError::RollbackTransaction,
+        DieselErrorKind::Transaction,
+      ),
+      (
+        SimpleDieselError::UniqueViolation,
+        DieselErrorKind::ConstraintViolation,
+      ),
+      (
+        SimpleDieselError::ForeignKeyViolation,
+        DieselErrorKind::ConstraintViolation,
+      ),
+      (SimpleDieselError::DatabaseError, DieselErrorKind::Database),
+      (
+        SimpleDieselError::InternalServerError,
+        DieselErrorKind::Internal,
+      ),
+    ];
+
+    for (err, kind) in cases {
+      assert_eq!(err.kind(), kind);
+      assert_eq!(err.is_not_found(), kind.eq(&DieselErrorKind::NotFound));
+      assert_eq!(
+        err.is_connection_error(),
+        kind.eq(&DieselErrorKind::Connection)
+      );
+      assert_eq!(
+        err.is_constraint_violation(),
+        kind.eq(&DieselErrorKind::ConstraintViolation)
+      );
+    }
+  }
+
+  #[test]
+  fn it_preserves_kind_from_diesel_errors() {
+    let err: SimpleDieselError = DieselError::QueryError(diesel::result::Error::NotFound).into();
+    assert!(err.is_not_found());
+
+    let err: SimpleDieselError =
+      DieselError::ConnectionError(ConnectionError::BadConnection("connection reset".into()))
+        .into();
+    assert!(err.is_connection_error());
+
+    let err: SimpleDieselError = DatabaseErrorKind::UniqueViolation.into();
+    assert!(err.is_constraint_violation());
+  }
+}
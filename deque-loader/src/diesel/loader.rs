@@ -2,22 +2,65 @@ use super::{error::DieselError, SimpleDieselError};
 use crate::{
   key::Key,
   loader::{DataLoader, LocalLoader, StoreType},
-  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+  task::{
+    task_handler_defaults, CompletionReceipt, LoadBatch, PendingAssignment, Task, TaskAssignment,
+    TaskHandler,
+  },
 };
-use diesel_connection::{get_connection, PooledConnection};
-use std::{collections::HashMap, sync::Arc};
+use diesel_connection::{get_connection, ConnectionPool, PoolContext, PooledConnection};
+use log::{error, warn};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::time::Instant;
 
 /// a [`diesel`] specific loader interface using [`diesel_connection::get_connection`] for connection acquisition
 pub trait DieselLoader: Sized + Send + Sync + 'static {
   type Key: Key;
   type Value: Send + Sync + Clone + 'static;
-  const CORES_PER_WORKER_GROUP: usize = 4;
-  const MAX_BATCH_SIZE: Option<usize> = None;
+  task_handler_defaults!(SimpleDieselError);
+
+  /// Number of batches to load in sequence from a single connection acquisition, amortizing pool acquisition latency when batches are small. Depths greater than 1 load via [`DieselLoader::load_pipelined`]
+  const PIPELINE_DEPTH: usize = 1;
+  /// Wait for the pool to have capacity prior to task assignment rather than dispatch batches bound to fail upon connection acquisition, as measured by [`DieselLoader::pool_exhausted`]. Loads continue to be enqueued while waiting
+  const PAUSE_ON_POOL_EXHAUSTION: bool = false;
+  /// Interval at which an exhausted pool is rechecked
+  const POOL_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+  /// Maximum duration to wait upon an exhausted pool, after which batches resolve as [`SimpleDieselError::ConnectionTimeout`]. As waiting precedes task assignment, it counts towards any startup timeout set via [`crate::loader::DataLoader::set_worker_startup_timeout`]
+  const POOL_WAIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+  /// Whether every connection of the pool is in use and the pool is at capacity
+  fn pool_exhausted() -> bool {
+    let pool = <ConnectionPool as PoolContext>::pool();
+    let state = pool.state();
+
+    state.idle_connections.eq(&0) && state.connections.ge(&pool.max_size())
+  }
 
   fn load(
     conn: PooledConnection,
     keys: Vec<Self::Key>,
   ) -> Result<HashMap<Self::Key, Arc<Self::Value>>, DieselError>;
+
+  /// Load against a connection shared across a pipeline of batches when [`DieselLoader::PIPELINE_DEPTH`] is greater than 1. Unless implemented, each batch falls back to [`DieselLoader::load`] on a connection of its own, forgoing connection reuse
+  fn load_pipelined(
+    _conn: &mut PipelineConnection,
+    keys: Vec<Self::Key>,
+  ) -> Result<HashMap<Self::Key, Arc<Self::Value>>, DieselError> {
+    Self::load(get_connection()?, keys)
+  }
+}
+
+/// The connection shared across a pipeline of batches, acquired upon first use so that pipelines loading via [`DieselLoader::load`] never hold a connection alongside the connection of each batch
+#[derive(Default)]
+pub struct PipelineConnection(Option<PooledConnection>);
+
+impl PipelineConnection {
+  /// Get the shared connection, acquiring it from the pool should this be the first use within the pipeline
+  pub fn get(&mut self) -> Result<&mut PooledConnection, DieselError> {
+    match &mut self.0 {
+      Some(conn) => Ok(conn),
+      conn => Ok(conn.insert(get_connection()?)),
+    }
+  }
 }
 pub struct DieselHandler<T: DieselLoader>(T);
 
@@ -29,27 +72,90 @@ where
   type Key = T::Key;
   type Value = T::Value;
   type Error = SimpleDieselError;
-  const CORES_PER_WORKER_GROUP: usize = T::CORES_PER_WORKER_GROUP;
-  const MAX_BATCH_SIZE: Option<usize> = T::MAX_BATCH_SIZE;
+  crate::forward_task_handler!(T);
 
   async fn handle_task(
     task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
   ) -> Task<CompletionReceipt> {
-    let assignment = task.get_assignment::<Self>().await;
+    if T::PAUSE_ON_POOL_EXHAUSTION && !DieselHandler::<T>::await_pool_capacity().await {
+      return match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          error!(
+            "batch_id={} {} pool exhausted for {:?}",
+            task.batch_id(),
+            tynm::type_name::<T>(),
+            T::POOL_WAIT_TIMEOUT
+          );
+          task.resolve(Err(SimpleDieselError::ConnectionTimeout))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      };
+    }
+
+    if T::PIPELINE_DEPTH.gt(&1) {
+      let assignments: Vec<_> = task
+        .get_assignments::<Self>(T::PIPELINE_DEPTH)
+        .await
+        .into_iter()
+        .filter_map(|task| match task.validate::<Self>() {
+          TaskAssignment::LoadBatch(task) => Some(task),
+          TaskAssignment::NoAssignment(_) => None,
+        })
+        .collect();
+
+      #[cfg(feature = "tracing")]
+      let span = tracing::Span::current();
+
+      return tokio::task::spawn_blocking(move || {
+        #[cfg(feature = "tracing")]
+        let _guard = span.enter();
+
+        DieselHandler::<T>::load_pipeline(assignments)
+      })
+      .await
+      .unwrap();
+    }
+
+    // Validated ahead of spawn_blocking, so that rejected batches don't acquire a connection
+    let task = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => match task.validate::<Self>() {
+        TaskAssignment::LoadBatch(task) => task,
+        TaskAssignment::NoAssignment(receipt) => return receipt,
+      },
+      TaskAssignment::NoAssignment(receipt) => return receipt,
+    };
+
+    #[cfg(feature = "tracing")]
+    let span = tracing::Span::current();
 
     tokio::task::spawn_blocking(move || {
-      let conn = get_connection();
-
-      match assignment {
-        TaskAssignment::LoadBatch(task) => match conn {
-          Ok(conn) => {
-            let keys = task.keys();
-            let result = T::load(conn, keys).map_err(|err| err.into());
-            task.resolve(result)
-          }
-          Err(err) => task.resolve(Err(err.into())),
-        },
-        TaskAssignment::NoAssignment(receipt) => receipt,
+      // Re-enter the span of the task handler, as spawn_blocking doesn't carry over the current span
+      #[cfg(feature = "tracing")]
+      let _guard = span.enter();
+
+      match get_connection() {
+        Ok(conn) => {
+          let keys = task.keys();
+          let result = T::load(conn, keys).map_err(|err| {
+            error!(
+              "batch_id={} {} failed: {}",
+              task.batch_id(),
+              tynm::type_name::<T>(),
+              err
+            );
+            err.into()
+          });
+          task.resolve(result)
+        }
+        Err(err) => {
+          error!(
+            "batch_id={} {} unable to acquire connection: {}",
+            task.batch_id(),
+            tynm::type_name::<T>(),
+            err
+          );
+          task.resolve(Err(err.into()))
+        }
       }
     })
     .await
@@ -57,6 +163,59 @@ where
   }
 }
 
+impl<T> DieselHandler<T>
+where
+  T: DieselLoader,
+{
+  // Returns false should the pool remain exhausted for DieselLoader::POOL_WAIT_TIMEOUT
+  async fn await_pool_capacity() -> bool {
+    if !T::pool_exhausted() {
+      return true;
+    }
+
+    warn!("{} pausing for pool capacity", tynm::type_name::<T>());
+
+    let deadline = Instant::now() + T::POOL_WAIT_TIMEOUT;
+
+    while T::pool_exhausted() {
+      if Instant::now().ge(&deadline) {
+        return false;
+      }
+
+      tokio::time::sleep(T::POOL_RETRY_INTERVAL).await;
+    }
+
+    true
+  }
+
+  #[allow(clippy::type_complexity)]
+  fn load_pipeline(
+    assignments: Vec<Task<LoadBatch<T::Key, T::Value, SimpleDieselError>>>,
+  ) -> Task<CompletionReceipt> {
+    if assignments.is_empty() {
+      return Task::completion_receipt();
+    }
+
+    let mut conn = PipelineConnection::default();
+
+    assignments
+      .into_iter()
+      .fold(Task::completion_receipt(), |_, task| {
+        let keys = task.keys();
+        let result = T::load_pipelined(&mut conn, keys).map_err(|err| {
+          error!(
+            "batch_id={} {} failed: {}",
+            task.batch_id(),
+            tynm::type_name::<T>(),
+            err
+          );
+          err.into()
+        });
+        task.resolve(result)
+      })
+  }
+}
+
 impl<T, Store> LocalLoader<Store> for DieselHandler<T>
 where
   T: DieselLoader + LocalLoader<Store>,
@@ -67,3 +226,182 @@ where
     T::loader()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::DataLoader;
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+  };
+
+  static RECOVERING_CHECKS: AtomicUsize = AtomicUsize::new(0);
+
+  pub struct RecoveringLoader;
+
+  impl DieselLoader for RecoveringLoader {
+    type Key = i32;
+    type Value = i32;
+    const PAUSE_ON_POOL_EXHAUSTION: bool = true;
+
+    // Exhausted for the first three checks
+    fn pool_exhausted() -> bool {
+      RECOVERING_CHECKS.fetch_add(1, Ordering::SeqCst) < 3
+    }
+
+    fn load(
+      _conn: PooledConnection,
+      keys: Vec<i32>,
+    ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+      Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+    }
+  }
+
+  pub struct ExhaustedLoader;
+
+  impl DieselLoader for ExhaustedLoader {
+    type Key = i32;
+    type Value = i32;
+    const PAUSE_ON_POOL_EXHAUSTION: bool = true;
+    const POOL_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn pool_exhausted() -> bool {
+      true
+    }
+
+    fn load(
+      _conn: PooledConnection,
+      _keys: Vec<i32>,
+    ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+      unreachable!("loads without pool capacity")
+    }
+  }
+
+  // Rejects negative keys, and batches of more than two keys
+  pub struct ValidatedLoader;
+
+  impl DieselLoader for ValidatedLoader {
+    type Key = i32;
+    type Value = i32;
+
+    fn validate_key(key: &i32) -> Result<(), SimpleDieselError> {
+      if key.ge(&0) {
+        Ok(())
+      } else {
+        Err(SimpleDieselError::NotFound)
+      }
+    }
+
+    fn validate_batch(keys: &[i32]) -> Result<(), SimpleDieselError> {
+      if keys.len().le(&2) {
+        Ok(())
+      } else {
+        Err(SimpleDieselError::Forbidden)
+      }
+    }
+
+    fn load(
+      _conn: PooledConnection,
+      _keys: Vec<i32>,
+    ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+      unreachable!("loads invalid batches")
+    }
+  }
+
+  static PIPELINED_BATCHES: Mutex<Vec<(usize, usize)>> = Mutex::new(vec![]);
+
+  // Loads batches of up to two keys, three batches to a pipeline, without using the shared connection
+  pub struct PipelinedLoader;
+
+  impl DieselLoader for PipelinedLoader {
+    type Key = i32;
+    type Value = i32;
+    const MAX_BATCH_SIZE: Option<usize> = Some(2);
+    const PIPELINE_DEPTH: usize = 3;
+
+    fn load(
+      _conn: PooledConnection,
+      _keys: Vec<i32>,
+    ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+      unreachable!("loads via load_pipelined")
+    }
+
+    fn load_pipelined(
+      conn: &mut PipelineConnection,
+      keys: Vec<i32>,
+    ) -> Result<HashMap<i32, Arc<i32>>, DieselError> {
+      PIPELINED_BATCHES
+        .lock()
+        .unwrap()
+        .push((conn as *const PipelineConnection as usize, keys.len()));
+
+      Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_loads_pipelined_batches_without_acquiring_unused_connections() {
+    let loader: DataLoader<DieselHandler<PipelinedLoader>> = DataLoader::default();
+
+    let receivers: Vec<_> = (0..6).map(|key| (key, loader.load_by(key))).collect();
+
+    for (key, rx) in receivers {
+      assert_eq!(rx.recv().await.unwrap(), Some(Arc::new(key)));
+    }
+
+    let batches = PIPELINED_BATCHES.lock().unwrap();
+
+    assert_eq!(batches.len(), 3);
+    assert!(batches.iter().all(|(_, len)| len.eq(&2)));
+    // Each batch was loaded against the same pipeline connection
+    assert!(batches.iter().all(|(conn, _)| conn.eq(&batches[0].0)));
+  }
+
+  #[tokio::test]
+  async fn it_rejects_invalid_batches_without_a_connection() {
+    let loader: DataLoader<DieselHandler<ValidatedLoader>> = DataLoader::default();
+
+    let receivers: Vec<_> = vec![-1, 1, 2, 3]
+      .into_iter()
+      .map(|key| loader.load_by(key))
+      .collect();
+
+    let mut receivers = receivers.into_iter();
+
+    assert!(matches!(
+      receivers.next().unwrap().recv().await,
+      Err(SimpleDieselError::NotFound)
+    ));
+
+    for rx in receivers {
+      assert!(matches!(rx.recv().await, Err(SimpleDieselError::Forbidden)));
+    }
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_pauses_until_pool_capacity() {
+    let start = Instant::now();
+
+    assert!(DieselHandler::<RecoveringLoader>::await_pool_capacity().await);
+    assert_eq!(start.elapsed(), Duration::from_millis(20));
+    assert_eq!(RECOVERING_CHECKS.load(Ordering::SeqCst), 4);
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_fails_upon_pool_wait_timeout() {
+    let loader: DataLoader<DieselHandler<ExhaustedLoader>> = DataLoader::default();
+    let start = Instant::now();
+
+    let receivers = vec![loader.load_by(1), loader.load_by(2)];
+
+    for rx in receivers {
+      assert!(matches!(
+        rx.recv().await,
+        Err(SimpleDieselError::ConnectionTimeout)
+      ));
+    }
+
+    assert!(start.elapsed().ge(&Duration::from_millis(100)));
+  }
+}
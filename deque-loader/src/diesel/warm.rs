@@ -0,0 +1,32 @@
+use super::{DieselError, DieselLoader};
+use crate::{request::ContextCache, task::TaskHandler};
+use diesel_connection::PooledConnection;
+
+impl<T> ContextCache<T>
+where
+  T: TaskHandler,
+{
+  /// Populate the cache with hot keys loaded directly by `Q`, bypassing batching. Intended as a startup optimization, such as prior to serving traffic, and so runs synchronously; call from within [`tokio::task::spawn_blocking`] once the runtime is serving loads. Keys already loading or loaded are left as is so that loads in flight are never replaced, and keys not found aren't cached. Returns the number of keys warmed
+  ///
+  /// ```rust
+  /// let hot_keys = vec![UserId(1), UserId(2)];
+  ///
+  /// ContextCache::<DieselHandler<UserLoader>>::global()
+  ///   .warm_from_database::<UserLoader>(get_connection()?, hot_keys)?;
+  /// ```
+  pub fn warm_from_database<Q>(
+    &self,
+    conn: PooledConnection,
+    keys: Vec<T::Key>,
+  ) -> Result<usize, DieselError>
+  where
+    Q: DieselLoader<Key = T::Key, Value = T::Value>,
+  {
+    let warmed = Q::load(conn, keys)?
+      .into_iter()
+      .filter(|(key, value)| self.warm_if_absent(key.to_owned(), value.to_owned()))
+      .count();
+
+    Ok(warmed)
+  }
+}
@@ -0,0 +1,57 @@
+//! Post-resolution hooks for writing loaded values into additional caches, such as an in-process cache in front of a shared Redis cache
+//!
+//! ```rust
+//! let local_cache: moka::sync::Cache<UserId, Arc<User>> = moka::sync::Cache::new(10_000);
+//!
+//! UserLoader::loader().with(|loader| {
+//!   loader.fan_out(vec![
+//!     Box::new(local_cache.clone()) as Box<dyn CacheWriter<UserId, User>>,
+//!     Box::new(FnCacheWriter(|user_id: &UserId, _user: Arc<User>| {
+//!       log::debug!("loaded {:?}", user_id);
+//!     })),
+//!   ]);
+//! });
+//! ```
+
+use crate::Key;
+use std::sync::Arc;
+
+/// A cache that loaded values are written into after each load resolves
+pub trait CacheWriter<K: Key, V: Send + Sync + 'static>: Send + Sync + 'static {
+  fn write(&self, key: &K, value: Arc<V>);
+}
+
+impl<K, V> CacheWriter<K, V> for Box<dyn CacheWriter<K, V>>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+{
+  fn write(&self, key: &K, value: Arc<V>) {
+    self.as_ref().write(key, value)
+  }
+}
+
+/// Adapts a closure into a [`CacheWriter`]
+pub struct FnCacheWriter<F>(pub F);
+
+impl<K, V, F> CacheWriter<K, V> for FnCacheWriter<F>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+  F: Fn(&K, Arc<V>) + Send + Sync + 'static,
+{
+  fn write(&self, key: &K, value: Arc<V>) {
+    (self.0)(key, value)
+  }
+}
+
+#[cfg(feature = "moka")]
+impl<K, V> CacheWriter<K, V> for moka::sync::Cache<K, Arc<V>>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+{
+  fn write(&self, key: &K, value: Arc<V>) {
+    self.insert(key.to_owned(), value);
+  }
+}
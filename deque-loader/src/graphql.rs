@@ -1,15 +1,28 @@
-use crate::{request::ContextCache, task::TaskHandler};
-use async_graphql::{context::Context, Request};
+use crate::{
+  loader::{DataStore, LocalLoader},
+  request::ContextCache,
+  task::TaskHandler,
+};
+use async_graphql::{context::Context, ErrorExtensions, Request};
+use std::{marker::PhantomData, sync::Arc};
 
 #[doc(hidden)]
-pub struct CacheFactory(Box<dyn Fn(Request) -> Request + Send + Sync>);
+pub struct CacheFactory(fn(Request) -> Request);
+
+fn insert_cache<T>(request: Request) -> Request
+where
+  T: TaskHandler,
+{
+  request.data(ContextCache::<T>::new())
+}
 
 impl CacheFactory {
-  pub fn new<T>() -> Self
+  // As a const fn so that factories can be submitted to inventory, which requires a const initializer
+  pub const fn new<T>() -> Self
   where
     T: TaskHandler,
   {
-    CacheFactory(Box::new(|request| request.data(ContextCache::<T>::new())))
+    CacheFactory(insert_cache::<T>)
   }
 
   pub fn insert_loader_cache(&self, request: Request) -> Request {
@@ -28,14 +41,116 @@ pub fn insert_loader_caches(mut request: Request) -> Request {
   request
 }
 
-/// Register cache factory for a [`TaskHandler`] using [`inventory`]. This will require adding [`inventory`] to your dependencies as well.
+/// Register cache factory for a [`TaskHandler`] using [`inventory`]
 #[macro_export]
 macro_rules! register_cache_factory {
   ($handler:ty) => {
-    inventory::submit!({ $crate::graphql::CacheFactory::new::<$handler>() });
+    $crate::inventory::submit!({ $crate::graphql::CacheFactory::new::<$handler>() });
   };
 }
 
+/// Load by key against the [`ContextCache`] of the request, or without caching should none have been inserted by [`insert_loader_caches`]. Used by resolvers generated with [`deque_loader_derive::graphql_loader`]
+#[doc(hidden)]
+pub async fn load_by_context<T>(
+  ctx: &Context<'_>,
+  key: <T::Handler as TaskHandler>::Key,
+) -> async_graphql::Result<Option<Arc<<T::Handler as TaskHandler>::Value>>>
+where
+  T: LocalLoader<DataStore>,
+  <T::Handler as TaskHandler>::Error: ErrorExtensions,
+{
+  let result = match ctx.data_opt::<ContextCache<T::Handler>>() {
+    Some(cache) => {
+      let rx = T::loader().with(|loader| loader.cached_load_by(key, cache));
+      rx.recv().await
+    }
+    None => {
+      let rx = T::loader().with(|loader| loader.load_by(key));
+      rx.recv().await
+    }
+  };
+
+  result.map_err(|err| err.extend())
+}
+
+/// Load many keys as a single batch, as for an [`async_graphql::dataloader::Loader`] generated with [`deque_loader_derive::graphql_loader`]
+#[cfg(feature = "graphql-dataloader")]
+#[doc(hidden)]
+pub async fn load_many<T>(
+  keys: &[<T::Handler as TaskHandler>::Key],
+) -> Result<
+  std::collections::HashMap<
+    <T::Handler as TaskHandler>::Key,
+    Arc<<T::Handler as TaskHandler>::Value>,
+  >,
+  <T::Handler as TaskHandler>::Error,
+>
+where
+  T: LocalLoader<DataStore>,
+{
+  let receivers: Vec<_> = T::loader().with(|loader| {
+    keys
+      .iter()
+      .map(|key| loader.load_by(key.to_owned()))
+      .collect()
+  });
+
+  let values =
+    futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+  Ok(
+    keys
+      .iter()
+      .zip(values)
+      .filter_map(|(key, value)| value.map(|value| (key.to_owned(), value)))
+      .collect(),
+  )
+}
+
+/// Per-key authorization of loaded values for [`AuthorizedLoader`], such as against the viewer within request data
+///
+/// ```rust
+/// pub struct ViewableUser;
+///
+/// #[async_trait::async_trait]
+/// impl Authorizer<DieselHandler<User>> for ViewableUser {
+///   async fn is_authorized(ctx: &Context<'_>, _: &i32, user: &User) -> bool {
+///     ctx.data_opt::<Viewer>().is_some_and(|viewer| viewer.can_view(user))
+///   }
+/// }
+///
+/// async fn user(&self, ctx: &Context<'_>, id: i32) -> FieldResult<Option<Arc<User>>> {
+///   AuthorizedLoader::<DieselHandler<User>, ViewableUser>::load(ctx, id).await
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait Authorizer<T: TaskHandler>: Send + Sync + 'static {
+  async fn is_authorized(ctx: &Context<'_>, key: &T::Key, value: &T::Value) -> bool;
+}
+
+/// Loads via [`load_by_context`], resolving values not permitted by the [`Authorizer`] as `Ok(None)` such that values missing due to authorization are indistinguishable from values that don't exist. Authorization is checked after the batch resolves, so that whether a key exists isn't revealed by which keys are loaded
+pub struct AuthorizedLoader<T: TaskHandler, A: Authorizer<T>> {
+  handler: PhantomData<fn() -> T>,
+  authorizer: PhantomData<fn() -> A>,
+}
+
+impl<T, A> AuthorizedLoader<T, A>
+where
+  T: TaskHandler + LocalLoader<DataStore, Handler = T>,
+  T::Error: ErrorExtensions,
+  A: Authorizer<T>,
+{
+  pub async fn load(
+    ctx: &Context<'_>,
+    key: T::Key,
+  ) -> async_graphql::Result<Option<Arc<T::Value>>> {
+    match load_by_context::<T>(ctx, key.clone()).await? {
+      Some(value) if A::is_authorized(ctx, &key, &value).await => Ok(Some(value)),
+      _ => Ok(None),
+    }
+  }
+}
+
 impl<T> AsRef<ContextCache<T>> for Context<'_>
 where
   T: TaskHandler,
@@ -49,3 +164,88 @@ where
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler};
+  use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+  use deque_loader_derive::Loader;
+  use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  static LOADED_KEYS: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(thiserror::Error, Debug, Clone)]
+  #[error("backend error")]
+  pub struct BackendError;
+
+  impl ErrorExtensions for BackendError {
+    fn extend(&self) -> async_graphql::Error {
+      async_graphql::Error::new("backend error")
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<RecordLoader>")]
+  pub struct RecordLoader;
+
+  impl MockBackend for RecordLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = BackendError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, BackendError> {
+      Ok(
+        keys
+          .iter()
+          .filter(|key| key.lt(&&10))
+          .map(|key| {
+            LOADED_KEYS.fetch_add(1, Ordering::SeqCst);
+            (*key, Arc::new(key * 100))
+          })
+          .collect(),
+      )
+    }
+  }
+
+  pub struct EvenRecords;
+
+  #[async_trait::async_trait]
+  impl Authorizer<MockHandler<RecordLoader>> for EvenRecords {
+    async fn is_authorized(_: &Context<'_>, key: &i32, _: &i32) -> bool {
+      key % 2 == 0
+    }
+  }
+
+  struct Query;
+
+  #[Object]
+  impl Query {
+    async fn record(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<i32>> {
+      let record =
+        AuthorizedLoader::<MockHandler<RecordLoader>, EvenRecords>::load(ctx, id).await?;
+      Ok(record.map(|record| *record))
+    }
+  }
+
+  #[tokio::test]
+  async fn it_hides_unauthorized_values() {
+    let schema = Schema::new(Query, EmptyMutation, EmptySubscription);
+    let request = Request::new("{ a: record(id: 2) b: record(id: 3) c: record(id: 12) }")
+      .data(ContextCache::<MockHandler<RecordLoader>>::new());
+
+    let response = schema.execute(request).await;
+
+    assert!(response.errors.is_empty());
+    assert_eq!(
+      response.data.into_json().unwrap(),
+      serde_json::json!({ "a": 200, "b": null, "c": null })
+    );
+
+    // Denied keys are loaded as any other, with authorization checked after the batch resolves
+    assert_eq!(LOADED_KEYS.load(Ordering::SeqCst), 2);
+  }
+}
@@ -0,0 +1,47 @@
+//! Loading from REST APIs, one request per key. Implementations of [`HttpLoader`] bring their own HTTP client and translate responses into [`Fetched`], which is all that [`HttpHandler`] and [`EtagCache`] need to load batches and make conditional requests
+//!
+//! ```rust
+//! #[derive(Loader)]
+//! #[data_loader(handler = "EtagCache<ArticleLoader>")]
+//! pub struct ArticleLoader;
+//!
+//! #[async_trait::async_trait]
+//! impl HttpLoader for ArticleLoader {
+//!   type Key = i32;
+//!   type Value = Article;
+//!   type Error = ApiError;
+//!
+//!   async fn fetch(key: &i32, if_none_match: Option<&str>) -> Result<Fetched<Article>, ApiError> {
+//!     let mut request = CLIENT.get(format!("https://api.example.com/articles/{}", key));
+//!
+//!     // Set by EtagCache for keys previously fetched with an ETag
+//!     if let Some(etag) = if_none_match {
+//!       request = request.header(IF_NONE_MATCH, etag);
+//!     }
+//!
+//!     let response = request.send().await?;
+//!
+//!     match response.status() {
+//!       StatusCode::NOT_MODIFIED => Ok(Fetched::NotModified),
+//!       StatusCode::NOT_FOUND => Ok(Fetched::NotFound),
+//!       _ => {
+//!         let etag = response
+//!           .headers()
+//!           .get(ETAG)
+//!           .and_then(|etag| etag.to_str().ok())
+//!           .map(String::from);
+//!
+//!         // The body is only parsed upon 200 OK
+//!         let value = response.error_for_status()?.json::<Article>().await?;
+//!
+//!         Ok(Fetched::Modified { value, etag })
+//!       }
+//!     }
+//!   }
+//! }
+//! ```
+mod etag;
+mod loader;
+
+pub use etag::*;
+pub use loader::*;
@@ -0,0 +1,215 @@
+use super::{fetch_each, Fetched, HttpLoader};
+use crate::{
+  loader::{DataLoader, LocalLoader, StoreType},
+  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+};
+use flurry::HashMap;
+use std::{
+  any::{Any, TypeId},
+  sync::{Arc, Mutex, OnceLock},
+};
+
+static ENTRIES: OnceLock<
+  Mutex<std::collections::HashMap<TypeId, &'static (dyn Any + Send + Sync)>>,
+> = OnceLock::new();
+
+/// A [`TaskHandler`] making conditional requests by [`HttpLoader::fetch`]. Values fetched with an `ETag` are retained alongside it, and subsequent loads of the key send it as `If-None-Match`: upon `304 Not Modified` the retained value is reused without the body being re-parsed, whereas upon `200 OK` the new value and ETag replace the entry, and upon `404 Not Found` the entry is removed. Entries are shared process-wide by every thread local loader of `T` and live until invalidated, as only the server can tell whether they're stale
+///
+/// ```rust
+/// #[derive(Loader)]
+/// #[data_loader(handler = "EtagCache<ArticleLoader>")]
+/// pub struct ArticleLoader;
+/// ```
+pub struct EtagCache<T: HttpLoader>(T);
+
+impl<T> EtagCache<T>
+where
+  T: HttpLoader,
+{
+  // Entries are created on first use and live for the duration of the program
+  fn entries() -> &'static HashMap<T::Key, (Arc<T::Value>, String)> {
+    let mut entries = ENTRIES.get_or_init(Default::default).lock().unwrap();
+
+    let store = *entries
+      .entry(TypeId::of::<T>())
+      .or_insert_with(|| Box::leak(Box::new(HashMap::<T::Key, (Arc<T::Value>, String)>::new())));
+
+    store
+      .downcast_ref::<HashMap<T::Key, (Arc<T::Value>, String)>>()
+      .unwrap()
+  }
+
+  /// The ETag retained for `key`, if any
+  pub fn etag(key: &T::Key) -> Option<String> {
+    Self::entries()
+      .pin()
+      .get(key)
+      .map(|(_, etag)| etag.to_owned())
+  }
+
+  /// Remove the entry of `key`, such that it's next fetched unconditionally
+  pub fn invalidate(key: &T::Key) {
+    Self::entries().pin().remove(key);
+  }
+
+  async fn fetch(key: T::Key) -> (T::Key, Result<Option<Arc<T::Value>>, T::Error>) {
+    let entries = Self::entries();
+    let retained = entries.pin().get(&key).cloned();
+
+    let fetched = match &retained {
+      Some((_, etag)) => T::fetch(&key, Some(etag)).await,
+      None => T::fetch(&key, None).await,
+    };
+
+    let outcome = match (fetched, retained) {
+      (Ok(Fetched::NotModified), Some((value, _))) => Ok(Some(value)),
+      // Servers mustn't respond 304 to unconditional requests, though should one it's treated as absent
+      (Ok(Fetched::NotModified), None) => Ok(None),
+      (Ok(Fetched::Modified { value, etag }), _) => {
+        let value = Arc::new(value);
+
+        match etag {
+          Some(etag) => {
+            entries.pin().insert(key.clone(), (value.clone(), etag));
+          }
+          None => {
+            entries.pin().remove(&key);
+          }
+        }
+
+        Ok(Some(value))
+      }
+      (Ok(Fetched::NotFound), _) => {
+        entries.pin().remove(&key);
+        Ok(None)
+      }
+      (Err(err), _) => Err(err),
+    };
+
+    (key, outcome)
+  }
+}
+
+#[async_trait::async_trait]
+impl<T> TaskHandler for EtagCache<T>
+where
+  T: HttpLoader,
+{
+  type Key = T::Key;
+  type Value = T::Value;
+  type Error = T::Error;
+  crate::forward_task_handler!(T);
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let assignment = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.validate::<Self>(),
+      assignment => assignment,
+    };
+
+    match assignment {
+      TaskAssignment::LoadBatch(task) => {
+        let outcomes = fetch_each::<T, _, _>(task.keys(), Self::fetch).await;
+        task.resolve_outcomes(outcomes, T::shutdown_error())
+      }
+      TaskAssignment::NoAssignment(receipt) => receipt,
+    }
+  }
+}
+
+impl<Loader, Store> LocalLoader<Store> for EtagCache<Loader>
+where
+  Loader: HttpLoader + LocalLoader<Store>,
+  Store: StoreType,
+{
+  type Handler = <Loader as LocalLoader<Store>>::Handler;
+  fn loader() -> &'static std::thread::LocalKey<DataLoader<Self::Handler>> {
+    Loader::loader()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestError;
+  use deque_loader_derive::{Loadable, Loader};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  static PARSED_BODIES: AtomicUsize = AtomicUsize::new(0);
+  static NOT_MODIFIED: AtomicUsize = AtomicUsize::new(0);
+  static REVISIONS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+  #[derive(Loader)]
+  #[data_loader(handler = "EtagCache<ArticleLoader>")]
+  pub struct ArticleLoader;
+
+  #[derive(Clone, Debug, PartialEq, Eq, Loadable)]
+  #[data_loader(handler = "EtagCache<ArticleLoader>")]
+  pub struct Article {
+    id: usize,
+    revision: usize,
+  }
+
+  #[async_trait::async_trait]
+  impl HttpLoader for ArticleLoader {
+    type Key = usize;
+    type Value = Article;
+    type Error = TestError;
+
+    // Serves `REVISIONS[id]` as a mock REST API would, with the revision as the ETag
+    async fn fetch(
+      key: &usize,
+      if_none_match: Option<&str>,
+    ) -> Result<Fetched<Article>, TestError> {
+      let revision = match REVISIONS.lock().unwrap().get(*key) {
+        Some(revision) => *revision,
+        None => return Ok(Fetched::NotFound),
+      };
+
+      let etag = format!("\"{}\"", revision);
+
+      if if_none_match.eq(&Some(etag.as_str())) {
+        NOT_MODIFIED.fetch_add(1, Ordering::SeqCst);
+        return Ok(Fetched::NotModified);
+      }
+
+      PARSED_BODIES.fetch_add(1, Ordering::SeqCst);
+
+      Ok(Fetched::Modified {
+        value: Article { id: *key, revision },
+        etag: Some(etag),
+      })
+    }
+  }
+
+  #[tokio::test]
+  async fn it_reuses_values_of_unmodified_resources() -> Result<(), TestError> {
+    use crate::LoadBy;
+
+    *REVISIONS.lock().unwrap() = vec![1, 1];
+
+    let article = Article::load_by(0_usize).await?;
+    assert_eq!(article, Some(Arc::new(Article { id: 0, revision: 1 })));
+    assert_eq!(EtagCache::<ArticleLoader>::etag(&0), Some("\"1\"".into()));
+
+    let article = Article::load_by(0_usize).await?;
+    assert_eq!(article, Some(Arc::new(Article { id: 0, revision: 1 })));
+    assert_eq!(PARSED_BODIES.load(Ordering::SeqCst), 1);
+    assert_eq!(NOT_MODIFIED.load(Ordering::SeqCst), 1);
+
+    REVISIONS.lock().unwrap()[0] = 2;
+
+    let article = Article::load_by(0_usize).await?;
+    assert_eq!(article, Some(Arc::new(Article { id: 0, revision: 2 })));
+    assert_eq!(EtagCache::<ArticleLoader>::etag(&0), Some("\"2\"".into()));
+    assert_eq!(PARSED_BODIES.load(Ordering::SeqCst), 2);
+
+    REVISIONS.lock().unwrap().truncate(1);
+
+    assert_eq!(Article::load_by(1_usize).await?, None);
+    assert_eq!(EtagCache::<ArticleLoader>::etag(&1), None);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,107 @@
+use crate::{
+  key::Key,
+  loader::{DataLoader, LocalLoader, StoreType},
+  task::{
+    task_handler_defaults, CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskError,
+    TaskHandler,
+  },
+};
+use futures_util::{stream, StreamExt};
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+/// The response to a request made by [`HttpLoader::fetch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fetched<V> {
+  /// `200 OK`, along with the `ETag` header if given
+  Modified { value: V, etag: Option<String> },
+  /// `304 Not Modified`, in response to `If-None-Match`
+  NotModified,
+  /// `404 Not Found`
+  NotFound,
+}
+
+/// An HTTP specific loader interface for REST APIs that fetch one key per request. Keys of a batch are fetched concurrently, up to [`HttpLoader::MAX_CONCURRENT_REQUESTS`] at a time
+#[async_trait::async_trait]
+pub trait HttpLoader: Sized + Send + Sync + 'static {
+  type Key: Key;
+  type Value: Send + Sync + Clone + 'static;
+  type Error: TaskError;
+  task_handler_defaults!(Self::Error);
+
+  const MAX_CONCURRENT_REQUESTS: usize = 16;
+
+  /// Fetch `key`, sending `If-None-Match` when given an ETag
+  async fn fetch(
+    key: &Self::Key,
+    if_none_match: Option<&str>,
+  ) -> Result<Fetched<Self::Value>, Self::Error>;
+}
+
+// Fetch each key by `fetch_key`, up to `T::MAX_CONCURRENT_REQUESTS` at a time
+pub(crate) async fn fetch_each<T, F, Fut>(
+  keys: Vec<T::Key>,
+  fetch_key: F,
+) -> HashMap<T::Key, Result<Option<Arc<T::Value>>, T::Error>>
+where
+  T: HttpLoader,
+  F: Fn(T::Key) -> Fut,
+  Fut: Future<Output = (T::Key, Result<Option<Arc<T::Value>>, T::Error>)>,
+{
+  stream::iter(keys)
+    .map(fetch_key)
+    .buffer_unordered(T::MAX_CONCURRENT_REQUESTS.max(1))
+    .collect()
+    .await
+}
+
+/// A [`TaskHandler`] fetching each key of a batch by [`HttpLoader::fetch`] without conditional requests. See [`crate::http::EtagCache`] for reusing values of unmodified resources
+pub struct HttpHandler<T: HttpLoader>(T);
+
+#[async_trait::async_trait]
+impl<T> TaskHandler for HttpHandler<T>
+where
+  T: HttpLoader,
+{
+  type Key = T::Key;
+  type Value = T::Value;
+  type Error = T::Error;
+  crate::forward_task_handler!(T);
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let assignment = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.validate::<Self>(),
+      assignment => assignment,
+    };
+
+    match assignment {
+      TaskAssignment::LoadBatch(task) => {
+        let outcomes = fetch_each::<T, _, _>(task.keys(), |key| async move {
+          let outcome = match T::fetch(&key, None).await {
+            Ok(Fetched::Modified { value, .. }) => Ok(Some(Arc::new(value))),
+            Ok(Fetched::NotModified | Fetched::NotFound) => Ok(None),
+            Err(err) => Err(err),
+          };
+
+          (key, outcome)
+        })
+        .await;
+
+        task.resolve_outcomes(outcomes, T::shutdown_error())
+      }
+      TaskAssignment::NoAssignment(receipt) => receipt,
+    }
+  }
+}
+
+impl<Loader, Store> LocalLoader<Store> for HttpHandler<Loader>
+where
+  Loader: HttpLoader + LocalLoader<Store>,
+  Store: StoreType,
+{
+  type Handler = <Loader as LocalLoader<Store>>::Handler;
+  fn loader() -> &'static std::thread::LocalKey<DataLoader<Self::Handler>> {
+    Loader::loader()
+  }
+}
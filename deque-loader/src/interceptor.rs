@@ -0,0 +1,158 @@
+//! Hooks into the lifecycle of each batch, from dispatch through to resolution, for concerns such as logging, auditing or cache warming
+//!
+//! ```rust
+//! UserLoader::loader().with(|loader| loader.add_interceptor(Arc::new(LoggingInterceptor)));
+//! ```
+
+use crate::Key;
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+/// Middleware for batches of a [`crate::loader::DataLoader`]. Interceptors registered via [`crate::loader::DataLoader::add_interceptor`] are called in the order registered
+#[async_trait::async_trait]
+pub trait BatchInterceptor<K: Key, V: Send + Sync + 'static, E: Send + Sync + 'static>:
+  Send + Sync + 'static
+{
+  /// Awaited upon each batch being assigned, before it's returned by [`crate::task::Task::get_assignment`] to be loaded from the backend
+  async fn before_dispatch(&self, _keys: &[K]) {}
+  /// Awaited once every request of a batch resolved by [`crate::task::Task::resolve`] has been resolved successfully
+  async fn after_resolve(&self, _results: &HashMap<K, Arc<V>>) {}
+  /// Called once every request of a batch resolved by [`crate::task::Task::resolve`] has been resolved as `error`
+  fn on_error(&self, _error: &E) {}
+}
+
+pub(crate) type Interceptors<K, V, E> = Vec<Arc<dyn BatchInterceptor<K, V, E>>>;
+
+/// Logs the lifecycle of each batch at debug level
+pub struct LoggingInterceptor;
+
+#[async_trait::async_trait]
+impl<K, V, E> BatchInterceptor<K, V, E> for LoggingInterceptor
+where
+  K: Key + Debug,
+  V: Send + Sync + 'static,
+  E: Send + Sync + Debug + 'static,
+{
+  async fn before_dispatch(&self, keys: &[K]) {
+    log::debug!("dispatching {} batch of {:?}", tynm::type_name::<V>(), keys);
+  }
+
+  async fn after_resolve(&self, results: &HashMap<K, Arc<V>>) {
+    log::debug!(
+      "resolved {} batch with {} values",
+      tynm::type_name::<V>(),
+      results.len()
+    );
+  }
+
+  fn on_error(&self, error: &E) {
+    log::debug!("{} batch failed: {:?}", tynm::type_name::<V>(), error);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::{DataStore, LocalLoader};
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use std::{sync::Mutex, time::Duration};
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<EvenLoader>")]
+  pub struct EvenLoader;
+
+  impl MockBackend for EvenLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      if keys.iter().all(|key| key % 2 == 0) {
+        Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+      } else {
+        Err(TestError("odd key"))
+      }
+    }
+  }
+
+  struct RecordingInterceptor {
+    name: &'static str,
+    events: Arc<Mutex<Vec<String>>>,
+  }
+
+  #[async_trait::async_trait]
+  impl BatchInterceptor<i32, i32, TestError> for RecordingInterceptor {
+    async fn before_dispatch(&self, keys: &[i32]) {
+      self
+        .events
+        .lock()
+        .unwrap()
+        .push(format!("{} before_dispatch {:?}", self.name, keys));
+    }
+
+    async fn after_resolve(&self, results: &HashMap<i32, Arc<i32>>) {
+      self
+        .events
+        .lock()
+        .unwrap()
+        .push(format!("{} after_resolve {}", self.name, results.len()));
+    }
+
+    fn on_error(&self, error: &TestError) {
+      self
+        .events
+        .lock()
+        .unwrap()
+        .push(format!("{} on_error {}", self.name, error));
+    }
+  }
+
+  #[tokio::test]
+  async fn it_calls_hooks_in_order() {
+    let events = Arc::new(Mutex::new(vec![]));
+
+    let loader = <EvenLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| {
+      loader.add_interceptor(Arc::new(RecordingInterceptor {
+        name: "first",
+        events: events.clone(),
+      }));
+
+      loader.add_interceptor(Arc::new(RecordingInterceptor {
+        name: "second",
+        events: events.clone(),
+      }));
+
+      loader.add_interceptor(Arc::new(LoggingInterceptor));
+    });
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(2)).recv().await,
+      Ok(Some(Arc::new(2)))
+    );
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(3)).recv().await,
+      Err(TestError("odd key"))
+    );
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(
+      *events.lock().unwrap(),
+      vec![
+        "first before_dispatch [2]",
+        "second before_dispatch [2]",
+        "first after_resolve 1",
+        "second after_resolve 1",
+        "first before_dispatch [3]",
+        "second before_dispatch [3]",
+        "first on_error odd key",
+        "second on_error odd key",
+      ]
+    );
+  }
+}
@@ -1,5 +1,162 @@
-use std::hash::Hash;
+use std::{hash::Hash, iter::FromIterator, slice, vec};
 
 /// Params to [`crate::loadable::LoadBy`]; typically [`i32`] or newtype wrapper
 pub trait Key: Send + Sync + Hash + Ord + Eq + Clone + 'static {}
 impl<T: Send + Sync + Hash + Ord + Eq + Clone + 'static> Key for T {}
+
+/// Batches of at most this many requests deduplicate keys with a [`KeySet`] rather than a [`std::collections::HashSet`], the allocation of a hash table being costly relative to searching so few keys
+pub const SMALL_BATCH_THRESHOLD: usize = 32;
+
+/// A set of unique keys in ascending order, backed by a sorted [`Vec`] that deduplicates upon insertion via binary search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySet<K: Key>(Vec<K>);
+
+impl<K: Key> KeySet<K> {
+  pub fn new() -> Self {
+    KeySet(vec![])
+  }
+
+  pub fn with_capacity(capacity: usize) -> Self {
+    KeySet(Vec::with_capacity(capacity))
+  }
+
+  /// Insert `key` in order, returning false if already present
+  pub fn insert(&mut self, key: K) -> bool {
+    match self.0.binary_search(&key) {
+      Ok(_) => false,
+      Err(idx) => {
+        self.0.insert(idx, key);
+        true
+      }
+    }
+  }
+
+  pub fn contains(&self, key: &K) -> bool {
+    self.0.binary_search(key).is_ok()
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> slice::Iter<'_, K> {
+    self.0.iter()
+  }
+}
+
+impl<K: Key> Default for KeySet<K> {
+  fn default() -> Self {
+    KeySet::new()
+  }
+}
+
+impl<K: Key> FromIterator<K> for KeySet<K> {
+  fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+    let iter = iter.into_iter();
+    let mut keys = KeySet::with_capacity(iter.size_hint().0);
+
+    for key in iter {
+      keys.insert(key);
+    }
+
+    keys
+  }
+}
+
+impl<K: Key> Extend<K> for KeySet<K> {
+  fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+    for key in iter {
+      self.insert(key);
+    }
+  }
+}
+
+impl<K: Key> From<Vec<K>> for KeySet<K> {
+  fn from(mut keys: Vec<K>) -> Self {
+    keys.sort_unstable();
+    keys.dedup();
+    KeySet(keys)
+  }
+}
+
+impl<K: Key> From<KeySet<K>> for Vec<K> {
+  fn from(keys: KeySet<K>) -> Self {
+    keys.0
+  }
+}
+
+impl<K: Key> IntoIterator for KeySet<K> {
+  type Item = K;
+  type IntoIter = vec::IntoIter<K>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.0.into_iter()
+  }
+}
+
+impl<'a, K: Key> IntoIterator for &'a KeySet<K> {
+  type Item = &'a K;
+  type IntoIter = slice::Iter<'a, K>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.0.iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use deque_loader_derive::Key;
+  use std::collections::HashSet;
+
+  #[derive(Key, Debug)]
+  #[repr(transparent)]
+  pub struct UserId(i32);
+
+  #[derive(Key, Debug)]
+  pub struct Slug {
+    value: String,
+  }
+
+  fn assert_key<K: super::Key>(_: &K) {}
+
+  #[test]
+  fn it_dedups_keys_in_order() {
+    let mut keys: super::KeySet<i32> = vec![3, 1, 3, 2].into_iter().collect();
+
+    assert_eq!(keys.len(), 3);
+    assert!(keys.contains(&2));
+    assert!(!keys.contains(&4));
+    assert!(!keys.insert(1));
+    assert!(keys.insert(0));
+    assert_eq!(Vec::from(keys.clone()), vec![0, 1, 2, 3]);
+    assert_eq!(super::KeySet::from(vec![2, 2, 1]), vec![1, 2].into());
+    assert_eq!(keys.into_iter().rev().collect::<Vec<_>>(), vec![3, 2, 1, 0]);
+  }
+
+  #[test]
+  fn it_derives_key() {
+    let user_id = UserId(7);
+
+    assert_key(&user_id);
+    assert!(UserId(1) < UserId(2));
+    assert_eq!(
+      vec![UserId(1), UserId(1), UserId(2)]
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .len(),
+      2
+    );
+    assert_eq!(user_id.clone(), UserId(7));
+
+    let slug = Slug {
+      value: String::from("deque-loader"),
+    };
+
+    assert_key(&slug);
+    assert_eq!(slug.clone().value, "deque-loader");
+  }
+}
@@ -0,0 +1,371 @@
+//! A [`tower_layer::Layer`] giving each HTTP request a [`RequestLoader`], such as for use as an axum [`Extension`](https://docs.rs/axum/0.6/axum/struct.Extension.html)
+//!
+//! Loads of concurrent requests are coalesced into the same batches because every [`RequestLoader`] enqueues onto the app-level thread local [`DataLoader`](crate::loader::DataLoader) of its loader; only the [`ContextCache`] is scoped to the request
+//!
+//! ```rust
+//! async fn get_user(
+//!   Path(user_id): Path<i32>,
+//!   Extension(users): Extension<RequestLoader<UserLoader>>,
+//! ) -> Result<Json<Option<User>>, StatusCode> {
+//!   let user = users
+//!     .load_by(user_id)
+//!     .await
+//!     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+//!
+//!   Ok(Json(user.map(|user| user.as_ref().to_owned())))
+//! }
+//!
+//! let app = Router::new()
+//!   .route("/users/:user_id", get(get_user))
+//!   .layer(DataLoaderLayer::<UserLoader>::new());
+//!
+//! axum::Server::bind(&addr).serve(app.into_make_service()).await?;
+//! ```
+
+use crate::{
+  loader::{DataStore, LocalLoader},
+  request::{ContextCache, WatchReceiver},
+  task::TaskHandler,
+};
+use std::{
+  marker::PhantomData,
+  sync::Arc,
+  task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type Handler<T> = <T as LocalLoader<DataStore>>::Handler;
+
+/// Loads against the thread local [`DataLoader`](crate::loader::DataLoader) of `T`, deduplicated by a [`ContextCache`] scoped to the current HTTP request
+pub struct RequestLoader<T: LocalLoader<DataStore>> {
+  cache: Arc<ContextCache<Handler<T>>>,
+}
+
+impl<T> Clone for RequestLoader<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  fn clone(&self) -> Self {
+    RequestLoader {
+      cache: self.cache.clone(),
+    }
+  }
+}
+
+impl<T> Default for RequestLoader<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  fn default() -> Self {
+    RequestLoader {
+      cache: Arc::new(ContextCache::new()),
+    }
+  }
+}
+
+impl<T> RequestLoader<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  pub fn load_by(
+    &self,
+    key: <Handler<T> as TaskHandler>::Key,
+  ) -> WatchReceiver<<Handler<T> as TaskHandler>::Value, <Handler<T> as TaskHandler>::Error> {
+    T::loader().with(|loader| loader.cached_load_by(key, self.cache.as_ref()))
+  }
+
+  /// The cache of loads made during this request
+  pub fn cache(&self) -> &ContextCache<Handler<T>> {
+    &self.cache
+  }
+}
+
+/// Extracts the [`RequestLoader`] inserted by [`DataLoaderLayer`], so that handlers can take `RequestLoader<T>` in place of `Extension<RequestLoader<T>>`. Rejects as [`ExtensionRejection`](axum::extract::rejection::ExtensionRejection) should the layer be missing
+///
+/// Loaders shared app-wide rather than scoped per request can instead be kept in state and extracted via [`State`](axum::extract::State) by implementing [`FromRef`](axum::extract::FromRef) for the state type. Loads then share one [`ContextCache`] for the lifetime of the state
+///
+/// ```rust
+/// #[derive(Clone)]
+/// struct AppState {
+///   users: RequestLoader<UserLoader>,
+/// }
+///
+/// impl FromRef<AppState> for RequestLoader<UserLoader> {
+///   fn from_ref(state: &AppState) -> Self {
+///     state.users.clone()
+///   }
+/// }
+///
+/// async fn get_user(Path(user_id): Path<i32>, users: RequestLoader<UserLoader>) -> String { ... }
+///
+/// async fn get_cached_user(
+///   Path(user_id): Path<i32>,
+///   State(users): State<RequestLoader<UserLoader>>,
+/// ) -> String { ... }
+///
+/// let app = Router::new()
+///   .route("/users/:user_id", get(get_user))
+///   .route("/cached/users/:user_id", get(get_cached_user))
+///   .layer(DataLoaderLayer::<UserLoader>::new())
+///   .with_state(AppState {
+///     users: RequestLoader::default(),
+///   });
+/// ```
+#[cfg(feature = "axum")]
+#[async_trait::async_trait]
+impl<T, S> axum::extract::FromRequestParts<S> for RequestLoader<T>
+where
+  T: LocalLoader<DataStore>,
+  S: Send + Sync,
+{
+  type Rejection = axum::extract::rejection::ExtensionRejection;
+
+  async fn from_request_parts(
+    parts: &mut http::request::Parts,
+    state: &S,
+  ) -> Result<Self, Self::Rejection> {
+    let axum::Extension(loader) =
+      axum::Extension::<RequestLoader<T>>::from_request_parts(parts, state).await?;
+
+    Ok(loader)
+  }
+}
+
+/// Inserts a new [`RequestLoader`] of `T` into the extensions of each request
+pub struct DataLoaderLayer<T: LocalLoader<DataStore>>(PhantomData<fn() -> T>);
+
+impl<T> DataLoaderLayer<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  pub fn new() -> Self {
+    DataLoaderLayer(PhantomData)
+  }
+}
+
+impl<T> Default for DataLoaderLayer<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  fn default() -> Self {
+    DataLoaderLayer::new()
+  }
+}
+
+impl<T> Clone for DataLoaderLayer<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  fn clone(&self) -> Self {
+    DataLoaderLayer::new()
+  }
+}
+
+impl<S, T> Layer<S> for DataLoaderLayer<T>
+where
+  T: LocalLoader<DataStore>,
+{
+  type Service = DataLoaderService<S, T>;
+
+  fn layer(&self, inner: S) -> Self::Service {
+    DataLoaderService {
+      inner,
+      loader: PhantomData,
+    }
+  }
+}
+
+/// The [`Service`] of [`DataLoaderLayer`]
+pub struct DataLoaderService<S, T: LocalLoader<DataStore>> {
+  inner: S,
+  loader: PhantomData<fn() -> T>,
+}
+
+impl<S, T> Clone for DataLoaderService<S, T>
+where
+  S: Clone,
+  T: LocalLoader<DataStore>,
+{
+  fn clone(&self) -> Self {
+    DataLoaderService {
+      inner: self.inner.clone(),
+      loader: PhantomData,
+    }
+  }
+}
+
+impl<S, T, B> Service<http::Request<B>> for DataLoaderService<S, T>
+where
+  S: Service<http::Request<B>>,
+  T: LocalLoader<DataStore>,
+{
+  type Response = S::Response;
+  type Error = S::Error;
+  type Future = S::Future;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+    req.extensions_mut().insert(RequestLoader::<T>::default());
+    self.inner.call(req)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use axum::{extract::Path, routing::get, Extension, Router};
+  use deque_loader_derive::Loader;
+  use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+  use tower::ServiceExt;
+
+  #[cfg(feature = "axum")]
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<ExtractedLoader>")]
+  pub struct ExtractedLoader;
+
+  #[cfg(feature = "axum")]
+  impl MockBackend for ExtractedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  static COALESCED_BATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<CoalescedLoader>")]
+  pub struct CoalescedLoader;
+
+  impl MockBackend for CoalescedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      COALESCED_BATCH_COUNT.fetch_add(1, Ordering::SeqCst);
+
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  async fn echo(
+    Path(key): Path<i32>,
+    Extension(loader): Extension<RequestLoader<CoalescedLoader>>,
+  ) -> String {
+    let value = loader.load_by(key).await.unwrap().unwrap();
+
+    format!("{}", value)
+  }
+
+  #[cfg(feature = "axum")]
+  #[tokio::test]
+  async fn it_extracts_request_loaders() {
+    use axum::extract::{FromRef, State};
+    use http::StatusCode;
+
+    #[derive(Clone)]
+    struct AppState {
+      shared: RequestLoader<ExtractedLoader>,
+    }
+
+    impl FromRef<AppState> for RequestLoader<ExtractedLoader> {
+      fn from_ref(state: &AppState) -> Self {
+        state.shared.clone()
+      }
+    }
+
+    async fn extracted(Path(key): Path<i32>, loader: RequestLoader<ExtractedLoader>) -> String {
+      let value = loader.load_by(key).await.unwrap().unwrap();
+
+      format!("{}", value)
+    }
+
+    async fn from_state(
+      Path(key): Path<i32>,
+      State(loader): State<RequestLoader<ExtractedLoader>>,
+    ) -> String {
+      let value = loader.load_by(key).await.unwrap().unwrap();
+
+      format!("{}", value)
+    }
+
+    let state = AppState {
+      shared: RequestLoader::default(),
+    };
+
+    let app = Router::new()
+      .route("/extracted/:key", get(extracted))
+      .route("/state/:key", get(from_state))
+      .layer(DataLoaderLayer::<ExtractedLoader>::new())
+      .with_state(state.clone());
+
+    for uri in ["/extracted/7", "/state/7"] {
+      let response = app
+        .clone()
+        .oneshot(
+          http::Request::get(uri)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+      assert_eq!(body, "7");
+    }
+
+    assert!(state.shared.cache().peek(&7).is_some());
+
+    let unlayered: Router = Router::new().route("/:key", get(extracted));
+
+    let response = unlayered
+      .oneshot(
+        http::Request::get("/7")
+          .body(axum::body::Body::empty())
+          .unwrap(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  #[tokio::test]
+  async fn it_coalesces_loads_across_requests() {
+    let app = Router::new()
+      .route("/:key", get(echo))
+      .layer(DataLoaderLayer::<CoalescedLoader>::new());
+
+    let responses = futures_util::future::join_all((0..4).map(|key| {
+      app.clone().oneshot(
+        http::Request::get(format!("/{}", key))
+          .body(axum::body::Body::empty())
+          .unwrap(),
+      )
+    }))
+    .await;
+
+    for (key, response) in responses.into_iter().enumerate() {
+      let body = hyper::body::to_bytes(response.unwrap().into_body())
+        .await
+        .unwrap();
+
+      assert_eq!(body, format!("{}", key));
+    }
+
+    assert_eq!(COALESCED_BATCH_COUNT.load(Ordering::SeqCst), 1);
+  }
+}
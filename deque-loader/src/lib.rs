@@ -56,26 +56,58 @@ pub extern crate async_trait;
 #[doc(hidden)]
 #[cfg(feature = "diesel-loader")]
 pub extern crate diesel_connection;
+#[doc(hidden)]
+pub extern crate inventory;
+pub extern crate rayon;
 extern crate self as deque_loader;
 #[doc(hidden)]
 pub extern crate static_init;
 
 pub use deque_loader_derive::*;
 
+pub mod backpressure;
 pub mod batch;
+mod buckets;
+pub mod concurrent;
+pub mod config;
+pub mod decorator;
+pub mod dedup;
+pub mod deferred;
 #[cfg(feature = "diesel-loader")]
 pub mod diesel;
+pub mod fan_out;
 #[cfg(feature = "graphql")]
 pub mod graphql;
+#[cfg(feature = "http-loader")]
+pub mod http;
+pub mod interceptor;
 mod key;
+#[cfg(feature = "axum-layer")]
+pub mod layer;
 #[doc(hidden)]
 pub mod loadable;
 pub mod loader;
+pub mod mapped;
+pub mod multi;
+pub mod observer;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+pub mod preemptive;
+#[cfg(feature = "prometheus-metrics")]
+pub mod prometheus_metrics;
+#[cfg(feature = "rate-limit")]
+pub mod rate_limited;
 #[cfg(feature = "redis-loader")]
 pub mod redis;
 #[doc(hidden)]
 pub mod request;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod stats;
 pub mod task;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod write;
 
-pub use key::Key;
+pub use key::{Key, KeySet, SMALL_BATCH_THRESHOLD};
 pub use loadable::LoadBy;
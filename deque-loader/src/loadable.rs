@@ -1,4 +1,4 @@
-use crate::{request::ContextCache, task::TaskHandler, Key};
+use crate::{request::KeyedCache, task::TaskHandler, Key};
 use std::sync::Arc;
 
 #[async_trait::async_trait]
@@ -12,8 +12,8 @@ where
   type Error: Send + Sync + Clone + 'static;
   /// Load a value by it's key in a batched load. If no [`TaskHandler`] is pending assignment, one will be scheduled. Even though this is scheduled up front, task assignment is deferred and will capture all loads that come thereafter; for a given request, it is guaranteed all loads will be enqueued before task assigment and batched optimally.
   async fn load_by(key: K) -> Result<Option<Arc<V>>, Self::Error>;
-  /// Load against a request contextual cache. Use [`register_cache_factory`] and [`crate::graphql::insert_loader_caches`] to hydrate Context<'_> and to define AsRef impl
-  async fn cached_load_by<RequestCache: Send + Sync + AsRef<ContextCache<T>>>(
+  /// Load against a request contextual cache. Use [`register_cache_factory`] and [`crate::graphql::insert_loader_caches`] to hydrate Context<'_> and to define AsRef impl, from which [`KeyedCache`] is implemented
+  async fn cached_load_by<RequestCache: Send + Sync + KeyedCache<T>>(
     key: K,
     request_cache: &RequestCache,
   ) -> Result<Option<Arc<V>>, Self::Error>;
@@ -21,6 +21,7 @@ where
 
 #[cfg(test)]
 mod tests {
+  use crate::testing::TestError;
   use crate::{
     loadable::LoadBy,
     task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
@@ -41,7 +42,7 @@ mod tests {
   impl TaskHandler for BatchLoader {
     type Key = i32;
     type Value = BatchSize;
-    type Error = ();
+    type Error = TestError;
     async fn handle_task(
       task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
     ) -> Task<CompletionReceipt> {
@@ -65,7 +66,7 @@ mod tests {
   }
 
   #[tokio::test]
-  async fn it_loads() -> Result<(), ()> {
+  async fn it_loads() -> Result<(), TestError> {
     let data = BatchSize::load_by(1_i32).await?;
 
     assert!(data.is_some());
@@ -74,7 +75,7 @@ mod tests {
   }
 
   #[tokio::test]
-  async fn it_auto_batches() -> Result<(), ()> {
+  async fn it_auto_batches() -> Result<(), TestError> {
     let a = BatchSize::load_by(2_i32);
 
     let b = BatchSize::load_by(3_i32);
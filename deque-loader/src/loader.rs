@@ -1,13 +1,188 @@
+#[cfg(feature = "prometheus-metrics")]
+use crate::prometheus_metrics::DataLoaderMetrics;
 use crate::{
-  request::{ContextCache, OneshotReceiver, Request, WatchReceiver},
-  task::{CompletionReceipt, LoadBatch, PendingAssignment, Task, TaskHandler},
+  backpressure::{Backpressure, BackpressureSignal},
+  fan_out::CacheWriter,
+  interceptor::{BatchInterceptor, Interceptors},
+  observer::{BatchObserver, LoadEvent, LoadObserver, Observers},
+  preemptive::PreemptiveLoads,
+  request::{
+    CacheCallback, ContextCache, DefaultFn, HandlerRequest, KeyedCache, LoadError, LoadProgress,
+    LoadResult, NegativeCache, OneshotReceiver, Request, WatchReceiver,
+  },
+  stats::{BatchCounters, BatchStats},
+  task::{
+    draining_flag, handle_with_startup_timeout, spawn_on, CompletionReceipt, LoadBatch,
+    PendingAssignment, Priority, PriorityQueues, QueuedCount, QueuedKeys, Task, TaskHandler,
+    ValidConstants,
+  },
 };
-use std::thread::LocalKey;
-use swap_queue::Worker;
+use futures_channel::mpsc;
+use futures_util::{FutureExt, Sink, Stream, StreamExt};
+use rayon::ThreadPool;
+use std::{
+  cell::{Cell, OnceCell, RefCell},
+  collections::HashMap,
+  fmt::Debug,
+  future::Future,
+  sync::{atomic::AtomicBool, Arc, Mutex, OnceLock},
+  thread::LocalKey,
+  time::Duration,
+};
+use swap_queue::{Stealer, Worker};
+use tokio::time::Instant;
+
+/// Log target of loads logged by [`DataLoader::set_debug_logging`]
+pub const DEBUG_TARGET: &str = "deque_loader::debug";
+
+fn debug_enabled() -> bool {
+  #[cfg(feature = "tracing")]
+  return tracing::enabled!(target: DEBUG_TARGET, tracing::Level::DEBUG);
+
+  #[cfg(not(feature = "tracing"))]
+  return log::log_enabled!(target: DEBUG_TARGET, log::Level::Debug);
+}
+
+fn debug_load<T: TaskHandler>(
+  key: &str,
+  cache_hit: bool,
+  result: Option<&LoadResult<T::Value, T::Error>>,
+  batch_id: Option<u64>,
+  elapsed: Duration,
+) {
+  let result = match result {
+    Some(Ok(Some(_))) => "some",
+    Some(Ok(None)) => "none",
+    Some(Err(_)) => "error",
+    None => "cancelled",
+  };
+
+  let batch_id = batch_id.map_or_else(|| String::from("none"), |batch_id| batch_id.to_string());
+
+  #[cfg(feature = "tracing")]
+  tracing::debug!(
+    target: DEBUG_TARGET,
+    "{} load key={} cache_hit={} result={} batch_id={} elapsed_us={}",
+    tynm::type_name::<T>(),
+    key,
+    cache_hit,
+    result,
+    batch_id,
+    elapsed.as_micros()
+  );
+
+  #[cfg(not(feature = "tracing"))]
+  log::debug!(
+    target: DEBUG_TARGET,
+    "{} load key={} cache_hit={} result={} batch_id={} elapsed_us={}",
+    tynm::type_name::<T>(),
+    key,
+    cache_hit,
+    result,
+    batch_id,
+    elapsed.as_micros()
+  );
+}
+
+// The id of the batch each load is dispatched in, set upon dispatch
+type BatchIds<K> = HashMap<K, Vec<Arc<OnceLock<u64>>>>;
+
+// Loads queued while debug logging, awaiting the id of the batch they're dispatched in
+struct DebugLoads<T: TaskHandler> {
+  debug_key: fn(&T::Key) -> String,
+  queued: Mutex<BatchIds<T::Key>>,
+}
+
+impl<T> DebugLoads<T>
+where
+  T: TaskHandler,
+{
+  // Log the load of `req` upon resolution, under the id of the batch it was dispatched in
+  fn watch(self: &Arc<Self>, req: &mut Request<T::Key, T::Value, T::Error>) {
+    if !debug_enabled() {
+      return;
+    }
+
+    let batch_id = Arc::new(OnceLock::new());
+
+    self
+      .queued
+      .lock()
+      .unwrap()
+      .entry(req.key().clone())
+      .or_default()
+      .push(batch_id.clone());
+
+    let debug = self.clone();
+    let started_at = Instant::now();
+
+    req.set_on_resolve(Box::new(move |key, result| {
+      // Resolved without being dispatched, such as by cancellation
+      if batch_id.get().is_none() {
+        debug.unqueue(key, &batch_id);
+      }
+
+      debug_load::<T>(
+        &(debug.debug_key)(key),
+        false,
+        result,
+        batch_id.get().copied(),
+        started_at.elapsed(),
+      );
+    }));
+  }
+
+  fn dispatched(&self, keys: &[T::Key], batch_id: u64) {
+    let mut queued = self.queued.lock().unwrap();
+
+    for key in keys {
+      for load in queued.remove(key).into_iter().flatten() {
+        load.set(batch_id).ok();
+      }
+    }
+  }
+
+  fn unqueue(&self, key: &T::Key, batch_id: &Arc<OnceLock<u64>>) {
+    let mut queued = self.queued.lock().unwrap();
+
+    if let Some(loads) = queued.get_mut(key) {
+      loads.retain(|load| !Arc::ptr_eq(load, batch_id));
 
-/// Each DataLoader is a thread local owner of a [`swap_queue::Worker`] queue for a given worker group
+      if loads.is_empty() {
+        queued.remove(key);
+      }
+    }
+  }
+}
+
+/// Each DataLoader is a thread local owner of a [`swap_queue::Worker`] queue per [`Priority`] for a given worker group
+///
+/// As loaders are thread local, settings such as [`DataLoader::set_backpressure`] or [`DataLoader::add_interceptor`] only configure the loader of the calling thread. Loaders of every thread are configured by applying settings within each thread, such as by `UserLoader::loader().with(|loader| loader.set_backpressure(1024, 256))`
 pub struct DataLoader<T: TaskHandler> {
   queue: Worker<Request<T::Key, T::Value, T::Error>>,
+  high_priority: Worker<Request<T::Key, T::Value, T::Error>>,
+  low_priority: Worker<Request<T::Key, T::Value, T::Error>>,
+  priority_queues: Arc<PriorityQueues<T::Key, T::Value, T::Error>>,
+  fan_out_writes: RefCell<Option<CacheCallback<T::Key, T::Value>>>,
+  fan_out: RefCell<Option<CacheCallback<T::Key, T::Value>>>,
+  queued_counts: RefCell<HashMap<Priority, QueuedCount>>,
+  queued_keys: OnceCell<RefCell<HashMap<Priority, QueuedKeys<T::Key>>>>,
+  debug: OnceCell<Arc<DebugLoads<T>>>,
+  debug_logging: Cell<bool>,
+  default_fn: RefCell<Option<DefaultFn<T::Key, T::Value>>>,
+  interceptors: RefCell<Interceptors<T::Key, T::Value, T::Error>>,
+  stats: std::cell::OnceCell<Arc<BatchCounters>>,
+  preemptive: OnceCell<PreemptiveLoads<T>>,
+  observers: RefCell<Option<Arc<Observers<T>>>>,
+  backpressure: Arc<Backpressure>,
+  draining: &'static AtomicBool,
+  startup_timeout: Cell<Option<Duration>>,
+  deduplicate_in_flight: Cell<bool>,
+  thread_pool: RefCell<Option<Arc<ThreadPool>>>,
+  #[cfg(feature = "global-cache")]
+  global_cache: std::cell::OnceCell<&'static ContextCache<T>>,
+  #[cfg(feature = "prometheus-metrics")]
+  metrics: RefCell<Option<Arc<DataLoaderMetrics>>>,
 }
 
 impl<T> DataLoader<T>
@@ -15,84 +190,1744 @@ where
   T: TaskHandler,
 {
   pub fn new(queue: Worker<Request<T::Key, T::Value, T::Error>>) -> Self {
-    DataLoader { queue }
+    let () = ValidConstants::<T>::ASSERTED;
+
+    DataLoader {
+      queue,
+      high_priority: Worker::new(),
+      low_priority: Worker::new(),
+      priority_queues: Arc::default(),
+      fan_out_writes: RefCell::new(None),
+      fan_out: RefCell::new(None),
+      queued_counts: RefCell::new(HashMap::new()),
+      queued_keys: OnceCell::new(),
+      debug: OnceCell::new(),
+      debug_logging: Cell::new(false),
+      default_fn: RefCell::new(None),
+      interceptors: RefCell::new(vec![]),
+      stats: std::cell::OnceCell::new(),
+      preemptive: OnceCell::new(),
+      observers: RefCell::new(None),
+      backpressure: Arc::default(),
+      draining: draining_flag::<T>(),
+      startup_timeout: Cell::new(None),
+      deduplicate_in_flight: Cell::new(false),
+      thread_pool: RefCell::new(None),
+      #[cfg(feature = "global-cache")]
+      global_cache: std::cell::OnceCell::new(),
+      #[cfg(feature = "prometheus-metrics")]
+      metrics: RefCell::new(None),
+    }
   }
 
-  pub fn load_by(&self, key: T::Key) -> OneshotReceiver<T::Value, T::Error> {
-    let (req, rx) = Request::new_oneshot(key);
+  /// Log each load queued by this loader hereafter and each cache hit of [`DataLoader::cached_load_by`] upon resolution with the key, whether it was a cache hit, the result, the id of the batch it was dispatched in and the time to resolve in microseconds. Logs are emitted via `tracing` when that feature is enabled and via `log` otherwise, at debug level under [`DEBUG_TARGET`]. Loads aren't observed at all unless that level is enabled
+  pub fn set_debug_logging(&self, enabled: bool)
+  where
+    T::Key: Debug,
+  {
+    if enabled {
+      // Batches are observed once for the lifetime of the loader, however often logging is toggled
+      self.debug.get_or_init(|| {
+        let debug = Arc::new(DebugLoads {
+          debug_key: |key| format!("{:?}", key),
+          queued: Mutex::new(HashMap::new()),
+        });
 
-    if let Some(stealer) = self.queue.push(req) {
-      let task = Task::new(stealer);
-      tokio::task::spawn(async move {
-        T::handle_task(task).await;
+        self.observe({
+          let debug = debug.clone();
+
+          move |event| {
+            if let LoadEvent::BatchDispatched { keys, batch_id } = event {
+              debug.dispatched(&keys, batch_id);
+            }
+          }
+        });
+
+        debug
       });
     }
 
-    rx
+    self.debug_logging.set(enabled);
+  }
+
+  fn debug(&self) -> Option<&Arc<DebugLoads<T>>> {
+    self.debug.get().filter(|_| self.debug_logging.get())
+  }
+
+  /// Shadow the keys of requests queued hereafter so that they can be snapshotted by [`DataLoader::peek_queued_keys`]. Tracking clones each key loaded and is intended for debugging
+  pub fn enable_queued_key_tracking(&self) {
+    self.queued_keys.get_or_init(Default::default);
+  }
+
+  /// Bound the time from a batch being queued to its task handler beginning assignment via [`Task::get_assignment`], including time spent acquiring connections or awaiting rate limits. Should this elapse the handler is abandoned and the requests of the batch cancelled, failing loads with [`crate::request::RecvCancelled`] rather than leaving them waiting on a hung backend. Unbounded by default, as the timer this arms for every batch isn't free
+  pub fn set_worker_startup_timeout(&self, startup_timeout: Option<Duration>) {
+    self.startup_timeout.set(startup_timeout);
+  }
+
+  /// Share loads of keys in flight with every other loader of this handler deduplicating in flight via [`crate::dedup::RequestDeduplicator`], at the cost of contention on a process-wide map. Only batches dispatched hereafter are deduplicated, and only against keys in flight of loaders likewise deduplicating
+  pub fn enable_in_flight_deduplication(&self) {
+    self.deduplicate_in_flight.set(true);
+  }
+
+  /// Resolve batches of this loader, write values of [`DataLoader::fan_out`] and warm values of [`DataLoader::cold_start_warmup`] on `pool` rather than on the global rayon thread pool, isolating CPU-heavy loaders from the pool shared by all other loaders. A pool is shared by the loaders of every thread by setting the same pool on each
+  pub fn set_thread_pool(&self, pool: Arc<ThreadPool>) {
+    self.thread_pool.replace(Some(pool));
+    self.spawn_fan_out();
+  }
+
+  /// Signal overload once the loads of this thread local loader pending resolution reach `high_water_mark`, until they fall to `low_water_mark`. Loads queued hereafter are counted, and signals obtained beforehand observe the new water marks. See [`DataLoader::backpressure_signal`]
+  pub fn set_backpressure(&self, high_water_mark: usize, low_water_mark: usize) {
+    assert!(
+      low_water_mark < high_water_mark,
+      "the low water mark must be below the high water mark"
+    );
+
+    self
+      .backpressure
+      .set_water_marks(high_water_mark, low_water_mark);
+  }
+
+  /// A handle on whether this loader is overloaded, for upstream code to shed load rather than queue loads without bound. Loaders without water marks set via [`DataLoader::set_backpressure`] are never overloaded
+  pub fn backpressure_signal(&self) -> BackpressureSignal {
+    self.backpressure.signal()
+  }
+
+  /// Add an interceptor to the lifecycle of batches dispatched from this loader hereafter, called after those added before it
+  pub fn add_interceptor(
+    &self,
+    interceptor: Arc<dyn BatchInterceptor<T::Key, T::Value, T::Error>>,
+  ) {
+    self.interceptors.borrow_mut().push(interceptor);
+  }
+
+  /// Observe the [`LoadEvent`]s of this loader hereafter, called synchronously for each event from the thread it occurs on: the thread loading for cache events, and the thread resolving the batch for batch resolution
+  pub fn observe<F>(&self, f: F)
+  where
+    F: Fn(LoadEvent<T>) + Send + Sync + 'static,
+  {
+    self.add_observer(Arc::new(f));
+  }
+
+  /// Add an observer of the [`LoadEvent`]s of this loader hereafter, called after those added before it
+  pub fn add_observer(&self, observer: LoadObserver<T>) {
+    let mut observers = self.observers.borrow_mut();
+
+    let mut registered = observers
+      .take()
+      .map_or_else(Vec::new, |observers| observers.0.clone());
+
+    registered.push(observer);
+    *observers = Some(Arc::new(Observers(registered)));
+  }
+
+  fn has_observers(&self) -> bool {
+    self.observers.borrow().is_some()
+  }
+
+  fn emit(&self, event: LoadEvent<T>) {
+    // Released before emitting, so that observers may themselves register observers
+    let observers = self.observers.borrow().clone();
+
+    if let Some(observers) = observers {
+      observers.emit(event);
+    }
+  }
+
+  fn batch_observer(&self) -> Option<Arc<dyn BatchObserver<T::Key>>> {
+    self
+      .observers
+      .borrow()
+      .clone()
+      .map(|observers| observers as Arc<dyn BatchObserver<T::Key>>)
+  }
+
+  /// Experimental: learn which keys are loaded together hereafter and preemptively load them, holding each preemptive load for `ttl` awaiting consumption; see [`crate::preemptive`]. Enabling again only replaces the `ttl` of loads made thereafter
+  pub fn enable_preemptive_loading(&self, ttl: Duration) {
+    self
+      .preemptive
+      .get_or_init(|| {
+        let preemptive = PreemptiveLoads::new(ttl);
+
+        self.add_interceptor(preemptive.co_occurrences.clone());
+        preemptive
+      })
+      .set_ttl(ttl);
+  }
+
+  /// Resolve loads of absent keys as `default` rather than `None`. See [`DataLoader::set_default_fn`]
+  pub fn set_default_value(&self, default: Arc<T::Value>) {
+    self.set_default_fn(move |_| default.clone());
+  }
+
+  /// Resolve loads of absent keys made hereafter as the value of `f` for that key rather than `None`, replacing any default previously set. Defaults apply to the loads of this loader that don't go through a cache, namely [`DataLoader::load_by`], [`DataLoader::load`] and their variants. Defaults are never stored in a [`ContextCache`] nor written by cache callbacks such as [`DataLoader::fan_out`], so each load of an absent key hits the backend again
+  pub fn set_default_fn<F>(&self, f: F)
+  where
+    F: Fn(&T::Key) -> Arc<T::Value> + Send + Sync + 'static,
+  {
+    *self.default_fn.borrow_mut() = Some(Arc::new(f));
+  }
+
+  fn with_default(
+    &self,
+    mut req: Request<T::Key, T::Value, T::Error>,
+  ) -> Request<T::Key, T::Value, T::Error> {
+    if let Some(default_fn) = self.default_fn.borrow().as_ref() {
+      req.set_default_fn(default_fn.clone());
+    }
+
+    req
+  }
+
+  // Loads queued are logged upon resolution by the request itself, whereas cache hits await the entry they hit
+  fn debug_cache_hit(&self, key: &T::Key, rx: &WatchReceiver<T::Value, T::Error>) {
+    let debug_key = match self.debug() {
+      Some(debug) if debug_enabled() => debug.debug_key,
+      _ => return,
+    };
+
+    let key = debug_key(key);
+    let rx = rx.clone();
+    let started_at = Instant::now();
+
+    tokio::task::spawn(async move {
+      let result = rx.try_recv().await.ok();
+
+      debug_load::<T>(&key, true, result.as_ref(), None, started_at.elapsed());
+    });
   }
 
-  pub fn cached_load_by<RequestCache: Send + Sync + AsRef<ContextCache<T>>>(
+  /// Record request outcomes, batch sizes, batch durations and cache ratios of loads made by this loader hereafter, replacing any metrics previously set
+  #[cfg(feature = "prometheus-metrics")]
+  pub fn set_metrics(&self, metrics: Arc<DataLoaderMetrics>) {
+    *self.metrics.borrow_mut() = Some(metrics);
+  }
+
+  /// Write every value loaded by this thread local loader into each of `caches`, other than those for which [`TaskHandler::should_cache`] is false. Writes occur after resolution on the thread pool set by [`DataLoader::set_thread_pool`], or otherwise on the global rayon thread pool, and are in addition to any caching done by the task handler. Replaces previously registered caches
+  pub fn fan_out<C>(&self, caches: Vec<C>)
+  where
+    C: CacheWriter<T::Key, T::Value>,
+  {
+    let fan_out_writes: CacheCallback<T::Key, T::Value> = Arc::new(move |key, value| {
+      for cache in caches.iter() {
+        cache.write(key, value.clone());
+      }
+    });
+
+    self.fan_out_writes.replace(Some(fan_out_writes));
+    self.spawn_fan_out();
+  }
+
+  // Have the writes of DataLoader::fan_out spawn on the thread pool currently set
+  fn spawn_fan_out(&self) {
+    let fan_out = self.fan_out_writes.borrow().clone().map(|writes| {
+      let pool = self.thread_pool.borrow().clone();
+
+      let fan_out: CacheCallback<T::Key, T::Value> = Arc::new(move |key, value| {
+        if !T::should_cache(key, value) {
+          return;
+        }
+
+        let writes = writes.clone();
+        let key = key.to_owned();
+        let value = value.to_owned();
+
+        spawn_on(pool.as_deref(), move || writes(&key, &value));
+      });
+
+      fan_out
+    });
+
+    self.fan_out.replace(fan_out);
+  }
+
+  fn priority_queue(&self, priority: Priority) -> &Worker<Request<T::Key, T::Value, T::Error>> {
+    match priority {
+      Priority::High => &self.high_priority,
+      Priority::Normal => &self.queue,
+      Priority::Low => &self.low_priority,
+    }
+  }
+
+  /// A point-in-time snapshot of the keys of requests queued by this loader that have yet to be taken by a task handler, in order of priority. This clones every queued key while holding a lock contended by task handlers taking batches, and so is O(n) and intended for debugging rather than hot paths. Empty unless enabled by [`DataLoader::enable_queued_key_tracking`]
+  pub fn peek_queued_keys(&self) -> Vec<T::Key> {
+    let queued_keys = match self.queued_keys.get() {
+      Some(queued_keys) => queued_keys.borrow(),
+      None => return vec![],
+    };
+
+    vec![Priority::High, Priority::Normal, Priority::Low]
+      .into_iter()
+      .filter_map(|priority| queued_keys.get(&priority))
+      .flat_map(|queued_keys| queued_keys.snapshot())
+      .collect()
+  }
+
+  // Count the requests pushed onto a queue so that pending assignments can be counted, starting anew with each batch
+  fn track_queued_count(
+    &self,
+    priority: Priority,
+    stealer: Option<&Stealer<HandlerRequest<T>>>,
+  ) -> Option<QueuedCount> {
+    let mut queued_counts = self.queued_counts.borrow_mut();
+
+    match stealer {
+      Some(Stealer::Taker(_)) => {
+        let queued_count = QueuedCount::new();
+        queued_counts.insert(priority, queued_count.clone());
+        Some(queued_count)
+      }
+      // Batches owned are counted by their length
+      Some(Stealer::Owner(_)) => {
+        queued_counts.remove(&priority);
+        None
+      }
+      None => {
+        if let Some(queued_count) = queued_counts.get(&priority) {
+          queued_count.increment();
+        }
+        None
+      }
+    }
+  }
+
+  // Shadow the keys pushed onto a queue so that they can be peeked, starting anew with each batch
+  fn track_queued_keys(
     &self,
+    priority: Priority,
     key: T::Key,
-    request_cache: &RequestCache,
-  ) -> WatchReceiver<T::Value, T::Error> {
-    let (rx, req) = request_cache.as_ref().get_or_create(&key);
+    stealer: Option<&Stealer<HandlerRequest<T>>>,
+  ) -> Option<QueuedKeys<T::Key>> {
+    let mut queued_keys = self.queued_keys.get()?.borrow_mut();
+
+    match stealer {
+      Some(Stealer::Taker(_)) => {
+        let batch = QueuedKeys::new(vec![key]);
+        queued_keys.insert(priority, batch.clone());
+        Some(batch)
+      }
+      // Auto-batched queues at capacity hand off ownership of the batch in progress
+      Some(Stealer::Owner(_)) => queued_keys.remove(&priority),
+      None => {
+        if let Some(batch) = queued_keys.get(&priority) {
+          batch.push(key);
+        }
+        None
+      }
+    }
+  }
+
+  fn enqueue(&self, req: Request<T::Key, T::Value, T::Error>) {
+    self.enqueue_with_priority(req, T::DEFAULT_PRIORITY);
+  }
+
+  fn enqueue_with_priority(
+    &self,
+    mut req: Request<T::Key, T::Value, T::Error>,
+    priority: Priority,
+  ) {
+    if let Some(fan_out) = self.fan_out.borrow().as_ref() {
+      req.set_cache_cb(fan_out.clone());
+    }
+
+    if self.backpressure.is_enabled() {
+      req.set_pending(self.backpressure.enqueued());
+    }
+
+    if let Some(debug) = self.debug() {
+      debug.watch(&mut req);
+    }
+
+    let key = self.queued_keys.get().map(|_| req.key().to_owned());
+    let stealer = self.priority_queue(priority).push(req);
+    let queued_count = self.track_queued_count(priority, stealer.as_ref());
+    let queued_keys = key.and_then(|key| self.track_queued_keys(priority, key, stealer.as_ref()));
+
+    if let Some(stealer) = stealer {
+      self.spawn_task(Task::new(stealer), priority, queued_count, queued_keys);
+    }
+  }
+
+  // Configure a task of a batch queued at `priority` by the settings of this loader and spawn its task handler
+  fn spawn_task(
+    &self,
+    mut task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+    priority: Priority,
+    queued_count: Option<QueuedCount>,
+    queued_keys: Option<QueuedKeys<T::Key>>,
+  ) {
+    if let Some(queued_count) = queued_count {
+      task = task.with_queued_count(queued_count);
+    }
+
+    if let Some(queued_keys) = queued_keys {
+      task = task.with_queued_keys(queued_keys);
+    }
+
+    let interceptors = self.interceptors.borrow();
+
+    if !interceptors.is_empty() {
+      task = task.with_interceptors(interceptors.clone());
+    }
+
+    if let Some(stats) = self.stats.get() {
+      task = task.with_stats(stats.clone());
+    }
+
+    task = task
+      .with_observer(self.batch_observer())
+      .with_draining(self.draining)
+      .with_startup_timeout(self.startup_timeout.get())
+      .with_in_flight_deduplication(self.deduplicate_in_flight.get())
+      .with_thread_pool(self.thread_pool.borrow().clone())
+      .with_priority(priority, self.priority_queues.clone());
+
+    #[cfg(feature = "prometheus-metrics")]
+    let task = task.with_metrics(self.metrics.borrow().clone());
+
+    let handle_task = async move {
+      handle_with_startup_timeout::<T>(task).await;
+    };
+
+    // Batches are handled within the span of the load that started them
+    #[cfg(feature = "tracing")]
+    let handle_task = tracing::Instrument::in_current_span(handle_task);
+
+    tokio::task::spawn(handle_task);
+  }
+
+  pub fn load_by(&self, key: T::Key) -> OneshotReceiver<T::Value, T::Error> {
+    if let Some(preemptive) = self.preemptive.get() {
+      for key in preemptive.co_occurrences.predict(&key) {
+        self.preemptive_load(key);
+      }
+
+      if let Some(preempted) = preemptive.take(&key) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
 
-    if let Some(req) = req {
-      if let Some(stealer) = self.queue.push(req) {
-        let task = Task::new(stealer);
         tokio::task::spawn(async move {
-          T::handle_task(task).await;
+          if let Ok(result) = preempted.try_recv().await {
+            tx.send(result).ok();
+          }
         });
+
+        return rx.into();
       }
     }
 
+    let (req, rx) = Request::new_oneshot(key);
+
+    self.enqueue(self.with_default(req));
+
     rx
   }
 
-  pub fn schedule_assignment(
+  /// Experimental: load a key in the background at [`Priority::Low`], to be consumed by the next [`DataLoader::load_by`] of the key within the `ttl` of [`DataLoader::enable_preemptive_loading`]. Has no effect unless enabled
+  pub fn preemptive_load(&self, key: T::Key) {
+    if let Some(preemptive) = self.preemptive.get() {
+      if !preemptive.contains(&key) {
+        let (req, rx) = Request::new_watch(key.clone());
+
+        self.enqueue_with_priority(self.with_default(req), Priority::Low);
+        preemptive.insert(key, rx);
+      }
+    }
+  }
+
+  /// Load a value by key with `metadata` attached to the request, for the task handler to retrieve via [`Task::request_metadata`]. Loads of the same key made with differing metadata are still batched together, in which case the task handler observes the metadata of the first
+  ///
+  /// ```rust
+  /// #[derive(Clone, Copy, Debug)]
+  /// pub struct TraceId(u64);
+  ///
+  /// // Within the request handler
+  /// let trace_id = TraceId(request.headers().trace_id());
+  /// let user = UserLoader::loader().with(|loader| loader.load_with_metadata(user_id, trace_id)).await?;
+  ///
+  /// // Within the task handler
+  /// TaskAssignment::LoadBatch(task) => {
+  ///   for key in task.keys() {
+  ///     if let Some(TraceId(trace_id)) = task.request_metadata::<TraceId>(&key) {
+  ///       log::info!("trace_id={} loading user {:?}", trace_id, key);
+  ///     }
+  ///   }
+  ///
+  ///   task.resolve(load_users(task.keys()).await)
+  /// }
+  /// ```
+  pub fn load_with_metadata<M>(
     &self,
-    task: Task<LoadBatch<T::Key, T::Value, T::Error>>,
-  ) -> Task<CompletionReceipt> {
-    let Task(LoadBatch { requests }) = task;
+    key: T::Key,
+    metadata: M,
+  ) -> OneshotReceiver<T::Value, T::Error>
+  where
+    M: Send + Sync + 'static,
+  {
+    let (mut req, rx) = Request::new_oneshot(key);
 
-    let mut requests = requests.into_iter();
+    req.set_metadata(Arc::new(metadata));
 
-    while let Some(req) = requests.next() {
-      if let Some(stealer) = self.queue.push(req) {
-        let task = Task(PendingAssignment {
-          stealer,
-          requests: requests.collect(),
-        });
+    self.enqueue(self.with_default(req));
 
-        tokio::task::spawn(async move {
-          T::handle_task(task).await;
-        });
+    rx
+  }
 
-        return Task::completion_receipt();
+  /// Load a value by key, batched only with loads of the same priority and assigned ahead of batches of lower priority as per [`Priority`]
+  pub fn load_with_priority(
+    &self,
+    key: T::Key,
+    priority: Priority,
+  ) -> OneshotReceiver<T::Value, T::Error> {
+    let (req, rx) = Request::new_oneshot(key);
+
+    self.enqueue_with_priority(self.with_default(req), priority);
+
+    rx
+  }
+
+  /// Load a value by key, distinguishing task handler errors from loads cancelled by the task handler dropping the request. [`LoadError`] implements [`std::error::Error`] whenever the error of the task handler does, with it as the source, and so converts into `anyhow::Error` or `Box<dyn Error>` with `?`
+  ///
+  /// ```rust
+  /// async fn get_user(user_id: UserId) -> anyhow::Result<Option<User>> {
+  ///   let user = UserLoader::loader()
+  ///     .with(|loader| loader.load_result(user_id))
+  ///     .await?;
+  ///
+  ///   Ok(user.map(|user| user.as_ref().to_owned()))
+  /// }
+  /// ```
+  pub fn load_result(
+    &self,
+    key: T::Key,
+  ) -> impl Future<Output = Result<Option<Arc<T::Value>>, LoadError<T::Error>>> {
+    let rx = self.load_by(key);
+
+    async move { rx.try_recv().await?.map_err(LoadError::HandlerError) }
+  }
+
+  /// Load a value by key, failing with [`LoadError::Deadline`] should the load not resolve by `deadline`. The batch isn't cancelled and continues to load on behalf of other requests, but as the receiver is dropped upon the deadline elapsing this request is skipped during resolution
+  pub fn load_until(
+    &self,
+    key: T::Key,
+    deadline: Instant,
+  ) -> impl Future<Output = Result<Option<Arc<T::Value>>, LoadError<T::Error>>> {
+    let rx = self.load_by(key);
+
+    async move {
+      tokio::select! {
+        result = rx.try_recv() => result?.map_err(LoadError::HandlerError),
+        _ = tokio::time::sleep_until(deadline) => Err(LoadError::Deadline),
       }
     }
+  }
 
-    Task::completion_receipt()
+  /// Load a value by key and map it by `transform`, such as from a database model into the type exposed by an API. Absent values resolve as `None` without calling `transform`
+  ///
+  /// ```rust
+  /// let user: Option<UserDto> = UserLoader::loader()
+  ///   .with(|loader| loader.load_then(user_id, |user| UserDto::from(user.as_ref())))
+  ///   .await?;
+  /// ```
+  pub fn load_then<F, U>(
+    &self,
+    key: T::Key,
+    transform: F,
+  ) -> impl Future<Output = Result<Option<U>, T::Error>>
+  where
+    F: FnOnce(Arc<T::Value>) -> U + Send + 'static,
+    U: Send + 'static,
+  {
+    let rx = self.load_by(key);
+
+    async move { Ok(rx.recv().await?.map(transform)) }
   }
-}
 
-impl<T> Default for DataLoader<T>
-where
-  T: TaskHandler,
-{
-  fn default() -> Self {
-    let queue = Worker::new();
-    DataLoader { queue }
+  /// As with [`DataLoader::load_then`], but for transformations that are themselves async, such as those loading related records
+  ///
+  /// ```rust
+  /// let user: Option<UserDto> = UserLoader::loader()
+  ///   .with(|loader| {
+  ///     loader.load_then_async(user_id, |user| async move {
+  ///       let avatar_url = avatars::signed_url(user.avatar_id).await;
+  ///       UserDto::new(user.as_ref(), avatar_url)
+  ///     })
+  ///   })
+  ///   .await?;
+  /// ```
+  pub fn load_then_async<F, Fut, U>(
+    &self,
+    key: T::Key,
+    transform: F,
+  ) -> impl Future<Output = Result<Option<U>, T::Error>>
+  where
+    F: FnOnce(Arc<T::Value>) -> Fut + Send + 'static,
+    Fut: Future<Output = U> + Send,
+    U: Send + 'static,
+  {
+    let rx = self.load_by(key);
+
+    async move {
+      match rx.recv().await? {
+        Some(value) => Ok(Some(transform(value).await)),
+        None => Ok(None),
+      }
+    }
   }
-}
 
-pub trait StoreType {}
-pub struct CacheStore;
-impl StoreType for CacheStore {}
-pub struct DataStore;
-impl StoreType for DataStore {}
-pub trait LocalLoader<T: StoreType>: Sized + Send + Sync + 'static {
-  type Handler: TaskHandler;
-  fn loader() -> &'static LocalKey<DataLoader<Self::Handler>>;
+  /// Load a value by key, returning a [`LoadProgress`] that can be awaited or polled synchronously
+  pub fn load(&self, key: T::Key) -> LoadProgress<T> {
+    let (req, rx) = Request::new_watch(key);
+
+    self.enqueue(self.with_default(req));
+
+    LoadProgress::new(rx)
+  }
+
+  /// A [`Sink`] of keys to load and a [`Stream`] of results paired with their key, loading with the thread local loader of whichever thread polls the stream. Keys sent together are batched together, and results are emitted in the order loads complete. At most `capacity` keys are buffered and `capacity` loads are in flight at once, beyond which sending waits for the stream to be polled
+  ///
+  /// ```rust
+  /// let (mut sink, stream) = DataLoader::sink_stream(UserLoader::loader(), 64);
+  ///
+  /// tokio::task::spawn(async move {
+  ///   sink.send_all(&mut stream::iter(user_ids).map(Ok)).await
+  /// });
+  ///
+  /// let users: Vec<_> = stream.collect().await;
+  /// ```
+  #[allow(clippy::type_complexity)]
+  pub fn sink_stream(
+    loader: &'static LocalKey<DataLoader<T>>,
+    capacity: usize,
+  ) -> (
+    impl Sink<T::Key, Error = mpsc::SendError>,
+    impl Stream<Item = (T::Key, Result<Option<Arc<T::Value>>, T::Error>)>,
+  ) {
+    let (tx, rx) = mpsc::channel::<T::Key>(capacity);
+
+    let stream = rx
+      .map(move |key| {
+        let rx = loader.with(|loader| loader.load_by(key.clone()));
+        async move { (key, rx.recv().await) }
+      })
+      .buffer_unordered(capacity.max(1));
+
+    (tx, stream)
+  }
+
+  pub fn cached_load_by<RequestCache: Send + Sync + KeyedCache<T>>(
+    &self,
+    key: T::Key,
+    request_cache: &RequestCache,
+  ) -> WatchReceiver<T::Value, T::Error> {
+    let (rx, req) = request_cache.cache_for(&key).get_or_create(&key);
+
+    if req.is_none() {
+      self.debug_cache_hit(&key, &rx);
+    }
+
+    if self.has_observers() {
+      self.emit(match req {
+        Some(_) => LoadEvent::CacheMiss { key: key.clone() },
+        None => LoadEvent::CacheHit { key: key.clone() },
+      });
+    }
+
+    #[cfg(feature = "prometheus-metrics")]
+    if let Some(metrics) = self.metrics.borrow().as_ref() {
+      metrics.observe_cache_lookup(req.is_none());
+    }
+
+    if let Some(req) = req {
+      self.enqueue(req);
+    }
+
+    rx
+  }
+
+  /// Queue loads of keys expected to be needed soon without awaiting them, such that the batch is dispatched ahead of and shared by subsequent [`DataLoader::cached_load_by`] calls against the same request cache
+  ///
+  /// ```rust
+  /// loader.batch_insert_cold_keys(&user_ids, ctx);
+  /// render_header(ctx).await?;
+  /// let users = join_all(user_ids.into_iter().map(|id| loader.cached_load_by(id, ctx).recv())).await;
+  /// ```
+  pub fn batch_insert_cold_keys<RequestCache: Send + Sync + KeyedCache<T>>(
+    &self,
+    keys: &[T::Key],
+    request_cache: &RequestCache,
+  ) {
+    for key in keys.iter() {
+      self.cached_load_by(key.to_owned(), request_cache);
+    }
+  }
+
+  /// Populate a request cache with values loaded ahead of time, such as upon restart from a snapshot exported with [`ContextCache::export_snapshot`], such that loads thereafter resolve from the cache as though the values had been loaded. Values replace any entries for their keys and are inserted in parallel via rayon when there are many. As with values loaded normally, warmed values are retained for the lifetime of the cache, costing the size of each key and value plus a channel per key; warming a global cache therefore retains every value for the lifetime of the program
+  ///
+  /// ```rust
+  /// let snapshot: Vec<(i32, User)> = read_snapshot("users.bin")?;
+  ///
+  /// User::loader()
+  ///   .with(|loader| loader.cold_start_warmup(snapshot, ContextCache::global()))
+  ///   .await;
+  /// ```
+  pub fn cold_start_warmup<I, RequestCache>(
+    &self,
+    iter: I,
+    request_cache: &RequestCache,
+  ) -> impl Future<Output = ()> + Send + 'static
+  where
+    I: IntoIterator<Item = (T::Key, T::Value)>,
+    RequestCache: Send + Sync + KeyedCache<T>,
+  {
+    let pool = self.thread_pool.borrow().clone();
+    let mut shards: Vec<(&ContextCache<T>, Vec<_>)> = vec![];
+
+    for (key, value) in iter.into_iter() {
+      let cache = request_cache.cache_for(&key);
+
+      match shards
+        .iter_mut()
+        .find(|(shard, _)| std::ptr::eq(*shard, cache))
+      {
+        Some((_, values)) => values.push((key, value)),
+        None => shards.push((cache, vec![(key, value)])),
+      }
+    }
+
+    futures_util::future::join_all(
+      shards
+        .into_iter()
+        .map(|(cache, values)| cache.warm_all(values, pool.clone())),
+    )
+    .map(|_| ())
+  }
+
+  /// Load against a request cache, creating the value with `f` should the key not exist. Concurrent callers racing on an absent key share the value created by a single call of `f`, which is then warmed into the cache for subsequent loads
+  ///
+  /// ```rust
+  /// let user = loader
+  ///   .load_or_insert_with(user_id, ctx, || create_user(user_id))
+  ///   .await?;
+  /// ```
+  pub fn load_or_insert_with<'a, RequestCache, F, Fut>(
+    &self,
+    key: T::Key,
+    request_cache: &'a RequestCache,
+    f: F,
+  ) -> impl Future<Output = Result<Arc<T::Value>, T::Error>> + 'a
+  where
+    RequestCache: Send + Sync + KeyedCache<T>,
+    F: FnOnce() -> Fut + 'a,
+    Fut: Future<Output = Result<T::Value, T::Error>> + 'a,
+  {
+    let rx = self.cached_load_by(key.clone(), request_cache);
+
+    async move {
+      match rx.recv().await? {
+        Some(value) => Ok(value),
+        None => {
+          request_cache
+            .cache_for(&key)
+            .get_or_insert_with(key, f)
+            .await
+        }
+      }
+    }
+  }
+
+  /// As with [`DataLoader::load_or_insert_with`], but computing the value of a key confirmed absent by `compute`, which runs as a task of its own so that compute-heavy fallbacks progress independently of the caller. Concurrent callers racing on an absent key share the value of a single computation, which is warmed into the cache such that subsequent loads see it
+  ///
+  /// ```rust
+  /// let route = loader
+  ///   .load_or_compute(trip_id, ctx, |trip_id| async move { plan_route(trip_id).await })
+  ///   .await?;
+  /// ```
+  pub fn load_or_compute<'a, RequestCache, F, Fut>(
+    &self,
+    key: T::Key,
+    request_cache: &'a RequestCache,
+    compute: F,
+  ) -> impl Future<Output = Result<Arc<T::Value>, T::Error>> + 'a
+  where
+    RequestCache: Send + Sync + KeyedCache<T>,
+    F: FnOnce(T::Key) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T::Value, T::Error>> + Send + 'static,
+  {
+    let computed_key = key.clone();
+
+    self.load_or_insert_with(key, request_cache, move || async move {
+      match tokio::task::spawn(compute(computed_key)).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+      }
+    })
+  }
+
+  /// Reload a key in the background, returning the result currently cached, or `Ok(None)` when not yet resolved, along with a receiver of the reload. The key is invalidated unconditionally so that loads thereafter share the reload, allowing stale values to be used while revalidating
+  ///
+  /// ```rust
+  /// let (stale, rx) = loader.watch_reload(user_id, ctx);
+  /// render(stale?);
+  /// render(rx.recv().await?);
+  /// ```
+  #[allow(clippy::type_complexity)]
+  pub fn watch_reload<RequestCache: Send + Sync + KeyedCache<T>>(
+    &self,
+    key: T::Key,
+    request_cache: &RequestCache,
+  ) -> (
+    LoadResult<T::Value, T::Error>,
+    WatchReceiver<T::Value, T::Error>,
+  ) {
+    let cache = request_cache.cache_for(&key);
+    let current = cache.peek(&key).unwrap_or(Ok(None));
+
+    cache.invalidate(&key);
+    self.emit(LoadEvent::CacheInvalidated { key: key.clone() });
+
+    (current, self.cached_load_by(key, request_cache))
+  }
+
+  /// Load against the process-wide [`ContextCache::global`], sharing in-flight and resolved loads with the loaders of all other threads
+  #[cfg(feature = "global-cache")]
+  pub fn global_cached_load_by(&self, key: T::Key) -> WatchReceiver<T::Value, T::Error> {
+    // Looked up once per thread local loader, as ContextCache::global locks a process-wide registry
+    let global_cache = *self.global_cache.get_or_init(ContextCache::<T>::global);
+
+    self.cached_load_by(key, global_cache)
+  }
+
+  /// Load against a [`NegativeCache`], re-fetching keys confirmed absent once the TTL of the cache has elapsed
+  pub fn negative_cached_load_by(
+    &self,
+    key: T::Key,
+    negative_cache: &NegativeCache<T>,
+  ) -> WatchReceiver<T::Value, T::Error> {
+    let (rx, req) = negative_cache.get_or_create(&key);
+
+    if self.has_observers() {
+      self.emit(match req {
+        Some(_) => LoadEvent::CacheMiss { key: key.clone() },
+        None => LoadEvent::CacheHit { key: key.clone() },
+      });
+    }
+
+    #[cfg(feature = "prometheus-metrics")]
+    if let Some(metrics) = self.metrics.borrow().as_ref() {
+      metrics.observe_cache_lookup(req.is_none());
+    }
+
+    if let Some(req) = req {
+      self.enqueue(req);
+    }
+
+    rx
+  }
+
+  /// Count the batches dispatched from this loader hereafter for [`DataLoader::batch_stats`]. Counting deduplicated requests hashes the keys of each batch, and so stats are opt-in
+  pub fn enable_batch_stats(&self) {
+    self.stats.get_or_init(Default::default);
+  }
+
+  /// A snapshot of the batches dispatched from this loader since stats were enabled via [`DataLoader::enable_batch_stats`] or since last reset. Empty unless enabled
+  pub fn batch_stats(&self) -> BatchStats {
+    match self.stats.get() {
+      Some(stats) => stats.snapshot(),
+      None => BatchCounters::default().snapshot(),
+    }
+  }
+
+  pub fn reset_batch_stats(&self) {
+    if let Some(stats) = self.stats.get() {
+      stats.reset();
+    }
+  }
+
+  /// Stop loading for every loader of `T` for the remainder of the program. Requests not yet assigned to a batch, including any made hereafter, resolve as [`TaskHandler::shutdown_error`] or are otherwise cancelled, whereas batches already assigned complete as usual
+  pub fn drain(&self) {
+    crate::task::drain::<T>();
+  }
+
+  /// Construct a batch directly from `keys`, bypassing the queue, along with a receiver per key in the same order as `keys`. The caller takes ownership of the batch and must resolve it, either directly with [`Task::resolve`] or by handing it back to the task handler via [`DataLoader::schedule_assignment`]; dropping the batch unresolved cancels its requests. The batch isn't split by [`TaskHandler::MAX_BATCH_SIZE`] or other limits, isn't deduplicated against the queue and doesn't use any [`ContextCache`]
+  #[allow(clippy::type_complexity)]
+  pub fn load_batch_raw(
+    keys: Vec<T::Key>,
+  ) -> (
+    Task<LoadBatch<T::Key, T::Value, T::Error>>,
+    Vec<WatchReceiver<T::Value, T::Error>>,
+  ) {
+    let (requests, receivers): (Vec<_>, Vec<_>) = keys.into_iter().map(Request::new_watch).unzip();
+
+    (Task::from_requests(requests), receivers)
+  }
+
+  pub fn schedule_assignment(
+    &self,
+    task: Task<LoadBatch<T::Key, T::Value, T::Error>>,
+  ) -> Task<CompletionReceipt> {
+    let requests = task.into_requests();
+
+    let mut requests = requests.into_iter();
+
+    while let Some(req) = requests.next() {
+      let key = self.queued_keys.get().map(|_| req.key().to_owned());
+      let stealer = self.queue.push(req);
+      let queued_count = self.track_queued_count(Priority::Normal, stealer.as_ref());
+      let queued_keys =
+        key.and_then(|key| self.track_queued_keys(Priority::Normal, key, stealer.as_ref()));
+
+      if let Some(stealer) = stealer {
+        let requests: Vec<_> = requests.collect();
+
+        if let Some(queued_keys) = &queued_keys {
+          for req in requests.iter() {
+            queued_keys.push(req.key().to_owned());
+          }
+        }
+
+        self.spawn_task(
+          Task::new(stealer).with_requests(requests),
+          Priority::Normal,
+          queued_count,
+          queued_keys,
+        );
+
+        return Task::completion_receipt();
+      }
+    }
+
+    Task::completion_receipt()
+  }
+}
+
+impl<T> Default for DataLoader<T>
+where
+  T: TaskHandler,
+{
+  fn default() -> Self {
+    DataLoader::new(Worker::new())
+  }
+}
+
+pub trait StoreType {}
+pub struct CacheStore;
+impl StoreType for CacheStore {}
+pub struct DataStore;
+impl StoreType for DataStore {}
+pub trait LocalLoader<T: StoreType>: Sized + Send + Sync + 'static {
+  type Handler: TaskHandler;
+  fn loader() -> &'static LocalKey<DataLoader<Self::Handler>>;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use crate::{request::RecvCancelled, task::TaskAssignment};
+  use deque_loader_derive::Loader;
+  use futures_util::{stream, SinkExt};
+  use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  static BATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  pub struct SquareLoader;
+
+  impl MockBackend for SquareLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      BATCH_COUNT.fetch_add(1, Ordering::SeqCst);
+
+      Ok(keys.iter().map(|key| (*key, Arc::new(key * key))).collect())
+    }
+  }
+
+  pub struct ContendedLoader;
+
+  static CONNECTION: tokio::sync::Semaphore = tokio::sync::Semaphore::const_new(1);
+
+  #[async_trait::async_trait]
+  impl TaskHandler for ContendedLoader {
+    type Key = i32;
+    type Value = Priority;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(5);
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      let _conn = CONNECTION.acquire().await.unwrap();
+
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+          let priority = if task.keys()[0] < 100 {
+            Priority::Low
+          } else {
+            Priority::High
+          };
+
+          let data: HashMap<i32, Arc<Priority>> = task
+            .keys()
+            .into_iter()
+            .map(|key| (key, Arc::new(priority)))
+            .collect();
+
+          task.resolve(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+  async fn it_prioritizes_loads() {
+    let loader: DataLoader<ContendedLoader> = DataLoader::default();
+
+    // Task handlers contend for the connection from the outset, as the low priority batch is dispatched first
+    let conn = CONNECTION.acquire().await.unwrap();
+
+    let low = (0..20).map(|key| loader.load_with_priority(key, Priority::Low));
+    let high = (100..120).map(|key| loader.load_with_priority(key, Priority::High));
+
+    let receivers: Vec<_> = low.chain(high).collect();
+
+    drop(conn);
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    for receiver in receivers {
+      let tx = tx.clone();
+      tokio::task::spawn(async move {
+        let priority = *receiver.recv().await.unwrap().unwrap();
+        tx.send(priority).unwrap();
+      });
+    }
+
+    drop(tx);
+
+    let mut completion_order = vec![];
+
+    while let Some(priority) = rx.recv().await {
+      completion_order.push(priority);
+    }
+
+    assert_eq!(completion_order.len(), 40);
+    assert!(completion_order[..20]
+      .iter()
+      .all(|priority| priority.eq(&Priority::High)));
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<SlowLoader>")]
+  pub struct SlowLoader;
+
+  #[async_trait::async_trait]
+  impl MockBackend for SlowLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn before_load(_keys: &[i32]) {
+      tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_fails_after_deadline() {
+    use crate::request::LoadError;
+    use std::time::Duration;
+
+    let loader: DataLoader<MockHandler<SlowLoader>> = DataLoader::default();
+
+    let start = Instant::now();
+    let expired = loader.load_until(1, start + Duration::from_millis(10));
+    let resolved = loader.load_until(2, start + Duration::from_secs(1));
+
+    let (expired, resolved) = tokio::join!(expired, resolved);
+
+    assert_eq!(expired, Err(LoadError::Deadline));
+    assert_eq!(resolved, Ok(Some(Arc::new(2))));
+    assert!(start.elapsed().ge(&Duration::from_millis(50)));
+  }
+
+  #[tokio::test]
+  async fn it_fans_out_to_caches() {
+    use crate::fan_out::FnCacheWriter;
+    use std::{sync::mpsc, time::Duration};
+
+    let (tx_a, rx_a) = mpsc::channel();
+    let (tx_b, rx_b) = mpsc::channel();
+
+    let tx_a = std::sync::Mutex::new(tx_a);
+    let tx_b = std::sync::Mutex::new(tx_b);
+
+    let loader: DataLoader<MockHandler<SlowLoader>> = DataLoader::default();
+
+    loader.fan_out(vec![
+      Box::new(FnCacheWriter(move |key: &i32, value: Arc<i32>| {
+        tx_a.lock().unwrap().send((*key, *value)).unwrap();
+      })) as Box<dyn CacheWriter<i32, i32>>,
+      Box::new(FnCacheWriter(move |key: &i32, value: Arc<i32>| {
+        tx_b.lock().unwrap().send((*key, *value)).unwrap();
+      })),
+    ]);
+
+    let receivers: Vec<_> = (1..4).map(|key| loader.load_by(key)).collect();
+
+    futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    for rx in [rx_a, rx_b].iter() {
+      let mut written: Vec<(i32, i32)> = (0..3)
+        .map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap())
+        .collect();
+
+      written.sort_unstable();
+
+      assert_eq!(written, vec![(1, 1), (2, 2), (3, 3)]);
+    }
+  }
+
+  #[tokio::test]
+  async fn it_fans_out_on_the_thread_pool_of_the_loader() {
+    use crate::fan_out::FnCacheWriter;
+    use std::{sync::mpsc, time::Duration};
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+
+    let loader: DataLoader<MockHandler<SlowLoader>> = DataLoader::default();
+
+    loader.fan_out(vec![FnCacheWriter(move |_: &i32, _: Arc<i32>| {
+      let thread_name = std::thread::current().name().map(String::from);
+      tx.lock().unwrap().send(thread_name).unwrap();
+    })]);
+
+    // Set after fan_out, which nonetheless writes on this pool
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(1)
+      .thread_name(|i| format!("fan-out-rayon-{}", i))
+      .build()
+      .unwrap();
+
+    loader.set_thread_pool(Arc::new(pool));
+    loader.load_by(1).recv().await.unwrap();
+
+    let thread_name = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+
+    assert!(thread_name.starts_with("fan-out-rayon-"));
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<NotFoundLoader>")]
+  pub struct NotFoundLoader;
+
+  impl MockBackend for NotFoundLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(_keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(HashMap::new())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_inserts_absent_values_once() {
+    let loader: DataLoader<MockHandler<NotFoundLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<NotFoundLoader>> = ContextCache::new();
+    let factory_calls = Arc::new(AtomicUsize::new(0));
+
+    let loads = (0..8).map(|_| {
+      let factory_calls = factory_calls.clone();
+
+      loader.load_or_insert_with(1, &cache, move || async move {
+        factory_calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        Ok(7)
+      })
+    });
+
+    let values = futures_util::future::join_all(loads).await;
+
+    assert!(values.into_iter().all(|value| value == Ok(Arc::new(7))));
+    assert_eq!(factory_calls.load(Ordering::SeqCst), 1);
+    assert_eq!(
+      loader.cached_load_by(1, &cache).recv().await,
+      Ok(Some(Arc::new(7)))
+    );
+  }
+
+  #[tokio::test]
+  async fn it_reinserts_invalidated_values() {
+    let loader: DataLoader<MockHandler<NotFoundLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<NotFoundLoader>> = ContextCache::new();
+
+    assert_eq!(
+      loader
+        .load_or_insert_with(2, &cache, || async { Ok(7) })
+        .await,
+      Ok(Arc::new(7))
+    );
+
+    cache.invalidate(&2);
+
+    assert_eq!(
+      loader
+        .load_or_insert_with(2, &cache, || async { Ok(8) })
+        .await,
+      Ok(Arc::new(8))
+    );
+    assert_eq!(
+      loader.cached_load_by(2, &cache).recv().await,
+      Ok(Some(Arc::new(8)))
+    );
+  }
+
+  #[tokio::test]
+  async fn it_computes_absent_values_once() {
+    let loader: DataLoader<MockHandler<NotFoundLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<NotFoundLoader>> = ContextCache::new();
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let loads = (0..8).map(|_| {
+      let computations = computations.clone();
+
+      loader.load_or_compute(3, &cache, move |key| async move {
+        computations.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        Ok(key * 3)
+      })
+    });
+
+    let values = futures_util::future::join_all(loads).await;
+
+    assert!(values.into_iter().all(|value| value == Ok(Arc::new(9))));
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+    assert_eq!(
+      loader.cached_load_by(3, &cache).recv().await,
+      Ok(Some(Arc::new(9)))
+    );
+  }
+
+  pub struct ModelLoader;
+
+  impl MockBackend for ModelLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_transforms_loaded_values() {
+    let loader: DataLoader<MockHandler<ModelLoader>> = DataLoader::default();
+
+    let described = loader.load_then(4, |value| format!("#{}", value)).await;
+    let doubled = loader
+      .load_then_async(4, |value| async move { *value * 2 })
+      .await;
+
+    assert_eq!(described, Ok(Some("#4".to_string())));
+    assert_eq!(doubled, Ok(Some(8)));
+
+    let loader: DataLoader<MockHandler<NotFoundLoader>> = DataLoader::default();
+
+    let absent = loader
+      .load_then(4, |_| -> i32 {
+        unreachable!("absent values aren't transformed")
+      })
+      .await;
+
+    assert_eq!(absent, Ok(None));
+  }
+
+  static COLD_BATCHES: AtomicUsize = AtomicUsize::new(0);
+
+  pub struct ColdLoader;
+
+  impl MockBackend for ColdLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      COLD_BATCHES.fetch_add(1, Ordering::SeqCst);
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_shares_batches_of_cold_keys() {
+    let loader: DataLoader<MockHandler<ColdLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<ColdLoader>> = ContextCache::new();
+
+    loader.batch_insert_cold_keys(&[1, 2, 3], &cache);
+
+    let values = futures_util::future::join_all(
+      vec![3, 1, 2]
+        .into_iter()
+        .map(|key| loader.cached_load_by(key, &cache).recv()),
+    )
+    .await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(3))),
+        Ok(Some(Arc::new(1))),
+        Ok(Some(Arc::new(2)))
+      ]
+    );
+    assert_eq!(COLD_BATCHES.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn it_resolves_warmed_values_without_loading() {
+    let loader: DataLoader<MockHandler<ColdLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<ColdLoader>> = ContextCache::new();
+    let batches = COLD_BATCHES.load(Ordering::SeqCst);
+
+    loader
+      .cold_start_warmup((100..5100).map(|key| (key, key)), &cache)
+      .await;
+
+    assert_eq!(cache.load_all_cached_count(), 5000);
+
+    let values = futures_util::future::join_all(
+      vec![100, 2600, 5099]
+        .into_iter()
+        .map(|key| loader.cached_load_by(key, &cache).recv()),
+    )
+    .await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(100))),
+        Ok(Some(Arc::new(2600))),
+        Ok(Some(Arc::new(5099)))
+      ]
+    );
+    assert_eq!(COLD_BATCHES.load(Ordering::SeqCst), batches);
+  }
+
+  #[derive(Clone, Default)]
+  struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+  impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      self.0.lock().unwrap().extend_from_slice(buf);
+      Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_logs_loads_when_debugging() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+
+    // A subscriber installed first by another test leaves this capturing nothing, rather than panicking
+    let _ = tracing_subscriber::fmt()
+      .with_max_level(tracing_subscriber::filter::LevelFilter::DEBUG)
+      .with_writer(move || writer.clone())
+      .with_ansi(false)
+      .try_init();
+
+    let loader = <NotFoundLoader as LocalLoader<DataStore>>::loader();
+    let cache: ContextCache<MockHandler<NotFoundLoader>> = ContextCache::new();
+
+    loader.with(|loader| loader.set_debug_logging(true));
+
+    assert_eq!(loader.with(|loader| loader.load(7)).await, Ok(None));
+    assert_eq!(
+      loader.with(|loader| loader.load_by(9)).recv().await,
+      Ok(None)
+    );
+
+    for _ in 0..2 {
+      let rx = loader.with(|loader| loader.cached_load_by(8, &cache));
+      assert_eq!(rx.recv().await, Ok(None));
+    }
+
+    // Loads once logging is disabled go unlogged
+    loader.with(|loader| loader.set_debug_logging(false));
+    assert_eq!(
+      loader.with(|loader| loader.load_by(10)).recv().await,
+      Ok(None)
+    );
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+
+    let lines: Vec<&str> = logs
+      .lines()
+      .filter(|line| line.contains(DEBUG_TARGET) && line.contains("NotFoundLoader"))
+      .collect();
+
+    assert_eq!(lines.len(), 4);
+    assert!(lines.iter().all(|line| line.contains("result=none")));
+    assert!(lines[0].contains("load key=7 cache_hit=false"));
+    assert!(lines[1].contains("load key=9 cache_hit=false"));
+    assert!(lines
+      .iter()
+      .any(|line| line.contains("load key=8 cache_hit=true") && line.contains("batch_id=none")));
+    assert!(lines
+      .iter()
+      .filter(|line| line.contains("cache_hit=false"))
+      .all(|line| !line.contains("batch_id=none")));
+  }
+
+  #[tokio::test]
+  async fn it_resolves_absent_keys_as_defaults() {
+    let loader = <NotFoundLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| loader.set_default_value(Arc::new(0)));
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(1)).recv().await,
+      Ok(Some(Arc::new(0)))
+    );
+    assert_eq!(
+      loader.with(|loader| loader.load(2)).await,
+      Ok(Some(Arc::new(0)))
+    );
+
+    // Defaults aren't cached, so cached loads see the absence
+    let cache: ContextCache<MockHandler<NotFoundLoader>> = ContextCache::new();
+    let rx = loader.with(|loader| loader.cached_load_by(3, &cache));
+    assert_eq!(rx.recv().await, Ok(None));
+
+    loader.with(|loader| loader.set_default_fn(|key| Arc::new(-key)));
+
+    assert_eq!(
+      loader
+        .with(|loader| loader.load_with_priority(4, Priority::High))
+        .recv()
+        .await,
+      Ok(Some(Arc::new(-4)))
+    );
+  }
+
+  static VERSION: AtomicUsize = AtomicUsize::new(0);
+
+  pub struct VersionedLoader;
+
+  impl MockBackend for VersionedLoader {
+    type Key = i32;
+    type Value = usize;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<usize>>, TestError> {
+      let version = Arc::new(VERSION.fetch_add(1, Ordering::SeqCst) + 1);
+
+      Ok(keys.iter().map(|key| (*key, version.clone())).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_reloads_while_returning_stale_values() {
+    let loader: DataLoader<MockHandler<VersionedLoader>> = DataLoader::default();
+    let cache: ContextCache<MockHandler<VersionedLoader>> = ContextCache::new();
+
+    assert_eq!(
+      loader.cached_load_by(1, &cache).recv().await,
+      Ok(Some(Arc::new(1)))
+    );
+
+    let (stale, rx) = loader.watch_reload(1, &cache);
+
+    assert_eq!(stale, Ok(Some(Arc::new(1))));
+    assert_eq!(rx.recv().await, Ok(Some(Arc::new(2))));
+    assert_eq!(
+      loader.cached_load_by(1, &cache).recv().await,
+      Ok(Some(Arc::new(2)))
+    );
+
+    let (stale, rx) = loader.watch_reload(2, &cache);
+
+    assert_eq!(stale, Ok(None));
+    assert_eq!(rx.recv().await, Ok(Some(Arc::new(3))));
+  }
+
+  #[derive(thiserror::Error, Debug, Clone, PartialEq)]
+  #[error("odd keys are unavailable")]
+  pub struct Unavailable;
+
+  pub struct FallibleLoader;
+
+  impl MockBackend for FallibleLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = Unavailable;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, Unavailable> {
+      if keys.iter().any(|key| key % 2 == 1) {
+        Err(Unavailable)
+      } else {
+        Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_converts_load_errors_with_the_question_mark_operator() {
+    async fn load(
+      loader: &DataLoader<MockHandler<FallibleLoader>>,
+      key: i32,
+    ) -> Result<Option<Arc<i32>>, Box<dyn std::error::Error + Send + Sync>> {
+      Ok(loader.load_result(key).await?)
+    }
+
+    let loader: DataLoader<MockHandler<FallibleLoader>> = DataLoader::default();
+
+    assert_eq!(load(&loader, 2).await.unwrap(), Some(Arc::new(2)));
+
+    let err = load(&loader, 3).await.unwrap_err();
+
+    assert_eq!(
+      err.downcast_ref::<LoadError<Unavailable>>(),
+      Some(&LoadError::HandlerError(Unavailable))
+    );
+    assert!(err
+      .source()
+      .is_some_and(|source| source.downcast_ref::<Unavailable>().is_some()));
+  }
+
+  #[derive(Clone, Copy, Debug)]
+  struct TraceId(u64);
+
+  pub struct TracedLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for TracedLoader {
+    type Key = i32;
+    type Value = String;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let data: HashMap<i32, Arc<String>> = task
+            .keys()
+            .into_iter()
+            .map(|key| {
+              let value = match task.request_metadata::<TraceId>(&key) {
+                Some(TraceId(trace_id)) => format!("trace_id={}", trace_id),
+                None => String::from("untraced"),
+              };
+
+              (key, Arc::new(value))
+            })
+            .collect();
+
+          task.resolve(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_propagates_request_metadata() {
+    let loader: DataLoader<TracedLoader> = DataLoader::default();
+
+    let traced = loader.load_with_metadata(1, TraceId(7));
+    let mistyped = loader.load_with_metadata(2, 7_u64);
+    let untraced = loader.load_by(3);
+
+    assert_eq!(traced.recv().await, Ok(Some(Arc::new("trace_id=7".into()))));
+    assert_eq!(mistyped.recv().await, Ok(Some(Arc::new("untraced".into()))));
+    assert_eq!(untraced.recv().await, Ok(Some(Arc::new("untraced".into()))));
+  }
+
+  #[tokio::test]
+  async fn it_loads_raw_batches() -> Result<(), TestError> {
+    let (task, receivers) = DataLoader::<MockHandler<SlowLoader>>::load_batch_raw(vec![3, 1, 3]);
+
+    assert_eq!(task.keys().len(), 2);
+
+    let data: HashMap<i32, Arc<i32>> = vec![(3, Arc::new(9))].into_iter().collect();
+    let _ = task.resolve(Ok(data));
+
+    let values =
+      futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+    assert_eq!(values, vec![Some(Arc::new(9)), None, Some(Arc::new(9))]);
+
+    let (task, receivers) = DataLoader::<MockHandler<SlowLoader>>::load_batch_raw(vec![4, 5]);
+    let loader: DataLoader<MockHandler<SlowLoader>> = DataLoader::default();
+    let _ = loader.schedule_assignment(task);
+
+    let values =
+      futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+    assert_eq!(values, vec![Some(Arc::new(4)), Some(Arc::new(5))]);
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn it_peeks_queued_keys() {
+    let loader = <SlowLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| loader.enable_queued_key_tracking());
+
+    let receivers: Vec<_> = loader.with(|loader| {
+      vec![1, 2, 3]
+        .into_iter()
+        .map(|key| loader.load_by(key))
+        .collect()
+    });
+
+    let high_priority = loader.with(|loader| loader.load_with_priority(9, Priority::High));
+
+    assert_eq!(
+      loader.with(|loader| loader.peek_queued_keys()),
+      vec![9, 1, 2, 3]
+    );
+    assert_eq!(
+      loader.with(|loader| loader.peek_queued_keys()),
+      vec![9, 1, 2, 3]
+    );
+
+    let values = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(1))),
+        Ok(Some(Arc::new(2))),
+        Ok(Some(Arc::new(3))),
+      ]
+    );
+    assert_eq!(high_priority.recv().await, Ok(Some(Arc::new(9))));
+    assert!(loader.with(|loader| loader.peek_queued_keys()).is_empty());
+  }
+
+  pub struct DrainedLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for DrainedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(_) => unreachable!("drained loaders are never assigned batches"),
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  pub struct ShutdownLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for ShutdownLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn shutdown_error() -> Option<Self::Error> {
+      Some(TestError("shutting down"))
+    }
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(_) => unreachable!("drained loaders are never assigned batches"),
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_cancels_queued_loads_when_drained() {
+    let loader: DataLoader<DrainedLoader> = DataLoader::default();
+
+    let cache = ContextCache::new();
+
+    let progress = loader.load(1);
+    let rx = loader.load_by(2);
+    let cached = loader.cached_load_by(4, &cache);
+
+    loader.drain();
+
+    assert_eq!(cached.try_recv().await, Err(RecvCancelled));
+    assert!(!cache.contains(&4));
+    assert_eq!(progress.subscribe().try_recv().await, Err(RecvCancelled));
+    assert!(progress.is_done());
+    assert_eq!(progress.current(), None);
+    assert_eq!(rx.try_recv().await, Err(RecvCancelled));
+
+    assert_eq!(
+      loader.load(3).subscribe().try_recv().await,
+      Err(RecvCancelled)
+    );
+  }
+
+  #[tokio::test]
+  async fn it_resolves_drained_loads_as_shutdown_error() {
+    let loader: DataLoader<ShutdownLoader> = DataLoader::default();
+
+    let progress = loader.load(1);
+    let rx = loader.load_by(2);
+
+    loader.drain();
+
+    assert_eq!(progress.await, Err(TestError("shutting down")));
+    assert_eq!(rx.recv().await, Err(TestError("shutting down")));
+  }
+
+  #[tokio::test]
+  async fn it_loads_from_sink() {
+    thread_local! {
+      static LOADER: DataLoader<MockHandler<SquareLoader>> = DataLoader::default();
+    }
+
+    let (mut sink, stream) = DataLoader::sink_stream(&LOADER, 16);
+
+    sink
+      .send_all(&mut stream::iter((0..10).map(Ok)))
+      .await
+      .unwrap();
+
+    drop(sink);
+
+    let mut results: Vec<(i32, LoadResult<i32, TestError>)> = stream.collect().await;
+
+    results.sort_by_key(|(key, _)| *key);
+
+    assert_eq!(
+      results,
+      (0..10)
+        .map(|key| (key, Ok(Some(Arc::new(key * key)))))
+        .collect::<Vec<_>>()
+    );
+
+    assert_eq!(BATCH_COUNT.load(Ordering::SeqCst), 1);
+  }
 }
@@ -0,0 +1,171 @@
+//! Normalize keys, such as by lowercasing email addresses or resolving aliases, before loading
+//!
+//! ```rust
+//! pub struct EmailMapper;
+//!
+//! #[async_trait::async_trait]
+//! impl KeyMapper for EmailMapper {
+//!   type Key = String;
+//!   type Normalized = String;
+//!
+//!   async fn map_key(key: String) -> String {
+//!     key.trim().to_lowercase()
+//!   }
+//! }
+//!
+//! let loader: MappedDataLoader<EmailMapper, UserByEmailLoader> = MappedDataLoader::new();
+//! let user = loader.load("Alice@Example.com".into()).await?;
+//! ```
+
+use crate::{
+  loader::{DataStore, LocalLoader},
+  task::TaskHandler,
+  Key,
+};
+use flurry::HashMap;
+use std::{marker::PhantomData, sync::Arc};
+
+/// An async mapping from raw input keys onto the normalized keys that values are loaded by
+#[async_trait::async_trait]
+pub trait KeyMapper: Send + Sync + 'static {
+  type Key: Key;
+  type Normalized: Key;
+  async fn map_key(key: Self::Key) -> Self::Normalized;
+}
+
+/// Loads by raw keys against the [`crate::loader::DataLoader`] of `T`, caching the normalization of each raw key for the lifetime of this loader
+pub struct MappedDataLoader<M, T>
+where
+  M: KeyMapper,
+  T: LocalLoader<DataStore>,
+  T::Handler: TaskHandler<Key = M::Normalized>,
+{
+  keys: HashMap<M::Key, M::Normalized>,
+  loader: PhantomData<fn() -> T>,
+}
+
+impl<M, T> Default for MappedDataLoader<M, T>
+where
+  M: KeyMapper,
+  T: LocalLoader<DataStore>,
+  T::Handler: TaskHandler<Key = M::Normalized>,
+{
+  fn default() -> Self {
+    MappedDataLoader::new()
+  }
+}
+
+impl<M, T> MappedDataLoader<M, T>
+where
+  M: KeyMapper,
+  T: LocalLoader<DataStore>,
+  T::Handler: TaskHandler<Key = M::Normalized>,
+{
+  pub fn new() -> Self {
+    MappedDataLoader {
+      keys: HashMap::new(),
+      loader: PhantomData,
+    }
+  }
+
+  /// Normalize `key`, reusing a prior normalization if one exists
+  pub async fn map_key(&self, key: M::Key) -> M::Normalized {
+    if let Some(normalized) = self.keys.pin().get(&key) {
+      return normalized.to_owned();
+    }
+
+    let normalized = M::map_key(key.clone()).await;
+
+    self.keys.pin().insert(key, normalized.clone());
+
+    normalized
+  }
+
+  pub async fn load(
+    &self,
+    key: M::Key,
+  ) -> Result<Option<Arc<<T::Handler as TaskHandler>::Value>>, <T::Handler as TaskHandler>::Error>
+  {
+    let key = self.map_key(key).await;
+
+    let rx = T::loader().with(|loader| loader.load_by(key));
+
+    rx.recv().await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::batch::{BatchHandler, BatchLoader};
+  use crate::testing::TestError;
+  use deque_loader_derive::Loader;
+  use std::{
+    collections,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  static MAP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  pub struct EmailMapper;
+
+  #[async_trait::async_trait]
+  impl KeyMapper for EmailMapper {
+    type Key = String;
+    type Normalized = String;
+
+    async fn map_key(key: String) -> String {
+      MAP_COUNT.fetch_add(1, Ordering::SeqCst);
+      key.trim().to_lowercase()
+    }
+  }
+
+  #[derive(Clone, Debug, PartialEq, Eq)]
+  pub struct User {
+    id: i32,
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "BatchHandler<UserByEmailLoader>")]
+  pub struct UserByEmailLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for UserByEmailLoader {
+    type Key = String;
+    type Value = User;
+    type Error = TestError;
+
+    async fn load(keys: Vec<String>) -> Result<collections::HashMap<String, Arc<User>>, TestError> {
+      Ok(
+        keys
+          .into_iter()
+          .filter(|key| key.eq("alice@example.com"))
+          .map(|key| (key, Arc::new(User { id: 1 })))
+          .collect(),
+      )
+    }
+  }
+
+  #[tokio::test]
+  async fn it_normalizes_keys() -> Result<(), TestError> {
+    let loader: MappedDataLoader<EmailMapper, UserByEmailLoader> = MappedDataLoader::new();
+
+    for email in [
+      "Alice@Example.com",
+      "ALICE@EXAMPLE.COM",
+      " alice@example.com ",
+    ]
+    .iter()
+    {
+      let user = loader.load(email.to_string()).await?;
+      assert_eq!(user, Some(Arc::new(User { id: 1 })));
+    }
+
+    loader.load("Alice@Example.com".to_string()).await?;
+
+    assert_eq!(MAP_COUNT.load(Ordering::SeqCst), 3);
+    assert_eq!(loader.load("bob@example.com".to_string()).await?, None);
+
+    Ok(())
+  }
+}
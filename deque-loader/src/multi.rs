@@ -0,0 +1,216 @@
+//! Load heterogeneous entities sharing a single key space, dispatching each key to the loader of its entity type
+//!
+//! ```rust
+//! #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+//! pub struct ObjectId(i64);
+//!
+//! // The high byte of each id is the type prefix
+//! impl Discriminant for ObjectId {
+//!   fn discriminant(&self) -> u8 {
+//!     (self.0 >> 56) as u8
+//!   }
+//! }
+//!
+//! let loader = MultiLoader::new()
+//!   .register(USER_PREFIX, DataLoader::<MockHandler<UserLoader>>::default())
+//!   .register(POST_PREFIX, DataLoader::<MockHandler<PostLoader>>::default());
+//!
+//! let user = loader
+//!   .load(user_id)
+//!   .await?
+//!   .and_then(|value| value.downcast::<User>().ok());
+//! ```
+
+use crate::{loader::DataLoader, task::TaskHandler, Key};
+use futures_util::future::{self, BoxFuture, FutureExt};
+use std::{any::Any, collections::HashMap, sync::Arc};
+use thiserror::Error;
+
+/// A cheap value identifying which loader of a [`MultiLoader`] a key belongs to
+pub trait Discriminant: Key {
+  fn discriminant(&self) -> u8;
+}
+
+/// The result of a [`MultiLoader`] load failing
+#[derive(Error, Debug, Clone)]
+pub enum MultiLoadError {
+  #[error("no loader registered for discriminant {0}")]
+  UnregisteredDiscriminant(u8),
+  /// The error of the task handler, to be downcast into [`TaskHandler::Error`]
+  #[error("task handler error")]
+  HandlerError(Arc<dyn Any + Send + Sync>),
+}
+
+type MultiLoadResult = Result<Option<Arc<dyn Any + Send + Sync>>, MultiLoadError>;
+
+/// Dispatches loads to the [`DataLoader`] registered for the [`Discriminant`] of each key, with values type-erased to be downcast by the caller. As with [`DataLoader`], a `MultiLoader` is intended to be thread local
+pub struct MultiLoader<K: Discriminant> {
+  loaders: HashMap<u8, Box<dyn Fn(K) -> BoxFuture<'static, MultiLoadResult>>>,
+}
+
+impl<K> Default for MultiLoader<K>
+where
+  K: Discriminant,
+{
+  fn default() -> Self {
+    MultiLoader::new()
+  }
+}
+
+impl<K> MultiLoader<K>
+where
+  K: Discriminant,
+{
+  pub fn new() -> Self {
+    MultiLoader {
+      loaders: HashMap::new(),
+    }
+  }
+
+  /// Load keys of `discriminant` with `loader`, replacing any loader previously registered for it
+  pub fn register<T>(mut self, discriminant: u8, loader: DataLoader<T>) -> Self
+  where
+    T: TaskHandler<Key = K>,
+  {
+    self.loaders.insert(
+      discriminant,
+      Box::new(move |key| {
+        let rx = loader.load_by(key);
+
+        async move {
+          match rx.recv().await {
+            Ok(value) => Ok(value.map(|value| value as Arc<dyn Any + Send + Sync>)),
+            Err(err) => Err(MultiLoadError::HandlerError(Arc::new(err))),
+          }
+        }
+        .boxed()
+      }),
+    );
+    self
+  }
+
+  pub fn load(&self, key: K) -> BoxFuture<'static, MultiLoadResult> {
+    let discriminant = key.discriminant();
+
+    match self.loaders.get(&discriminant) {
+      Some(load) => load(key),
+      None => future::ready(Err(MultiLoadError::UnregisteredDiscriminant(discriminant))).boxed(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+
+  const USER_PREFIX: u8 = 1;
+  const POST_PREFIX: u8 = 2;
+
+  #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+  pub struct ObjectId(i64);
+
+  impl ObjectId {
+    fn new(prefix: u8, id: i64) -> Self {
+      ObjectId(((prefix as i64) << 56) | id)
+    }
+
+    fn id(&self) -> i64 {
+      self.0 & 0x00ff_ffff_ffff_ffff
+    }
+  }
+
+  impl Discriminant for ObjectId {
+    fn discriminant(&self) -> u8 {
+      (self.0 >> 56) as u8
+    }
+  }
+
+  #[derive(Debug, Clone, PartialEq)]
+  pub struct User {
+    name: String,
+  }
+
+  #[derive(Debug, Clone, PartialEq)]
+  pub struct Post {
+    title: String,
+  }
+
+  pub struct UserLoader;
+
+  impl MockBackend for UserLoader {
+    type Key = ObjectId;
+    type Value = User;
+    type Error = TestError;
+
+    fn load(keys: &[ObjectId]) -> Result<HashMap<ObjectId, Arc<User>>, TestError> {
+      Ok(
+        keys
+          .iter()
+          .map(|key| {
+            let user = User {
+              name: format!("user {}", key.id()),
+            };
+
+            (*key, Arc::new(user))
+          })
+          .collect(),
+      )
+    }
+  }
+
+  pub struct PostLoader;
+
+  impl MockBackend for PostLoader {
+    type Key = ObjectId;
+    type Value = Post;
+    type Error = TestError;
+
+    fn load(_keys: &[ObjectId]) -> Result<HashMap<ObjectId, Arc<Post>>, TestError> {
+      Err(TestError("posts unavailable"))
+    }
+  }
+
+  #[tokio::test]
+  async fn it_dispatches_by_discriminant() {
+    let loader = MultiLoader::new()
+      .register(
+        USER_PREFIX,
+        DataLoader::<MockHandler<UserLoader>>::default(),
+      )
+      .register(
+        POST_PREFIX,
+        DataLoader::<MockHandler<PostLoader>>::default(),
+      );
+
+    let user = loader
+      .load(ObjectId::new(USER_PREFIX, 7))
+      .await
+      .unwrap()
+      .unwrap()
+      .downcast::<User>()
+      .unwrap();
+
+    assert_eq!(
+      user,
+      Arc::new(User {
+        name: "user 7".into()
+      })
+    );
+
+    match loader.load(ObjectId::new(POST_PREFIX, 7)).await {
+      Err(MultiLoadError::HandlerError(err)) => {
+        assert_eq!(
+          err.downcast_ref::<TestError>(),
+          Some(&TestError("posts unavailable"))
+        )
+      }
+      _ => panic!("expected the error of PostLoader"),
+    }
+
+    assert!(matches!(
+      loader.load(ObjectId::new(3, 7)).await,
+      Err(MultiLoadError::UnregisteredDiscriminant(3))
+    ));
+  }
+}
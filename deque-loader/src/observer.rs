@@ -0,0 +1,249 @@
+//! Event-based monitoring of a [`crate::loader::DataLoader`], for reporting to any metrics backend, logging or updating application state without the loader depending on them
+//!
+//! ```rust
+//! UserLoader::loader().with(|loader| {
+//!   loader.observe(|event| match event {
+//!     LoadEvent::CacheHit { .. } => CACHE_HITS.inc(),
+//!     LoadEvent::BatchResolved { duration, .. } => BATCH_DURATION.observe(duration.as_secs_f64()),
+//!     _ => {}
+//!   })
+//! });
+//! ```
+use crate::task::TaskHandler;
+use std::{sync::Arc, time::Duration};
+use tokio::time::Instant;
+
+/// The lifecycle events of loads made through a loader
+pub enum LoadEvent<T: TaskHandler> {
+  /// A cached load found the key already loading or loaded
+  CacheHit { key: T::Key },
+  /// A cached load queued the key to be loaded
+  CacheMiss { key: T::Key },
+  /// A batch was assigned to the task handler, with keys as returned by [`crate::task::Task::keys`]
+  BatchDispatched { keys: Vec<T::Key>, batch_id: u64 },
+  /// A batch was resolved, timed from dispatch and emitted ahead of waking the requests awaiting it. Batches split via [`crate::task::Task::split_by_shard`] report each sub-batch under the id of the batch dispatched
+  BatchResolved {
+    batch_id: u64,
+    duration: Duration,
+    ok_count: usize,
+    err_count: usize,
+  },
+  /// A key was invalidated by this loader, such as by [`crate::loader::DataLoader::watch_reload`]
+  CacheInvalidated { key: T::Key },
+}
+
+impl<T> Clone for LoadEvent<T>
+where
+  T: TaskHandler,
+{
+  fn clone(&self) -> Self {
+    match self {
+      LoadEvent::CacheHit { key } => LoadEvent::CacheHit { key: key.clone() },
+      LoadEvent::CacheMiss { key } => LoadEvent::CacheMiss { key: key.clone() },
+      LoadEvent::BatchDispatched { keys, batch_id } => LoadEvent::BatchDispatched {
+        keys: keys.clone(),
+        batch_id: *batch_id,
+      },
+      LoadEvent::BatchResolved {
+        batch_id,
+        duration,
+        ok_count,
+        err_count,
+      } => LoadEvent::BatchResolved {
+        batch_id: *batch_id,
+        duration: *duration,
+        ok_count: *ok_count,
+        err_count: *err_count,
+      },
+      LoadEvent::CacheInvalidated { key } => LoadEvent::CacheInvalidated { key: key.clone() },
+    }
+  }
+}
+
+pub type LoadObserver<T> = Arc<dyn Fn(LoadEvent<T>) + Send + Sync>;
+
+/// The observers of a loader, in the order added
+pub(crate) struct Observers<T: TaskHandler>(pub(crate) Vec<LoadObserver<T>>);
+
+impl<T> Observers<T>
+where
+  T: TaskHandler,
+{
+  pub(crate) fn emit(&self, event: LoadEvent<T>) {
+    for observer in self.0.iter() {
+      observer(event.clone());
+    }
+  }
+}
+
+// Batches are agnostic of their task handler, and so observe events by key type only
+pub(crate) trait BatchObserver<K>: Send + Sync {
+  fn dispatched(&self, keys: Vec<K>, batch_id: u64);
+  fn resolved(&self, batch_id: u64, duration: Duration, ok_count: usize, err_count: usize);
+}
+
+impl<T> BatchObserver<T::Key> for Observers<T>
+where
+  T: TaskHandler,
+{
+  fn dispatched(&self, keys: Vec<T::Key>, batch_id: u64) {
+    self.emit(LoadEvent::BatchDispatched { keys, batch_id });
+  }
+
+  fn resolved(&self, batch_id: u64, duration: Duration, ok_count: usize, err_count: usize) {
+    self.emit(LoadEvent::BatchResolved {
+      batch_id,
+      duration,
+      ok_count,
+      err_count,
+    });
+  }
+}
+
+/// The observation of a dispatched batch, carried through to its resolution
+#[derive(Clone)]
+pub(crate) struct BatchObservation<K> {
+  observer: Arc<dyn BatchObserver<K>>,
+  batch_id: u64,
+  dispatched_at: Instant,
+  // Requests resolved ahead of the batch, such as by streaming results
+  resolved_count: usize,
+  // Requests failed ahead of the batch, such as by rejecting invalid keys
+  failed_count: usize,
+}
+
+impl<K> BatchObservation<K> {
+  pub(crate) fn dispatched(
+    observer: Arc<dyn BatchObserver<K>>,
+    keys: Vec<K>,
+    batch_id: u64,
+  ) -> Self {
+    observer.dispatched(keys, batch_id);
+
+    BatchObservation {
+      observer,
+      batch_id,
+      dispatched_at: Instant::now(),
+      resolved_count: 0,
+      failed_count: 0,
+    }
+  }
+
+  pub(crate) fn partially_resolved(&mut self, count: usize) {
+    self.resolved_count += count;
+  }
+
+  pub(crate) fn partially_failed(&mut self, count: usize) {
+    self.failed_count += count;
+  }
+
+  pub(crate) fn resolved(&self, ok_count: usize, err_count: usize) {
+    self.observer.resolved(
+      self.batch_id,
+      self.dispatched_at.elapsed(),
+      self.resolved_count + ok_count,
+      self.failed_count + err_count,
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::{DataStore, LocalLoader};
+  use crate::request::ContextCache;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use std::{collections::HashMap, sync::Mutex};
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<ObservedLoader>")]
+  pub struct ObservedLoader;
+
+  impl MockBackend for ObservedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  fn describe(event: LoadEvent<MockHandler<ObservedLoader>>) -> String {
+    match event {
+      LoadEvent::CacheHit { key } => format!("hit {}", key),
+      LoadEvent::CacheMiss { key } => format!("miss {}", key),
+      LoadEvent::BatchDispatched { keys, .. } => format!("dispatched {:?}", keys),
+      LoadEvent::BatchResolved {
+        ok_count,
+        err_count,
+        ..
+      } => format!("resolved {} ok {} err", ok_count, err_count),
+      LoadEvent::CacheInvalidated { key } => format!("invalidated {}", key),
+    }
+  }
+
+  #[tokio::test]
+  async fn it_emits_events_in_sequence() {
+    let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let batch_ids: Arc<Mutex<Vec<u64>>> = Arc::new(Mutex::new(vec![]));
+
+    let loader = <ObservedLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| {
+      loader.observe({
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(describe(event))
+      });
+
+      loader.observe({
+        let batch_ids = batch_ids.clone();
+        move |event| match event {
+          LoadEvent::BatchDispatched { batch_id, .. }
+          | LoadEvent::BatchResolved { batch_id, .. } => batch_ids.lock().unwrap().push(batch_id),
+          _ => {}
+        }
+      });
+    });
+
+    let cache: ContextCache<MockHandler<ObservedLoader>> = ContextCache::new();
+
+    let receivers: Vec<_> = loader.with(|loader| {
+      vec![
+        loader.cached_load_by(1, &cache),
+        loader.cached_load_by(2, &cache),
+        loader.cached_load_by(1, &cache),
+      ]
+    });
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    let (_, rx) = loader.with(|loader| loader.watch_reload(1, &cache));
+
+    assert_eq!(rx.recv().await, Ok(Some(Arc::new(1))));
+
+    assert_eq!(
+      *events.lock().unwrap(),
+      vec![
+        "miss 1",
+        "miss 2",
+        "hit 1",
+        "dispatched [1, 2]",
+        "resolved 2 ok 0 err",
+        "invalidated 1",
+        "miss 1",
+        "dispatched [1]",
+        "resolved 1 ok 0 err",
+      ]
+    );
+
+    let batch_ids = batch_ids.lock().unwrap();
+
+    assert_eq!(batch_ids.len(), 4);
+    assert_eq!(batch_ids[0], batch_ids[1]);
+    assert_eq!(batch_ids[2], batch_ids[3]);
+  }
+}
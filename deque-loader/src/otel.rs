@@ -0,0 +1,219 @@
+//! Batch telemetry exported to OpenTelemetry via its [`Tracer`] API, for applications exporting spans without the `tracing` bridge
+//!
+//! Each batch is traced as a `dataloader.batch` span with attributes `batch.size`, `batch.handler` and `batch.id`, parented by the [`Context::current`] of the handler upon dispatch. Once resolved, a `dataloader.key.resolve` child span is recorded per key; as resolution runs on rayon, where no context is current, the context of each batch is carried across from dispatch rather than taken from the resolving thread. Cached loads record a `dataloader.cache_hit` event upon the span current to the loading thread
+//!
+//! ```rust
+//! use opentelemetry::trace::TracerProvider as _;
+//! use opentelemetry_otlp::WithExportConfig;
+//!
+//! // Jaeger accepts OTLP natively on port 4317
+//! let exporter = opentelemetry_otlp::SpanExporter::builder()
+//!   .with_tonic()
+//!   .with_endpoint("http://localhost:4317")
+//!   .build()?;
+//!
+//! let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+//!   .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+//!   .build();
+//!
+//! opentelemetry::global::set_tracer_provider(provider.clone());
+//!
+//! UserLoader::loader().with(|loader| {
+//!   loader.add_observer(OtelBatchTracer::new(provider.tracer("users")).into_observer())
+//! });
+//!
+//! // Parent loads by the span of the current request
+//! let cx = Context::current_with_span(provider.tracer("app").start("GET /users/:id"));
+//! let user = UserLoader::loader().with(|loader| loader.load_by(user_id)).with_context(cx).await?;
+//!
+//! provider.shutdown()?;
+//! ```
+use crate::{
+  observer::{LoadEvent, LoadObserver},
+  task::TaskHandler,
+};
+use opentelemetry::{
+  trace::{Span, Status, TraceContextExt, Tracer},
+  Context, KeyValue,
+};
+use std::{
+  collections::HashMap,
+  fmt::Debug,
+  sync::{Arc, Mutex},
+};
+
+/// Traces the batches of a [`crate::loader::DataLoader`] upon being added as an observer via [`OtelBatchTracer::into_observer`]. Batches split via [`crate::task::Task::split_by_shard`] end their span upon the first sub-batch resolving
+pub struct OtelBatchTracer<T: TaskHandler, R: Tracer> {
+  tracer: R,
+  batches: Mutex<HashMap<u64, DispatchedBatch<T::Key>>>,
+}
+
+// The context of a batch span, carried from dispatch through to resolution
+struct DispatchedBatch<K> {
+  cx: Context,
+  keys: Vec<K>,
+}
+
+impl<T, R> OtelBatchTracer<T, R>
+where
+  T: TaskHandler,
+  T::Key: Debug,
+  R: Tracer + Send + Sync + 'static,
+  R::Span: Send + Sync + 'static,
+{
+  pub fn new(tracer: R) -> Self {
+    OtelBatchTracer {
+      tracer,
+      batches: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// An observer for [`crate::loader::DataLoader::add_observer`]
+  pub fn into_observer(self) -> LoadObserver<T> {
+    let tracer = Arc::new(self);
+
+    Arc::new(move |event| tracer.trace(event))
+  }
+
+  fn trace(&self, event: LoadEvent<T>) {
+    match event {
+      LoadEvent::BatchDispatched { keys, batch_id } => {
+        let parent = Context::current();
+
+        let span = self
+          .tracer
+          .span_builder("dataloader.batch")
+          .with_attributes(vec![
+            KeyValue::new("batch.size", keys.len() as i64),
+            KeyValue::new("batch.handler", tynm::type_name::<T>()),
+            KeyValue::new("batch.id", batch_id as i64),
+          ])
+          .start_with_context(&self.tracer, &parent);
+
+        self.batches.lock().unwrap().insert(
+          batch_id,
+          DispatchedBatch {
+            cx: parent.with_span(span),
+            keys,
+          },
+        );
+      }
+      LoadEvent::BatchResolved {
+        batch_id,
+        ok_count,
+        err_count,
+        ..
+      } => {
+        let batch = match self.batches.lock().unwrap().remove(&batch_id) {
+          Some(batch) => batch,
+          None => return,
+        };
+
+        for key in batch.keys.iter() {
+          self
+            .tracer
+            .span_builder("dataloader.key.resolve")
+            .with_attributes(vec![KeyValue::new("key", format!("{:?}", key))])
+            .start_with_context(&self.tracer, &batch.cx)
+            .end();
+        }
+
+        let span = batch.cx.span();
+
+        span.set_attribute(KeyValue::new("batch.ok_count", ok_count as i64));
+        span.set_attribute(KeyValue::new("batch.err_count", err_count as i64));
+
+        if err_count.gt(&0) {
+          span.set_status(Status::error(format!("{} requests failed", err_count)));
+        }
+
+        span.end();
+      }
+      LoadEvent::CacheHit { key } => {
+        Context::current().span().add_event(
+          "dataloader.cache_hit",
+          vec![KeyValue::new("key", format!("{:?}", key))],
+        );
+      }
+      LoadEvent::CacheMiss { .. } | LoadEvent::CacheInvalidated { .. } => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use crate::{loader::DataLoader, request::ContextCache};
+  use opentelemetry::trace::TracerProvider as _;
+  use opentelemetry_sdk::{testing::trace::InMemorySpanExporter, trace::TracerProvider};
+
+  pub struct TracedLoader;
+
+  impl MockBackend for TracedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_exports_batch_spans() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = TracerProvider::builder()
+      .with_simple_exporter(exporter.clone())
+      .build();
+
+    let loader: DataLoader<MockHandler<TracedLoader>> = DataLoader::default();
+    loader.add_observer(OtelBatchTracer::new(provider.tracer("loader")).into_observer());
+    let cache: ContextCache<MockHandler<TracedLoader>> = ContextCache::new();
+
+    let receivers = vec![
+      loader.cached_load_by(1, &cache),
+      loader.cached_load_by(2, &cache),
+    ];
+
+    futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv()))
+      .await
+      .unwrap();
+
+    {
+      let cx = Context::current_with_span(provider.tracer("app").start("request"));
+      let _guard = cx.attach();
+
+      loader.cached_load_by(1, &cache);
+    }
+
+    let spans = exporter.get_finished_spans().unwrap();
+
+    let batch = spans
+      .iter()
+      .find(|span| span.name.eq("dataloader.batch"))
+      .unwrap();
+
+    assert!(batch
+      .attributes
+      .contains(&KeyValue::new("batch.size", 2_i64)));
+    assert!(batch
+      .attributes
+      .contains(&KeyValue::new("batch.ok_count", 2_i64)));
+
+    let resolved = spans
+      .iter()
+      .filter(|span| span.name.eq("dataloader.key.resolve"))
+      .filter(|span| span.parent_span_id.eq(&batch.span_context.span_id()))
+      .count();
+
+    assert_eq!(resolved, 2);
+
+    let request = spans.iter().find(|span| span.name.eq("request")).unwrap();
+
+    assert!(request
+      .events
+      .iter()
+      .any(|event| event.name.eq("dataloader.cache_hit")));
+  }
+}
@@ -0,0 +1,310 @@
+//! Experimental: predictive loading of keys likely to be requested, enabled per loader by [`crate::loader::DataLoader::enable_preemptive_loading`]
+//!
+//! Keys loaded together within successful batches are recorded as co-occurring, and upon a key being loaded by [`crate::loader::DataLoader::load_by`], keys that have consistently been loaded alongside it are loaded in the background at [`crate::task::Priority::Low`]. Preemptive loads are held by the loader to be consumed by the next [`crate::loader::DataLoader::load_by`] of the key, and are discarded if not consumed within the `ttl` preemptive loading was enabled with. Expiry is checked upon access rather than by timer
+//!
+//! ```rust
+//! UserLoader::loader().with(|loader| {
+//!   loader.enable_preemptive_loading(Duration::from_secs(30));
+//!
+//!   // Warm a key known to be needed shortly, without awaiting it
+//!   loader.preemptive_load(user_id);
+//! });
+//! ```
+use crate::{interceptor::BatchInterceptor, request::WatchReceiver, task::TaskHandler, Key};
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tokio::time::Instant;
+
+// Batches of more keys than this aren't recorded, as co-occurrences grow quadratically with batch size
+const MAX_RECORDED_BATCH_SIZE: usize = 32;
+
+// Batches a key must have been loaded in before its co-occurrences are trusted
+const MIN_OCCURRENCES: usize = 3;
+
+// Proportion of the batches of a key that a co-occurring key must have been loaded in to be preemptively loaded
+const MIN_CONFIDENCE: f64 = 0.8;
+
+// Keys tracked before every count is halved and keys no longer loaded are pruned
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+// Co-occurring keys tracked per key before the counts of that key are halved and pruned
+const MAX_CO_OCCURRENCES_PER_KEY: usize = 64;
+
+type Counts<K> = HashMap<K, (usize, HashMap<K, usize>)>;
+
+/// Counts of the keys loaded together within successful batches. Counts decay by half upon exceeding [`MAX_TRACKED_KEYS`] or [`MAX_CO_OCCURRENCES_PER_KEY`], so that keys no longer loaded together are forgotten while the confidence of keys still loaded together is retained
+pub(crate) struct CoOccurrences<K: Key> {
+  counts: Mutex<Counts<K>>,
+}
+
+// Halve occurrences alongside co-occurrences so that their proportion is unchanged, dropping counts reaching zero
+fn decay<K: Key>(occurrences: &mut usize, co_occurrences: &mut HashMap<K, usize>) {
+  *occurrences /= 2;
+
+  co_occurrences.retain(|_, count| {
+    *count /= 2;
+    (*count).gt(&0)
+  });
+}
+
+impl<K: Key> CoOccurrences<K> {
+  fn new() -> Self {
+    CoOccurrences {
+      counts: Mutex::new(HashMap::new()),
+    }
+  }
+
+  fn record(&self, keys: &[&K]) {
+    let mut counts = self.counts.lock().unwrap();
+
+    for key in keys.iter() {
+      let (occurrences, co_occurrences) = counts.entry((*key).to_owned()).or_default();
+
+      *occurrences += 1;
+
+      for other in keys.iter().filter(|other| other.ne(&key)) {
+        *co_occurrences.entry((*other).to_owned()).or_default() += 1;
+      }
+
+      while co_occurrences.len().gt(&MAX_CO_OCCURRENCES_PER_KEY) {
+        decay(occurrences, co_occurrences);
+      }
+    }
+
+    while counts.len().gt(&MAX_TRACKED_KEYS) {
+      counts.retain(|_, (occurrences, co_occurrences)| {
+        decay(occurrences, co_occurrences);
+        (*occurrences).gt(&0)
+      });
+    }
+  }
+
+  /// Keys loaded within at least [`MIN_CONFIDENCE`] of the batches of `key`
+  pub(crate) fn predict(&self, key: &K) -> Vec<K> {
+    let counts = self.counts.lock().unwrap();
+
+    match counts.get(key) {
+      Some((occurrences, co_occurrences)) if occurrences.ge(&MIN_OCCURRENCES) => co_occurrences
+        .iter()
+        .filter(|(_, count)| **count as f64 / *occurrences as f64 >= MIN_CONFIDENCE)
+        .map(|(key, _)| key.to_owned())
+        .collect(),
+      _ => vec![],
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl<K, V, E> BatchInterceptor<K, V, E> for CoOccurrences<K>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+  E: Send + Sync + 'static,
+{
+  async fn after_resolve(&self, results: &HashMap<K, Arc<V>>) {
+    if results.len() > 1 && results.len() <= MAX_RECORDED_BATCH_SIZE {
+      let keys: Vec<&K> = results.keys().collect();
+      self.record(&keys);
+    }
+  }
+}
+
+// The receiver of a preemptive load and the instant it expires at
+type PreemptiveLoad<T> = (
+  WatchReceiver<<T as TaskHandler>::Value, <T as TaskHandler>::Error>,
+  Instant,
+);
+
+/// The preemptive loads of a thread local loader awaiting consumption
+pub(crate) struct PreemptiveLoads<T: TaskHandler> {
+  pub(crate) co_occurrences: Arc<CoOccurrences<T::Key>>,
+  loads: RefCell<HashMap<T::Key, PreemptiveLoad<T>>>,
+  ttl: Cell<Duration>,
+}
+
+impl<T> PreemptiveLoads<T>
+where
+  T: TaskHandler,
+{
+  pub(crate) fn new(ttl: Duration) -> Self {
+    PreemptiveLoads {
+      co_occurrences: Arc::new(CoOccurrences::new()),
+      loads: RefCell::new(HashMap::new()),
+      ttl: Cell::new(ttl),
+    }
+  }
+
+  pub(crate) fn set_ttl(&self, ttl: Duration) {
+    self.ttl.set(ttl);
+  }
+
+  /// Whether an unexpired preemptive load of `key` is already held
+  pub(crate) fn contains(&self, key: &T::Key) -> bool {
+    self
+      .loads
+      .borrow()
+      .get(key)
+      .is_some_and(|(_, expires_at)| expires_at.gt(&Instant::now()))
+  }
+
+  pub(crate) fn insert(&self, key: T::Key, rx: WatchReceiver<T::Value, T::Error>) {
+    let now = Instant::now();
+    let mut loads = self.loads.borrow_mut();
+
+    loads.retain(|_, (_, expires_at)| now.lt(expires_at));
+    loads.insert(key, (rx, now + self.ttl.get()));
+  }
+
+  /// Take the preemptive load of `key`, if held and not yet expired
+  pub(crate) fn take(&self, key: &T::Key) -> Option<WatchReceiver<T::Value, T::Error>> {
+    match self.loads.borrow_mut().remove(key) {
+      Some((rx, expires_at)) if expires_at.gt(&Instant::now()) => Some(rx),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::{DataStore, LocalLoader};
+  use crate::testing::{BatchRecorder, MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use std::{collections::HashMap, sync::Arc, time::Duration};
+
+  fn echo(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+    Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<PredictedLoader>")]
+  pub struct PredictedLoader;
+
+  impl MockBackend for PredictedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      echo(keys)
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<ExpiringLoader>")]
+  pub struct ExpiringLoader;
+
+  impl MockBackend for ExpiringLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      echo(keys)
+    }
+  }
+
+  #[test]
+  fn it_bounds_tracked_co_occurrences() {
+    let co_occurrences: CoOccurrences<i32> = CoOccurrences::new();
+
+    for _ in 0..64 {
+      co_occurrences.record(&[&-1, &-2]);
+    }
+
+    // Key 0 is loaded with more distinct keys than are tracked per key
+    for other in 1..=MAX_CO_OCCURRENCES_PER_KEY as i32 * 2 {
+      co_occurrences.record(&[&0, &other]);
+    }
+
+    assert!(co_occurrences.counts.lock().unwrap()[&0].1.len() <= MAX_CO_OCCURRENCES_PER_KEY);
+
+    // Distinct pairs of keys loaded once each exceed the keys tracked
+    for key in (1..=MAX_TRACKED_KEYS as i32).map(|key| key * 2) {
+      co_occurrences.record(&[&key, &(key + 1)]);
+    }
+
+    assert!(co_occurrences.counts.lock().unwrap().len() <= MAX_TRACKED_KEYS);
+
+    // Keys consistently loaded together outlast the decay of keys loaded once
+    assert_eq!(co_occurrences.predict(&-1), vec![-2]);
+  }
+
+  #[tokio::test]
+  async fn it_preemptively_loads_co_occurring_keys() {
+    let recorder = BatchRecorder::<PredictedLoader>::start();
+    let loader = <PredictedLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| loader.enable_preemptive_loading(Duration::from_secs(30)));
+
+    for _ in 0..3 {
+      let receivers = loader.with(|loader| vec![loader.load_by(3), loader.load_by(4)]);
+
+      for rx in receivers {
+        rx.recv().await.unwrap();
+      }
+
+      tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(3)).recv().await,
+      Ok(Some(Arc::new(3)))
+    );
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+
+    // Key 4 was loaded in the background and is consumed without another batch
+    assert_eq!(
+      loader.with(|loader| loader.load_by(4)).recv().await,
+      Ok(Some(Arc::new(4)))
+    );
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+
+    // Whereas loading key 4 in turn preemptively loads key 3
+    assert_eq!(
+      recorder.batches(),
+      vec![
+        vec![3, 4],
+        vec![3, 4],
+        vec![3, 4],
+        vec![3],
+        vec![4],
+        vec![3]
+      ]
+    );
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_discards_unconsumed_preemptive_loads() {
+    let recorder = BatchRecorder::<ExpiringLoader>::start();
+    let loader = <ExpiringLoader as LocalLoader<DataStore>>::loader();
+
+    loader.with(|loader| {
+      loader.enable_preemptive_loading(Duration::from_secs(1));
+      loader.preemptive_load(1);
+      loader.preemptive_load(2);
+    });
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(1)).recv().await,
+      Ok(Some(Arc::new(1)))
+    );
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(
+      loader.with(|loader| loader.load_by(2)).recv().await,
+      Ok(Some(Arc::new(2)))
+    );
+
+    assert_eq!(recorder.batches(), vec![vec![1, 2], vec![2]]);
+  }
+}
@@ -0,0 +1,242 @@
+//! Prometheus metric families for observing a [`DataLoader`](crate::loader::DataLoader), labeled by `handler_type`
+//!
+//! ```rust
+//! let metrics = Arc::new(DataLoaderMetrics::new("UserLoader"));
+//! UserLoader::loader().with(|loader| loader.set_metrics(metrics.clone()));
+//!
+//! let encoded = prometheus::TextEncoder::new().encode_to_string(&metrics.registry().gather())?;
+//! ```
+
+use prometheus::{Gauge, Histogram, HistogramOpts, IntCounterVec, Opts, Registry};
+use std::{
+  sync::atomic::{AtomicU64, Ordering},
+  time::Duration,
+};
+
+/// The outcome of a request once resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+  Found,
+  NotFound,
+  Error,
+}
+
+impl Outcome {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Outcome::Found => "found",
+      Outcome::NotFound => "not_found",
+      Outcome::Error => "error",
+    }
+  }
+}
+
+/// Metric families registered on a [`Registry`] of their own, to be gathered directly or merged into an application registry
+pub struct DataLoaderMetrics {
+  registry: Registry,
+  requests_total: IntCounterVec,
+  batch_duration_seconds: Histogram,
+  batch_size_count: Histogram,
+  cache_ratio: Gauge,
+  cache_hits: AtomicU64,
+  cache_lookups: AtomicU64,
+}
+
+impl DataLoaderMetrics {
+  pub fn new(handler_name: &str) -> Self {
+    let registry = Registry::new();
+
+    let requests_total = IntCounterVec::new(
+      Opts::new("dataloader_requests_total", "Requests resolved by outcome")
+        .const_label("handler_type", handler_name),
+      &["outcome"],
+    )
+    .unwrap();
+
+    let batch_duration_seconds = Histogram::with_opts(
+      HistogramOpts::new(
+        "dataloader_batch_duration_seconds",
+        "Time from batch assignment until resolution",
+      )
+      .const_label("handler_type", handler_name),
+    )
+    .unwrap();
+
+    let batch_size_count = Histogram::with_opts(
+      HistogramOpts::new(
+        "dataloader_batch_size_count",
+        "Requests per batch assignment",
+      )
+      .const_label("handler_type", handler_name)
+      .buckets(prometheus::exponential_buckets(1.0, 2.0, 12).unwrap()),
+    )
+    .unwrap();
+
+    let cache_ratio = Gauge::with_opts(
+      Opts::new(
+        "dataloader_cache_ratio",
+        "Ratio of cached loads served without enqueueing a request",
+      )
+      .const_label("handler_type", handler_name),
+    )
+    .unwrap();
+
+    registry
+      .register(Box::new(requests_total.clone()))
+      .expect("failed to register dataloader_requests_total");
+    registry
+      .register(Box::new(batch_duration_seconds.clone()))
+      .expect("failed to register dataloader_batch_duration_seconds");
+    registry
+      .register(Box::new(batch_size_count.clone()))
+      .expect("failed to register dataloader_batch_size_count");
+    registry
+      .register(Box::new(cache_ratio.clone()))
+      .expect("failed to register dataloader_cache_ratio");
+
+    DataLoaderMetrics {
+      registry,
+      requests_total,
+      batch_duration_seconds,
+      batch_size_count,
+      cache_ratio,
+      cache_hits: AtomicU64::new(0),
+      cache_lookups: AtomicU64::new(0),
+    }
+  }
+
+  pub fn registry(&self) -> &Registry {
+    &self.registry
+  }
+
+  pub(crate) fn observe_requests(&self, outcome: Outcome, count: usize) {
+    if count.gt(&0) {
+      self
+        .requests_total
+        .with_label_values(&[outcome.as_str()])
+        .inc_by(count as u64);
+    }
+  }
+
+  pub(crate) fn observe_batch_size(&self, batch_size: usize) {
+    self.batch_size_count.observe(batch_size as f64);
+  }
+
+  pub(crate) fn observe_batch_duration(&self, duration: Duration) {
+    self.batch_duration_seconds.observe(duration.as_secs_f64());
+  }
+
+  pub(crate) fn observe_cache_lookup(&self, hit: bool) {
+    let hits = if hit {
+      self.cache_hits.fetch_add(1, Ordering::Relaxed) + 1
+    } else {
+      self.cache_hits.load(Ordering::Relaxed)
+    };
+
+    let lookups = self.cache_lookups.fetch_add(1, Ordering::Relaxed) + 1;
+
+    self.cache_ratio.set(hits as f64 / lookups as f64);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loader::{DataStore, LocalLoader};
+  use crate::request::ContextCache;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use std::{collections::HashMap, sync::Arc};
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<OddLoader>")]
+  pub struct OddLoader;
+
+  impl MockBackend for OddLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(
+        keys
+          .iter()
+          .filter(|key| *key % 2 == 1)
+          .map(|key| (*key, Arc::new(*key)))
+          .collect(),
+      )
+    }
+  }
+
+  #[tokio::test]
+  async fn it_registers_labeled_metric_families() {
+    let metrics = Arc::new(DataLoaderMetrics::new("OddLoader"));
+    let loader = <OddLoader as LocalLoader<DataStore>>::loader();
+    let cache: ContextCache<MockHandler<OddLoader>> = ContextCache::new();
+
+    loader.with(|loader| loader.set_metrics(metrics.clone()));
+
+    let receivers: Vec<_> = loader.with(|loader| (0..4).map(|key| loader.load_by(key)).collect());
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    for _ in 0..2 {
+      let rx = loader.with(|loader| loader.cached_load_by(1, &cache));
+      rx.recv().await.unwrap();
+    }
+
+    let families: HashMap<String, _> = metrics
+      .registry()
+      .gather()
+      .into_iter()
+      .map(|family| (family.get_name().to_owned(), family))
+      .collect();
+
+    let label_names = |name: &str| -> Vec<Vec<String>> {
+      families[name]
+        .get_metric()
+        .iter()
+        .map(|metric| {
+          metric
+            .get_label()
+            .iter()
+            .map(|label| label.get_name().to_owned())
+            .collect()
+        })
+        .collect()
+    };
+
+    assert_eq!(
+      label_names("dataloader_requests_total"),
+      vec![
+        vec!["handler_type".to_owned(), "outcome".to_owned()],
+        vec!["handler_type".to_owned(), "outcome".to_owned()],
+      ]
+    );
+
+    for name in [
+      "dataloader_batch_duration_seconds",
+      "dataloader_batch_size_count",
+      "dataloader_cache_ratio",
+    ]
+    .iter()
+    {
+      assert_eq!(label_names(name), vec![vec!["handler_type".to_owned()]]);
+    }
+
+    assert_eq!(
+      metrics.requests_total.with_label_values(&["found"]).get(),
+      3
+    );
+    assert_eq!(
+      metrics
+        .requests_total
+        .with_label_values(&["not_found"])
+        .get(),
+      2
+    );
+    assert_eq!(metrics.cache_ratio.get(), 0.5);
+  }
+}
@@ -0,0 +1,137 @@
+use crate::{
+  loader::{DataLoader, LocalLoader, StoreType},
+  task::{CompletionReceipt, PendingAssignment, Task, TaskHandler},
+};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use std::{
+  any::TypeId,
+  collections::HashMap,
+  num::NonZeroU32,
+  sync::{Mutex, OnceLock},
+};
+
+/// A [`TaskHandler`] wrapper that limits the rate at which batches are submitted to the backend of the inner handler to `RPS` batches per second, which must be greater than 0. Each batch counts as a single request regardless of batch size, and because the limiter is awaited prior to task assignment loads continue to be batched while rate limited
+///
+/// ```rust
+/// #[derive(Loader)]
+/// #[data_loader(handler = "RateLimitedLoader<BatchHandler<UserLoader>, 100>")]
+/// pub struct UserLoader;
+///
+/// #[async_trait::async_trait]
+/// impl BatchLoader for UserLoader {
+///   type Key = i32;
+///   type Value = User;
+///   type Error = ApiError;
+///
+///   async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<User>>, ApiError> {
+///     api::get_users(keys).await
+///   }
+/// }
+/// ```
+pub struct RateLimitedLoader<T: TaskHandler, const RPS: u32>(T);
+
+impl<T, const RPS: u32> RateLimitedLoader<T, RPS>
+where
+  T: TaskHandler,
+{
+  // Evaluated upon monomorphization, such that a rate of 0 fails to compile
+  const RPS: NonZeroU32 = match NonZeroU32::new(RPS) {
+    Some(rps) => rps,
+    None => panic!("RateLimitedLoader RPS must be greater than 0"),
+  };
+
+  // Rate limiters are created on first use and live for the duration of the program
+  fn rate_limiter() -> &'static DefaultDirectRateLimiter {
+    let mut limiters = RATE_LIMITERS.get_or_init(Default::default).lock().unwrap();
+
+    limiters
+      .entry(TypeId::of::<Self>())
+      .or_insert_with(|| Box::leak(Box::new(RateLimiter::direct(Quota::per_second(Self::RPS)))))
+  }
+}
+
+static RATE_LIMITERS: OnceLock<Mutex<HashMap<TypeId, &'static DefaultDirectRateLimiter>>> =
+  OnceLock::new();
+
+#[async_trait::async_trait]
+impl<T, const RPS: u32> TaskHandler for RateLimitedLoader<T, RPS>
+where
+  T: TaskHandler,
+{
+  type Key = T::Key;
+  type Value = T::Value;
+  type Error = T::Error;
+  crate::forward_task_handler!(T);
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    Self::rate_limiter().until_ready().await;
+
+    T::handle_task(task).await
+  }
+}
+
+impl<Loader, Store, const RPS: u32> LocalLoader<Store> for RateLimitedLoader<Loader, RPS>
+where
+  Loader: TaskHandler + LocalLoader<Store>,
+  Store: StoreType,
+{
+  type Handler = <Loader as LocalLoader<Store>>::Handler;
+  fn loader() -> &'static std::thread::LocalKey<DataLoader<Self::Handler>> {
+    Loader::loader()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestError;
+  use crate::{
+    batch::{BatchHandler, BatchLoader},
+    loader::DataStore,
+  };
+  use deque_loader_derive::Loader;
+  use std::{
+    sync::{
+      atomic::{AtomicUsize, Ordering},
+      Arc,
+    },
+    time::{Duration, Instant},
+  };
+
+  static BATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(Loader)]
+  #[data_loader(handler = "RateLimitedLoader<BatchHandler<ThrottledLoader>, 20>")]
+  pub struct ThrottledLoader;
+
+  #[async_trait::async_trait]
+  impl BatchLoader for ThrottledLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn load(keys: Vec<i32>) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      BATCH_COUNT.fetch_add(1, Ordering::SeqCst);
+      Ok(keys.into_iter().map(|key| (key, Arc::new(key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_limits_batch_rate() -> Result<(), TestError> {
+    let start = Instant::now();
+
+    // The quota allows an initial burst of 20 batches, after which one batch is replenished every 50ms
+    for key in 0..30 {
+      let rx =
+        <ThrottledLoader as LocalLoader<DataStore>>::loader().with(|loader| loader.load_by(key));
+      assert_eq!(rx.recv().await?, Some(Arc::new(key)));
+    }
+
+    assert_eq!(BATCH_COUNT.load(Ordering::SeqCst), 30);
+    assert!(start.elapsed().ge(&Duration::from_millis(450)));
+
+    Ok(())
+  }
+}
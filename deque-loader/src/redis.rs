@@ -1,8 +1,10 @@
 mod cache;
 mod client;
 mod connection;
+mod invalidation;
 mod loader;
 
 pub use cache::*;
 pub use connection::*;
+pub use invalidation::*;
 pub use loader::*;
@@ -2,6 +2,7 @@ use super::{get_tracked_connection, TrackedConnection};
 use crate::{
   key::Key,
   loader::{CacheStore, DataLoader, DataStore, LocalLoader},
+  request::CacheCallback,
   task::{CompletionReceipt, LoadBatch, PendingAssignment, Task, TaskAssignment, TaskHandler},
 };
 use log::error;
@@ -22,7 +23,7 @@ where
   pub(crate) fn update_cache_on_load(&mut self) {
     let runtime_handle = Handle::current();
 
-    let cache_cb: Arc<(dyn Fn(&K, &V) + Send + Sync + 'static)> = Arc::new(move |k, v| {
+    let cache_cb: CacheCallback<K, V> = Arc::new(move |k, v| {
       infallibly_update_cache(&runtime_handle, k, v);
     });
 
@@ -63,9 +64,8 @@ where
   type Key = <<T as LocalLoader<DataStore>>::Handler as TaskHandler>::Key;
   type Value = <<T as LocalLoader<DataStore>>::Handler as TaskHandler>::Value;
   type Error = <<T as LocalLoader<DataStore>>::Handler as TaskHandler>::Error;
-  const CORES_PER_WORKER_GROUP: usize =
-    <<T as LocalLoader<DataStore>>::Handler as TaskHandler>::CORES_PER_WORKER_GROUP;
   const MAX_BATCH_SIZE: Option<usize> = None;
+  crate::forward_task_handler!(<T as LocalLoader<DataStore>>::Handler, except MAX_BATCH_SIZE);
 
   async fn handle_task(
     task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
@@ -128,10 +128,7 @@ where
       conn.get(cache_keys).await?
     };
 
-    let mut data: HashMap<
-      <<T as LocalLoader<DataStore>>::Handler as TaskHandler>::Key,
-      Arc<<<T as LocalLoader<DataStore>>::Handler as TaskHandler>::Value>,
-    > = HashMap::new();
+    let mut data = HashMap::new();
 
     data.extend(
       keys
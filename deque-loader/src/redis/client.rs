@@ -1,7 +1,6 @@
 use crossbeam::atomic::AtomicCell;
-use once_cell::sync::Lazy;
 use redis::{Client, RedisResult};
-use std::env;
+use std::{env, sync::OnceLock};
 use url::Url;
 
 fn get_connection_url() -> String {
@@ -54,11 +53,16 @@ fn get_database_index(database_url: &str) -> Option<i64> {
 
 pub(crate) static DATABASE_INDEX: AtomicCell<i64> = AtomicCell::new(0);
 
-pub(crate) static CLIENT: Lazy<RedisResult<Client>> = Lazy::new(|| {
-  let database_url = get_connection_url();
-  let database_index = get_database_index(&database_url).unwrap_or(0);
+static CLIENT: OnceLock<RedisResult<Client>> = OnceLock::new();
 
-  DATABASE_INDEX.store(database_index);
+// The client is opened upon first use, storing the database index of the connection url prior to any connection being established
+pub(crate) fn client() -> &'static RedisResult<Client> {
+  CLIENT.get_or_init(|| {
+    let database_url = get_connection_url();
+    let database_index = get_database_index(&database_url).unwrap_or(0);
 
-  redis::Client::open(database_url)
-});
+    DATABASE_INDEX.store(database_index);
+
+    redis::Client::open(database_url)
+  })
+}
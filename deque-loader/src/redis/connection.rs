@@ -1,16 +1,18 @@
-use super::client::{CLIENT, DATABASE_INDEX};
+use super::client::{client, DATABASE_INDEX};
 use arc_swap::{ArcSwap, Guard};
 use futures_util::{future::FutureExt, StreamExt};
 use redis::{
   aio::{ConnectionLike, MultiplexedConnection},
   Client, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value,
 };
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::{sync::Notify, try_join};
 
+type InvalidateKeysFn = Box<dyn Fn(&[String]) + Send + Sync>;
+
 struct InvalidationObserver {
   invalidate_cache_fn: Box<dyn Fn() + Send + Sync>,
-  invalidate_keys_fn: Box<dyn Fn(&[String]) + Send + Sync>,
+  invalidate_keys_fn: InvalidateKeysFn,
 }
 
 inventory::collect!(InvalidationObserver);
@@ -94,7 +96,7 @@ impl CacheInvalidator {
       .compare_and_swap(&current, Arc::new(InvalidatorState::Connecting));
 
     if Arc::ptr_eq(&prev, &current) {
-      match &*CLIENT {
+      match client() {
         Ok(client) => {
           tokio::task::spawn(async move {
             if let Err(err) = self.start_new_connection(client).await {
@@ -129,7 +131,7 @@ impl CacheInvalidator {
       let mut pubsub = conn.into_pubsub();
 
       let result: RedisResult<()> = async {
-        let _ = pubsub.subscribe("__redis__:invalidate").await?;
+        pubsub.subscribe("__redis__:invalidate").await?;
 
         let stream = pubsub.into_on_message();
 
@@ -219,7 +221,7 @@ impl ConnectionManager {
         ConnectionState::Connected(_) => break Ok(state),
       }
 
-      i = i + 1;
+      i += 1;
     }
   }
 
@@ -234,7 +236,7 @@ impl ConnectionManager {
       .compare_and_swap(current, Arc::new(ConnectionState::Connecting));
 
     if Arc::ptr_eq(&prev, current) {
-      match &*CLIENT {
+      match client() {
         Ok(client) => {
           tokio::task::spawn(async move {
             if let Err(err) = self.start_new_connection(client).await {
@@ -340,11 +342,10 @@ impl ConnectionLike for TrackedConnection {
 
 /// An eventualistic [`MultiplexedConnection`] with Redis-assisted client-side cache invalidation tracking and managed reconnection
 pub fn get_tracked_connection() -> TrackedConnection {
-  #[static_init::dynamic(0)]
-  static INVALIDATOR: CacheInvalidator = CacheInvalidator::default();
+  static INVALIDATOR: OnceLock<CacheInvalidator> = OnceLock::new();
 
   thread_local! {
-    static CONNECTION_MANAGER: Arc<ConnectionManager> = ConnectionManager::new(unsafe { &INVALIDATOR });
+    static CONNECTION_MANAGER: Arc<ConnectionManager> = ConnectionManager::new(INVALIDATOR.get_or_init(CacheInvalidator::default));
   }
 
   CONNECTION_MANAGER.with(|connection_manager| connection_manager.get_tracked_connection())
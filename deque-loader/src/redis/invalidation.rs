@@ -0,0 +1,139 @@
+use crate::{request::ContextCache, task::TaskHandler};
+use futures_util::StreamExt;
+use log::error;
+use redis::{aio::Connection, Commands, ErrorKind, RedisError, RedisResult};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::{JoinError, JoinHandle};
+
+/// Publish `key` on `channel` for every [`RedisInvalidationListener`] subscribed to it to invalidate
+pub fn publish_invalidation<K: Serialize>(
+  conn: &mut redis::Connection,
+  channel: &str,
+  key: &K,
+) -> RedisResult<()> {
+  let payload = bincode::serialize(key).map_err(|err| {
+    RedisError::from((
+      ErrorKind::TypeError,
+      "Unable to serialize invalidation key",
+      err.to_string(),
+    ))
+  })?;
+
+  conn.publish(channel, payload)
+}
+
+/// Keeps a [`ContextCache`] in sync with writes made by other processes by invalidating keys published via [`publish_invalidation`] on a Redis pub/sub channel
+///
+/// Delivery is best-effort and not strongly consistent: Redis pub/sub is fire-and-forget, so keys published while the listener is disconnected are never received, and a load racing with an invalidation may cache a value read before the write it invalidates. Invalidations are applied in the order they are published
+///
+/// ```rust
+/// let conn = client.get_tokio_connection().await?;
+/// let listener =
+///   RedisInvalidationListener::start(ContextCache::<UserLoader>::global(), conn, "users").await?;
+///
+/// publish_invalidation(&mut client.get_connection()?, "users", &user_id)?;
+/// ```
+pub struct RedisInvalidationListener(JoinHandle<()>);
+
+impl RedisInvalidationListener {
+  /// Subscribe to `channel` and begin invalidating `cache` from a background task. The subscription is established before returning, so that invalidations published thereafter are received
+  pub async fn start<T, C>(cache: C, conn: Connection, channel: &str) -> RedisResult<Self>
+  where
+    T: TaskHandler,
+    T::Key: DeserializeOwned,
+    C: AsRef<ContextCache<T>> + Send + Sync + 'static,
+  {
+    let mut pubsub = conn.into_pubsub();
+
+    pubsub.subscribe(channel).await?;
+
+    let handle = tokio::task::spawn(async move {
+      let mut messages = pubsub.on_message();
+
+      while let Some(msg) = messages.next().await {
+        invalidate_payload(cache.as_ref(), msg.get_payload());
+      }
+    });
+
+    Ok(RedisInvalidationListener(handle))
+  }
+
+  /// Unsubscribe and stop invalidating
+  pub fn stop(self) {
+    self.0.abort();
+  }
+
+  /// Wait for the subscription to end, such as upon the connection closing. Errors should the listener have panicked or been stopped
+  pub async fn join(self) -> Result<(), JoinError> {
+    self.0.await
+  }
+}
+
+// Invalidate the key of a published payload, logging payloads that can't be read rather than ending the subscription
+fn invalidate_payload<T>(cache: &ContextCache<T>, payload: RedisResult<Vec<u8>>)
+where
+  T: TaskHandler,
+  T::Key: DeserializeOwned,
+{
+  let payload = match payload {
+    Ok(payload) => payload,
+    Err(err) => {
+      error!(
+        "{} unable to read invalidation payload: {:?}",
+        tynm::type_name::<T>(),
+        err
+      );
+      return;
+    }
+  };
+
+  match bincode::deserialize::<T::Key>(&payload) {
+    Ok(key) => cache.invalidate(&key),
+    Err(err) => error!(
+      "{} unable to deserialize invalidation key: {:?}",
+      tynm::type_name::<T>(),
+      err
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use std::{collections::HashMap, sync::Arc};
+
+  pub struct InvalidatedBackend;
+
+  impl MockBackend for InvalidatedBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[test]
+  fn it_invalidates_published_keys_and_skips_unreadable_payloads() {
+    let cache: ContextCache<MockHandler<InvalidatedBackend>> = ContextCache::new();
+
+    cache.warm(1, Arc::new(1));
+    cache.warm(2, Arc::new(2));
+
+    invalidate_payload(&cache, Ok(vec![0xff]));
+    invalidate_payload(
+      &cache,
+      Err(RedisError::from((ErrorKind::TypeError, "unreadable"))),
+    );
+
+    assert!(cache.contains(&1));
+    assert!(cache.contains(&2));
+
+    invalidate_payload(&cache, Ok(bincode::serialize(&1).unwrap()));
+
+    assert!(!cache.contains(&1));
+    assert!(cache.contains(&2));
+  }
+}
@@ -2,18 +2,39 @@ use super::{get_tracked_connection, TrackedConnection};
 use crate::{
   key::Key,
   loader::{DataLoader, LocalLoader, StoreType},
-  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+  task::{
+    task_handler_defaults, CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler,
+  },
 };
 use redis::{ErrorKind, RedisError};
 use std::{collections::HashMap, sync::Arc};
 
+/// The error of a [`RedisHandler`], being the [`ErrorKind`] of the [`RedisError`] loading failed with
+#[cfg(not(feature = "std-error"))]
+pub type RedisLoadError = ErrorKind;
+
+/// The error of a [`RedisHandler`], being the [`ErrorKind`] of the [`RedisError`] loading failed with. As [`ErrorKind`] doesn't implement [`std::error::Error`], it's wrapped with the `std-error` feature
+#[cfg(feature = "std-error")]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Redis error: {0:?}")]
+pub struct RedisLoadError(pub ErrorKind);
+
+#[cfg(not(feature = "std-error"))]
+fn load_error(err: RedisError) -> RedisLoadError {
+  err.kind()
+}
+
+#[cfg(feature = "std-error")]
+fn load_error(err: RedisError) -> RedisLoadError {
+  RedisLoadError(err.kind())
+}
+
 /// a [`redis`] specific loader interface using thread local multiplexed redis connections
 #[async_trait::async_trait]
 pub trait RedisLoader: Sized + Send + Sync + 'static {
   type Key: Key;
   type Value: Send + Sync + Clone + 'static;
-  const CORES_PER_WORKER_GROUP: usize = 4;
-  const MAX_BATCH_SIZE: Option<usize> = None;
+  task_handler_defaults!(RedisLoadError);
 
   async fn load(
     conn: TrackedConnection,
@@ -29,18 +50,22 @@ where
 {
   type Key = T::Key;
   type Value = T::Value;
-  type Error = ErrorKind;
-  const CORES_PER_WORKER_GROUP: usize = T::CORES_PER_WORKER_GROUP;
-  const MAX_BATCH_SIZE: Option<usize> = T::MAX_BATCH_SIZE;
+  type Error = RedisLoadError;
+  crate::forward_task_handler!(T);
 
   async fn handle_task(
     task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
   ) -> Task<CompletionReceipt> {
-    match task.get_assignment::<Self>().await {
+    let assignment = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.validate::<Self>(),
+      assignment => assignment,
+    };
+
+    match assignment {
       TaskAssignment::LoadBatch(task) => {
         let keys = task.keys();
         let conn = get_tracked_connection();
-        let result = T::load(conn, keys).await.map_err(|err| err.kind());
+        let result = T::load(conn, keys).await.map_err(load_error);
         task.resolve(result)
       }
       TaskAssignment::NoAssignment(receipt) => receipt,
@@ -1,16 +1,47 @@
-use crate::{task::TaskHandler, Key};
+use crate::{
+  backpressure::PendingLoad,
+  dedup::InFlight,
+  task::{spawn_on, TaskHandler},
+  Key,
+};
 use flurry::HashMap;
-use std::sync::Arc;
-use tokio::sync::{oneshot, watch};
+use futures_util::{
+  future::{BoxFuture, FutureExt},
+  Stream,
+};
+use rayon::{prelude::*, ThreadPool};
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+  any::Any,
+  collections::hash_map::RandomState,
+  fmt,
+  future::{Future, IntoFuture},
+  hash::BuildHasher,
+  pin::Pin,
+  sync::{Arc, OnceLock, Weak},
+  task::{Context, Poll},
+  time::Duration,
+};
+use thiserror::Error;
+use tokio::{
+  sync::{broadcast, oneshot, watch, OnceCell},
+  time::{Instant, MissedTickBehavior},
+};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LoadState<V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
   Ready(Result<Option<Arc<V>>, E>),
   Pending,
+  /// The request was cancelled without resolving, such as by the loader being drained before the request could be assigned to a batch, and [`TaskHandler::shutdown_error`] is `None`. Observed by [`WatchReceiver::try_recv`] as [`RecvCancelled`]
+  Cancelled,
 }
 
+// Receivers of a ContextCache with RefetchPolicy::DropWhenCold carry the watcher token of their entry, such that entries are cold once the cache holds the only token
 #[derive(Clone)]
 pub struct WatchReceiver<V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static>(
   watch::Receiver<LoadState<V, E>>,
+  Option<Arc<()>>,
 );
 
 pub struct OneshotReceiver<V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static>(
@@ -23,7 +54,7 @@ where
   E: Send + Sync + Clone + 'static,
 {
   fn from(rx: watch::Receiver<LoadState<V, E>>) -> Self {
-    WatchReceiver(rx)
+    WatchReceiver(rx, None)
   }
 }
 
@@ -42,13 +73,38 @@ where
   V: Send + Sync + Clone + 'static,
   E: Send + Sync + Clone + 'static,
 {
-  pub async fn recv(mut self) -> Result<Option<Arc<V>>, E> {
+  pub(crate) fn peek(&self) -> Option<Result<Option<Arc<V>>, E>> {
+    match &*self.0.borrow() {
+      LoadState::Ready(result) => Some(result.to_owned()),
+      LoadState::Pending | LoadState::Cancelled => None,
+    }
+  }
+
+  /// Convert into a [`SharedReceiver`] that can be cloned and awaited from many tasks
+  pub fn shared(self) -> SharedReceiver<V, E> {
+    SharedReceiver {
+      rx: Arc::new(self.0),
+      watcher: self.1,
+      pending: None,
+    }
+  }
+
+  /// Only for loads that cannot be cancelled. A load is cancelled, without a [`TaskHandler::shutdown_error`], when the loader is drained, upon the startup timeout of [`crate::loader::DataLoader::set_worker_startup_timeout`] elapsing, when the load it was deduplicated onto is cancelled, or when the task handler drops the request; as a cancelled load has no error of type `E` to resolve as, this then panics. Receive loads that may be cancelled by [`WatchReceiver::try_recv`], which fails with [`RecvCancelled`] instead. As the entry of a cancelled load is removed from its [`ContextCache`], only receivers handed out prior to cancellation observe it
+  pub async fn recv(self) -> Result<Option<Arc<V>>, E> {
+    self.try_recv().await.expect(
+      "load cancelled without a shutdown error; receive loads that may be cancelled via try_recv",
+    )
+  }
+
+  pub async fn try_recv(mut self) -> Result<Result<Option<Arc<V>>, E>, RecvCancelled> {
     loop {
-      if let LoadState::Ready(ref result) = *self.0.borrow() {
-        break result.to_owned();
+      match &*self.0.borrow() {
+        LoadState::Ready(result) => return Ok(result.to_owned()),
+        LoadState::Cancelled => return Err(RecvCancelled),
+        LoadState::Pending => {}
       }
 
-      self.0.changed().await.unwrap();
+      self.0.changed().await.map_err(|_| RecvCancelled)?;
     }
   }
 }
@@ -58,21 +114,295 @@ where
   V: Send + Sync + Clone + 'static,
   E: Send + Sync + Clone + 'static,
 {
+  /// Panics if the task handler dropped the request without resolving it, such as when the handler itself panics; prefer [`OneshotReceiver::try_recv`] in production code
   pub async fn recv(self) -> Result<Option<Arc<V>>, E> {
     self.0.await.unwrap()
   }
+
+  pub async fn try_recv(self) -> Result<Result<Option<Arc<V>>, E>, RecvCancelled> {
+    self.0.await.map_err(|_| RecvCancelled)
+  }
+}
+
+/// The request was cancelled without resolving, such as by the task handler dropping it, by draining the loader without a [`TaskHandler::shutdown_error`], or by the startup timeout of its loader elapsing
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("load cancelled: request dropped without resolving")]
+pub struct RecvCancelled;
+
+/// The result of a load failing either within the task handler or from the request being cancelled
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LoadError<E> {
+  #[error("task handler error")]
+  HandlerError(#[source] E),
+  #[error(transparent)]
+  Cancelled(#[from] RecvCancelled),
+  #[error("load deadline elapsed")]
+  Deadline,
+}
+
+/// Prints the current load state without blocking
+impl<V, E> fmt::Display for WatchReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &*self.0.borrow() {
+      LoadState::Ready(Ok(_)) => f.write_str("LoadState::Ready(Ok(_))"),
+      LoadState::Ready(Err(_)) => f.write_str("LoadState::Ready(Err(_))"),
+      LoadState::Pending => f.write_str("LoadState::Pending"),
+      LoadState::Cancelled => f.write_str("LoadState::Cancelled"),
+    }
+  }
+}
+
+/// As a oneshot channel cannot be peeked, this is always displayed as pending
+impl<V, E> fmt::Display for OneshotReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("OneshotReceiver(pending)")
+  }
+}
+
+/// Shorthand for [`WatchReceiver::recv`]
+///
+/// ```rust
+/// let rx = loader.cached_load_by(key, &request_cache);
+/// let value = rx.await?;
+/// ```
+impl<V, E> IntoFuture for WatchReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  type Output = Result<Option<Arc<V>>, E>;
+  type IntoFuture = BoxFuture<'static, Self::Output>;
+
+  fn into_future(self) -> Self::IntoFuture {
+    self.recv().boxed()
+  }
+}
+
+/// Shorthand for [`OneshotReceiver::recv`]
+///
+/// ```rust
+/// let rx = loader.load_by(key);
+/// let value = rx.await?;
+/// ```
+impl<V, E> IntoFuture for OneshotReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  type Output = Result<Option<Arc<V>>, E>;
+  type IntoFuture = BoxFuture<'static, Self::Output>;
+
+  fn into_future(self) -> Self::IntoFuture {
+    self.recv().boxed()
+  }
+}
+
+/// A load in progress that can be awaited or polled synchronously
+///
+/// ```rust
+/// let progress = loader.load(key);
+///
+/// while !progress.is_done() {
+///   std::thread::sleep(Duration::from_millis(1));
+/// }
+///
+/// let value = progress.current().unwrap()?;
+/// ```
+///
+/// ```rust
+/// let value = loader.load(key).await?;
+/// ```
+pub struct LoadProgress<T: TaskHandler> {
+  rx: WatchReceiver<T::Value, T::Error>,
+  pending: Option<BoxFuture<'static, LoadResult<T::Value, T::Error>>>,
+}
+
+impl<T> LoadProgress<T>
+where
+  T: TaskHandler,
+{
+  pub(crate) fn new(rx: WatchReceiver<T::Value, T::Error>) -> Self {
+    LoadProgress { rx, pending: None }
+  }
+
+  /// Peek at the result without blocking; `None` while the load is pending
+  pub fn current(&self) -> Option<LoadResult<T::Value, T::Error>> {
+    self.rx.peek()
+  }
+
+  pub fn is_done(&self) -> bool {
+    matches!(
+      *self.rx.0.borrow(),
+      LoadState::Ready(_) | LoadState::Cancelled
+    )
+  }
+
+  /// A new receiver for the same load
+  pub fn subscribe(&self) -> WatchReceiver<T::Value, T::Error> {
+    self.rx.clone()
+  }
+}
+
+impl<T> Future for LoadProgress<T>
+where
+  T: TaskHandler,
+{
+  type Output = Result<Option<Arc<T::Value>>, T::Error>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let LoadProgress { rx, pending } = self.get_mut();
+
+    pending
+      .get_or_insert_with(|| rx.clone().recv().boxed())
+      .poll_unpin(cx)
+  }
+}
+
+/// A cloneable future broadcasting the result of a load to every clone
+///
+/// ```rust
+/// let user = loader.load(user_id).subscribe().shared();
+///
+/// let (a, b) = tokio::join!(user.clone(), user);
+/// ```
+pub struct SharedReceiver<V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
+  rx: Arc<watch::Receiver<LoadState<V, E>>>,
+  watcher: Option<Arc<()>>,
+  pending: Option<BoxFuture<'static, Result<Option<Arc<V>>, E>>>,
+}
+
+impl<V, E> SharedReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  /// A new receiver for the same load
+  pub fn subscribe(&self) -> WatchReceiver<V, E> {
+    WatchReceiver(self.rx.as_ref().clone(), self.watcher.clone())
+  }
+}
+
+impl<V, E> Clone for SharedReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn clone(&self) -> Self {
+    SharedReceiver {
+      rx: self.rx.clone(),
+      watcher: self.watcher.clone(),
+      pending: None,
+    }
+  }
+}
+
+impl<V, E> Future for SharedReceiver<V, E>
+where
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  type Output = Result<Option<Arc<V>>, E>;
+
+  // Each clone awaits changes on a receiver of its own so that dropping any one clone never affects the others
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let SharedReceiver { rx, pending, .. } = self.get_mut();
+
+    pending
+      .get_or_insert_with(|| WatchReceiver(rx.as_ref().clone(), None).recv().boxed())
+      .poll_unpin(cx)
+  }
+}
+
+/// The cache that created a watch request, from which its entry is removed upon resolving if caching was bypassed or the value loaded isn't to be cached as per [`TaskHandler::should_cache`], and upon the request being cancelled or dropped without resolving, so that subsequent loads of the key aren't handed a receiver that was already cancelled. Entries since replaced, such as by [`ContextCache::warm`], are left be
+pub struct Evict<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
+  key: K,
+  data: Weak<HashMap<K, watch::Receiver<LoadState<V, E>>>>,
+  // The channel of the entry to remove upon drop, or `None` once the entry is to be kept
+  entry: Option<watch::Receiver<LoadState<V, E>>>,
+  should_cache: fn(&K, &V) -> bool,
+}
+
+impl<K, V, E> Evict<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  // Keep the entry unless caching was bypassed or the value loaded isn't to be cached, removing it otherwise upon drop
+  fn evict(mut self, loaded: Option<&Arc<V>>, bypass_cache: bool) {
+    let uncacheable = loaded.is_some_and(|value| !(self.should_cache)(&self.key, value));
+
+    if !bypass_cache && !uncacheable {
+      self.entry = None;
+    }
+  }
+}
+
+impl<K, V, E> Drop for Evict<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn drop(&mut self) {
+    if let (Some(entry), Some(data)) = (self.entry.take(), self.data.upgrade()) {
+      let guard = data.guard();
+
+      if matches!(data.get(&self.key, &guard), Some(current) if current.same_channel(&entry)) {
+        data.remove(&self.key, &guard);
+      }
+    }
+  }
 }
 
+// The result each load resolves as
+pub(crate) type LoadResult<V, E> = Result<Option<Arc<V>>, E>;
+
+pub(crate) type HandlerRequest<T> =
+  Request<<T as TaskHandler>::Key, <T as TaskHandler>::Value, <T as TaskHandler>::Error>;
+
+// Invoked with each loaded value upon resolution
+pub(crate) type CacheCallback<K, V> = Arc<dyn Fn(&K, &Arc<V>) + Send + Sync>;
+
+// Produces the value of absent keys
+pub(crate) type DefaultFn<K, V> = Arc<dyn Fn(&K) -> Arc<V> + Send + Sync>;
+
+// Invoked with the key and result of a request ahead of sending the result, or with no result upon cancellation
+type OnResolve<K, V, E> = Box<dyn FnOnce(&K, Option<&LoadResult<V, E>>) + Send + Sync>;
+
 pub enum Request<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
   Watch {
     key: K,
     tx: watch::Sender<LoadState<V, E>>,
-    cache_cb: Option<Arc<dyn Fn(&K, &V) + Send + Sync>>,
+    cache_cb: Option<CacheCallback<K, V>>,
+    default_fn: Option<DefaultFn<K, V>>,
+    in_flight: Option<InFlight<K, V, E>>,
+    metadata: Option<Arc<dyn Any + Send + Sync>>,
+    evict: Option<Evict<K, V, E>>,
+    on_resolve: Option<OnResolve<K, V, E>>,
+    pending: Option<PendingLoad>,
+    #[cfg(debug_assertions)]
+    unresolved: Option<Arc<AtomicUsize>>,
   },
   Oneshot {
     key: K,
-    tx: oneshot::Sender<Result<Option<Arc<V>>, E>>,
-    cache_cb: Option<Arc<dyn Fn(&K, &V) + Send + Sync>>,
+    tx: oneshot::Sender<LoadResult<V, E>>,
+    cache_cb: Option<CacheCallback<K, V>>,
+    default_fn: Option<DefaultFn<K, V>>,
+    in_flight: Option<InFlight<K, V, E>>,
+    metadata: Option<Arc<dyn Any + Send + Sync>>,
+    on_resolve: Option<OnResolve<K, V, E>>,
+    pending: Option<PendingLoad>,
+    #[cfg(debug_assertions)]
+    unresolved: Option<Arc<AtomicUsize>>,
   },
 }
 
@@ -89,6 +419,13 @@ where
       key,
       tx,
       cache_cb: None,
+      default_fn: None,
+      in_flight: None,
+      metadata: None,
+      on_resolve: None,
+      pending: None,
+      #[cfg(debug_assertions)]
+      unresolved: None,
     };
 
     (request, rx.into())
@@ -101,33 +438,120 @@ where
       key,
       tx,
       cache_cb: None,
+      default_fn: None,
+      in_flight: None,
+      metadata: None,
+      evict: None,
+      on_resolve: None,
+      pending: None,
+      #[cfg(debug_assertions)]
+      unresolved: None,
     };
 
     (request, rx.into())
   }
 
-  pub(crate) fn key<'a>(&'a self) -> &'a K {
+  pub fn key(&self) -> &K {
     match self {
       Request::Watch { key, .. } => key,
       Request::Oneshot { key, .. } => key,
     }
   }
 
+  /// Metadata attached via [`crate::loader::DataLoader::load_with_metadata`], if of type `M`
+  pub fn metadata<M: 'static>(&self) -> Option<&M> {
+    let metadata = match self {
+      Request::Watch { metadata, .. } => metadata,
+      Request::Oneshot { metadata, .. } => metadata,
+    };
+
+    metadata.as_ref()?.downcast_ref()
+  }
+
+  pub(crate) fn set_metadata(&mut self, metadata: Arc<dyn Any + Send + Sync>) {
+    match self {
+      Request::Watch { metadata: m, .. } => *m = Some(metadata),
+      Request::Oneshot { metadata: m, .. } => *m = Some(metadata),
+    }
+  }
+
+  /// Whether every receiver of this request has been dropped
+  pub(crate) fn is_closed(&self) -> bool {
+    match self {
+      Request::Watch { tx, .. } => tx.is_closed(),
+      Request::Oneshot { tx, .. } => tx.is_closed(),
+    }
+  }
+
   pub(crate) fn resolve(self, value: Result<Option<Arc<V>>, E>) {
+    self.resolve_bypassing_cache(value, false)
+  }
+
+  /// Resolve as with [`Request::resolve`], though when `bypass_cache` the cache callback isn't invoked and the entry of a watch request is removed from its [`ContextCache`] upon resolving
+  pub(crate) fn resolve_bypassing_cache(
+    mut self,
+    value: Result<Option<Arc<V>>, E>,
+    bypass_cache: bool,
+  ) {
+    self.release();
+
     match self {
-      Request::Watch { key, tx, cache_cb } => {
-        if let (Ok(Some(value)), Some(cache_cb)) = (&value, cache_cb) {
+      Request::Watch {
+        key,
+        tx,
+        cache_cb,
+        default_fn,
+        in_flight,
+        evict,
+        on_resolve,
+        ..
+      } => {
+        if let (Ok(Some(value)), Some(cache_cb), false) = (&value, cache_cb, bypass_cache) {
           cache_cb(&key, value);
         }
 
+        if let Some(in_flight) = in_flight {
+          in_flight.broadcast(&value);
+        }
+
+        // Evicted ahead of sending such that loads woken by this result don't observe the entry, whereas receivers already handed out still receive it
+        if let Some(evict) = evict {
+          evict.evict(value.as_ref().ok().and_then(Option::as_ref), bypass_cache);
+        }
+
+        let value = with_default(&key, value, default_fn);
+
+        if let Some(on_resolve) = on_resolve {
+          on_resolve(&key, Some(&value));
+        }
+
         if !tx.is_closed() {
           tx.send(LoadState::Ready(value)).ok();
         }
       }
-      Request::Oneshot { key, tx, cache_cb } => {
-        if let (Ok(Some(value)), Some(cache_cb)) = (&value, cache_cb) {
+      Request::Oneshot {
+        key,
+        tx,
+        cache_cb,
+        default_fn,
+        in_flight,
+        on_resolve,
+        ..
+      } => {
+        if let (Ok(Some(value)), Some(cache_cb), false) = (&value, cache_cb, bypass_cache) {
           cache_cb(&key, value);
         }
+
+        if let Some(in_flight) = in_flight {
+          in_flight.broadcast(&value);
+        }
+
+        let value = with_default(&key, value, default_fn);
+
+        if let Some(on_resolve) = on_resolve {
+          on_resolve(&key, Some(&value));
+        }
+
         if !tx.is_closed() {
           tx.send(value).ok();
         }
@@ -135,21 +559,210 @@ where
     };
   }
 
-  pub(crate) fn set_cache_cb(&mut self, cache_cb: Arc<dyn Fn(&K, &V) + Send + Sync>) {
+  // Resolve as `shutdown_error` when given, otherwise cancel; oneshot receivers observe cancellation by the sender being dropped
+  pub(crate) fn cancel(mut self, shutdown_error: Option<E>) {
+    self.release();
+
+    match (self, shutdown_error) {
+      (req, Some(err)) => req.resolve(Err(err)),
+      (
+        Request::Watch {
+          key,
+          tx,
+          evict,
+          on_resolve,
+          ..
+        },
+        None,
+      ) => {
+        // Evicted ahead of sending as upon resolving
+        drop(evict);
+
+        if let Some(on_resolve) = on_resolve {
+          on_resolve(&key, None);
+        }
+
+        tx.send(LoadState::Cancelled).ok();
+      }
+      (
+        Request::Oneshot {
+          key, on_resolve, ..
+        },
+        None,
+      ) => {
+        if let Some(on_resolve) = on_resolve {
+          on_resolve(&key, None);
+        }
+      }
+    }
+  }
+
+  /// Count this request towards the requests of a batch yet to be resolved or cancelled, as checked upon dropping a [`crate::task::CompletionReceipt`] of the batch. A request moved from one batch to another no longer counts towards the former
+  #[cfg(debug_assertions)]
+  pub(crate) fn set_unresolved(&mut self, unresolved: Arc<AtomicUsize>) {
+    self.release();
+    unresolved.fetch_add(1, Ordering::Release);
+
+    match self {
+      Request::Watch { unresolved: u, .. } => *u = Some(unresolved),
+      Request::Oneshot { unresolved: u, .. } => *u = Some(unresolved),
+    }
+  }
+
+  /// Stop counting towards the unresolved requests of its batch; done upon resolving or cancelling, and by batches ahead of resolving their requests asynchronously
+  pub(crate) fn release(&mut self) {
+    #[cfg(debug_assertions)]
+    {
+      let unresolved = match self {
+        Request::Watch { unresolved, .. } => unresolved,
+        Request::Oneshot { unresolved, .. } => unresolved,
+      };
+
+      if let Some(unresolved) = unresolved.take() {
+        unresolved.fetch_sub(1, Ordering::Release);
+      }
+    }
+  }
+
+  /// Resolve as the value of `default_fn` in place of `Ok(None)`. Defaults are applied after any cache callback, and so are never cached
+  pub(crate) fn set_default_fn(&mut self, default_fn: DefaultFn<K, V>) {
+    match self {
+      Request::Watch { default_fn: f, .. } => *f = Some(default_fn),
+      Request::Oneshot { default_fn: f, .. } => *f = Some(default_fn),
+    }
+  }
+
+  /// Count this request towards the backpressure of the loader it was enqueued onto until dropped upon resolving
+  pub(crate) fn set_pending(&mut self, pending: PendingLoad) {
+    match self {
+      Request::Watch { pending: p, .. } => *p = Some(pending),
+      Request::Oneshot { pending: p, .. } => *p = Some(pending),
+    }
+  }
+
+  /// Broadcast the result of this request to requests of other batches for the same key
+  pub(crate) fn set_in_flight(&mut self, in_flight: InFlight<K, V, E>) {
+    match self {
+      Request::Watch { in_flight: f, .. } => *f = Some(in_flight),
+      Request::Oneshot { in_flight: f, .. } => *f = Some(in_flight),
+    }
+  }
+
+  /// Set a callback to be invoked with the result sent upon resolution, or with no result upon cancellation, chaining after any callback already set
+  pub(crate) fn set_on_resolve(&mut self, on_resolve: OnResolve<K, V, E>) {
+    let value = match self {
+      Request::Watch { on_resolve, .. } => on_resolve,
+      Request::Oneshot { on_resolve, .. } => on_resolve,
+    };
+
+    *value = match value.take() {
+      Some(prev_cb) => Some(Box::new(move |key, value| {
+        prev_cb(key, value);
+        on_resolve(key, value);
+      })),
+      None => Some(on_resolve),
+    };
+  }
+
+  /// Set a callback to be invoked with the loaded value upon resolution, chaining after any callback already set
+  pub(crate) fn set_cache_cb(&mut self, cache_cb: CacheCallback<K, V>) {
     let value = match self {
       Request::Watch { cache_cb, .. } => cache_cb,
       Request::Oneshot { cache_cb, .. } => cache_cb,
     };
 
-    *value = Some(cache_cb);
+    *value = match value.take() {
+      Some(prev_cb) => Some(Arc::new(move |key, value| {
+        prev_cb(key, value);
+        cache_cb(key, value);
+      })),
+      None => Some(cache_cb),
+    };
+  }
+}
+
+fn with_default<K, V, E>(
+  key: &K,
+  value: LoadResult<V, E>,
+  default_fn: Option<DefaultFn<K, V>>,
+) -> LoadResult<V, E> {
+  match (value, default_fn) {
+    (Ok(None), Some(default_fn)) => Ok(Some(default_fn(key))),
+    (value, _) => value,
   }
 }
 
+/// Whether a [`ContextCache`] retains entries for its lifetime or evicts them once no longer watched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefetchPolicy {
+  #[default]
+  Retain,
+  /// Evict resolved entries once every [`WatchReceiver`] handed out for the key has been dropped, checked every `check_interval`, such that the next load re-fetches the key. This bounds the growth of caches loading many unique keys. Pending loads and values inserted via [`ContextCache::warm`] are never evicted
+  DropWhenCold { check_interval: Duration },
+}
+
 pub struct ContextCache<T>
 where
   T: TaskHandler,
 {
-  data: HashMap<T::Key, watch::Receiver<LoadState<T::Value, T::Error>>>,
+  data: Arc<CacheData<T>>,
+  invalidations: Invalidations<T::Key>,
+  inserts: CacheInserts<T>,
+  watchers: Option<Arc<HashMap<T::Key, Arc<()>>>>,
+}
+
+type CacheData<T> = HashMap<
+  <T as TaskHandler>::Key,
+  watch::Receiver<LoadState<<T as TaskHandler>::Value, <T as TaskHandler>::Error>>,
+>;
+
+// The receiver of the load of a key, along with the request to load it should the key have been absent
+type CacheLookup<T> = (
+  WatchReceiver<<T as TaskHandler>::Value, <T as TaskHandler>::Error>,
+  Option<HandlerRequest<T>>,
+);
+
+type CacheInserts<T> =
+  HashMap<<T as TaskHandler>::Key, Arc<OnceCell<Arc<<T as TaskHandler>::Value>>>>;
+
+// Invalidations buffered per subscriber before lagging subscribers begin skipping keys
+const INVALIDATION_CAPACITY: usize = 1024;
+
+// The broadcast channel of ContextCache::subscribe_invalidations, created upon the first subscription as most caches, being request-scoped, are never subscribed to
+#[derive(Clone)]
+struct Invalidations<K: Key>(Arc<OnceLock<broadcast::Sender<K>>>);
+
+impl<K: Key> Default for Invalidations<K> {
+  fn default() -> Self {
+    Invalidations(Arc::new(OnceLock::new()))
+  }
+}
+
+impl<K: Key> Invalidations<K> {
+  fn subscribe(&self) -> broadcast::Receiver<K> {
+    self
+      .0
+      .get_or_init(|| broadcast::channel(INVALIDATION_CAPACITY).0)
+      .subscribe()
+  }
+
+  fn send(&self, key: &K) {
+    if let Some(invalidations) = self.0.get() {
+      invalidations.send(key.to_owned()).ok();
+    }
+  }
+}
+
+// Values warmed at once below which insertion isn't worth dispatching to rayon
+const PARALLEL_WARMUP_THRESHOLD: usize = 1024;
+
+impl<T> Default for ContextCache<T>
+where
+  T: TaskHandler,
+{
+  fn default() -> Self {
+    ContextCache::new()
+  }
 }
 
 impl<T> ContextCache<T>
@@ -158,30 +771,1071 @@ where
 {
   pub fn new() -> Self {
     ContextCache {
-      data: HashMap::new(),
+      data: Arc::new(HashMap::new()),
+      invalidations: Invalidations::default(),
+      inserts: HashMap::new(),
+      watchers: None,
     }
   }
 
-  pub(crate) fn get_or_create(
-    &self,
-    key: &T::Key,
-  ) -> (
-    WatchReceiver<T::Value, T::Error>,
-    Option<Request<T::Key, T::Value, T::Error>>,
-  ) {
+  /// A cache evicting entries as per `refetch_policy`. Eviction of [`RefetchPolicy::DropWhenCold`] runs as a task spawned onto the current tokio runtime until the cache is dropped, and so must be called from within a runtime
+  pub fn with_refetch_policy(refetch_policy: RefetchPolicy) -> Self {
+    let mut cache = ContextCache::new();
+
+    if let RefetchPolicy::DropWhenCold { check_interval } = refetch_policy {
+      let watchers = Arc::new(HashMap::new());
+
+      tokio::task::spawn(evict_cold_entries::<T>(
+        check_interval,
+        Arc::downgrade(&cache.data),
+        watchers.clone(),
+        cache.invalidations.clone(),
+      ));
+
+      cache.watchers = Some(watchers);
+    }
+
+    cache
+  }
+
+  pub(crate) fn get_or_create(&self, key: &T::Key) -> CacheLookup<T> {
     let guard = self.data.guard();
 
-    loop {
+    let (rx, req) = loop {
       if let Some(rx) = self.data.get(key, &guard) {
-        break (rx.clone().into(), None);
+        break (rx.clone(), None);
       }
 
       let (req, rx) = Request::new_watch(key.to_owned());
 
       match self.data.try_insert(key.clone(), rx.0, &guard) {
-        Ok(rx) => break (rx.to_owned().into(), Some(req)),
+        Ok(rx) => break (rx.to_owned(), Some(req)),
         Err(_) => continue,
       }
+    };
+
+    let req = req.map(|mut req| {
+      if let Request::Watch { evict, .. } = &mut req {
+        *evict = Some(Evict {
+          key: key.to_owned(),
+          data: Arc::downgrade(&self.data),
+          entry: Some(rx.clone()),
+          should_cache: T::should_cache,
+        });
+      }
+
+      req
+    });
+
+    let watcher = self.watchers.as_ref().map(|watchers| {
+      let guard = watchers.guard();
+
+      match watchers.try_insert(key.clone(), Arc::new(()), &guard) {
+        Ok(watcher) => watcher.to_owned(),
+        Err(err) => err.current.to_owned(),
+      }
+    });
+
+    (WatchReceiver(rx, watcher), req)
+  }
+
+  /// Whether the cache has an entry for `key`, be it pending or resolved, without creating a request
+  pub fn contains(&self, key: &T::Key) -> bool {
+    self.data.pin().contains_key(key)
+  }
+
+  /// Whether the entry for `key` has resolved, be it as a value, as not found or as an error
+  pub fn is_ready(&self, key: &T::Key) -> bool {
+    let guard = self.data.guard();
+
+    self
+      .data
+      .get(key, &guard)
+      .is_some_and(|rx| matches!(&*rx.borrow(), LoadState::Ready(_)))
+  }
+
+  /// The result cached for `key` if resolved, or `None` if pending or absent
+  pub fn peek(&self, key: &T::Key) -> Option<LoadResult<T::Value, T::Error>> {
+    let guard = self.data.guard();
+
+    self
+      .data
+      .get(key, &guard)
+      .and_then(|rx| match &*rx.borrow() {
+        LoadState::Ready(result) => Some(result.to_owned()),
+        LoadState::Pending | LoadState::Cancelled => None,
+      })
+  }
+
+  /// Insert a loaded value, replacing any entry for the key. Loads already awaiting the replaced entry resolve as they otherwise would
+  pub fn warm(&self, key: T::Key, value: Arc<T::Value>) {
+    let (_, rx) = watch::channel(LoadState::Ready(Ok(Some(value))));
+
+    self.data.pin().insert(key, rx);
+  }
+
+  // Insert loaded values as with warm, in parallel on `pool` or else the global rayon pool when there are many values
+  pub(crate) fn warm_all(
+    &self,
+    values: Vec<(T::Key, T::Value)>,
+    pool: Option<Arc<ThreadPool>>,
+  ) -> impl Future<Output = ()> + Send + 'static {
+    let data = self.data.clone();
+    let (tx, rx) = oneshot::channel();
+
+    let insert = move |(key, value): (T::Key, T::Value)| {
+      let (_, rx) = watch::channel(LoadState::Ready(Ok(Some(Arc::new(value)))));
+      data.pin().insert(key, rx);
+    };
+
+    if values.len() < PARALLEL_WARMUP_THRESHOLD {
+      values.into_iter().for_each(insert);
+      tx.send(()).ok();
+    } else {
+      spawn_on(pool.as_deref(), move || {
+        values.into_par_iter().for_each(insert);
+        tx.send(()).ok();
+      });
     }
+
+    async move {
+      rx.await.ok();
+    }
+  }
+
+  // Insert a loaded value unless the key is already loading or loaded, returning whether it was inserted
+  pub(crate) fn warm_if_absent(&self, key: T::Key, value: Arc<T::Value>) -> bool {
+    let (_, rx) = watch::channel(LoadState::Ready(Ok(Some(value))));
+
+    self.data.pin().try_insert(key, rx).is_ok()
+  }
+
+  // Create and warm the value of a key confirmed absent, calling `f` at most once across concurrent callers unless it errors
+  pub(crate) async fn get_or_insert_with<F, Fut>(
+    &self,
+    key: T::Key,
+    f: F,
+  ) -> Result<Arc<T::Value>, T::Error>
+  where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T::Value, T::Error>>,
+  {
+    // Inserted by an initialization since completed
+    if let Some(Ok(Some(value))) = self.peek(&key) {
+      return Ok(value);
+    }
+
+    let cell = {
+      let guard = self.inserts.guard();
+
+      match self
+        .inserts
+        .try_insert(key.clone(), Arc::new(OnceCell::new()), &guard)
+      {
+        Ok(cell) => cell.to_owned(),
+        Err(err) => err.current.to_owned(),
+      }
+    };
+
+    let value = cell
+      .get_or_try_init(|| async {
+        let value = Arc::new(f().await?);
+        self.warm(key.clone(), value.clone());
+        Ok(value)
+      })
+      .await
+      .map(Arc::clone);
+
+    // Once initialized the value is cached, and so the cell is only needed by loads racing initialization
+    self.remove_insert(&key, &cell);
+
+    value
+  }
+
+  fn remove_insert(&self, key: &T::Key, cell: &Arc<OnceCell<Arc<T::Value>>>) {
+    let guard = self.inserts.guard();
+
+    self.inserts.compute_if_present(
+      key,
+      |_, current| (!Arc::ptr_eq(current, cell)).then(|| current.to_owned()),
+      &guard,
+    );
+  }
+
+  /// Remove a key from the cache so that the next load re-fetches it, notifying subscribers of [`ContextCache::subscribe_invalidations`] if the key was cached
+  pub fn invalidate(&self, key: &T::Key) {
+    self.inserts.pin().remove(key);
+
+    if self.data.pin().remove(key).is_some() {
+      self.invalidations.send(key);
+    }
+  }
+
+  /// A stream of keys removed from the cache, whether by [`ContextCache::invalidate`] or by expiry such as that of [`NegativeCache`]. Delivery is best-effort: a subscriber lagging more than 1024 keys behind skips the oldest keys, and keys removed before subscribing aren't received
+  pub fn subscribe_invalidations(&self) -> impl Stream<Item = T::Key> {
+    futures_util::stream::unfold(self.invalidations.subscribe(), |mut rx| async move {
+      loop {
+        match rx.recv().await {
+          Ok(key) => break Some((key, rx)),
+          Err(broadcast::error::RecvError::Lagged(_)) => continue,
+          Err(broadcast::error::RecvError::Closed) => break None,
+        }
+      }
+    })
+  }
+
+  /// A point-in-time snapshot of all values loaded into the cache. Loads that are pending, resolved as not found or that errored are excluded, and the snapshot may be stale by the time it's read
+  pub fn load_all_cached(&self) -> std::collections::HashMap<T::Key, Arc<T::Value>> {
+    let guard = self.data.guard();
+
+    self
+      .data
+      .iter(&guard)
+      .filter_map(|(key, rx)| match &*rx.borrow() {
+        LoadState::Ready(Ok(Some(value))) => Some((key.to_owned(), value.to_owned())),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// A serializable copy of every resolved entry, including keys resolved as not found or as errors, for exporting the cache across process boundaries. Deserializing produces independent values, not live cache entries; re-import with [`ContextCache::warm`]
+  #[cfg(feature = "serde")]
+  pub fn export_snapshot(&self) -> std::collections::HashMap<T::Key, LoadResult<T::Value, T::Error>>
+  where
+    T::Key: serde::Serialize,
+    T::Value: serde::Serialize,
+    T::Error: serde::Serialize,
+  {
+    let guard = self.data.guard();
+
+    self
+      .data
+      .iter(&guard)
+      .filter_map(|(key, rx)| match &*rx.borrow() {
+        LoadState::Ready(result) => Some((key.to_owned(), result.to_owned())),
+        LoadState::Pending | LoadState::Cancelled => None,
+      })
+      .collect()
+  }
+
+  /// The number of values loaded into the cache, without cloning keys or values
+  pub fn load_all_cached_count(&self) -> usize {
+    let guard = self.data.guard();
+
+    self
+      .data
+      .values(&guard)
+      .filter(|rx| matches!(&*rx.borrow(), LoadState::Ready(Ok(Some(_)))))
+      .count()
+  }
+}
+
+// Evict resolved entries of which the cache holds the only watcher token, until the cache is dropped
+async fn evict_cold_entries<T: TaskHandler>(
+  check_interval: Duration,
+  data: Weak<CacheData<T>>,
+  watchers: Arc<HashMap<T::Key, Arc<()>>>,
+  invalidations: Invalidations<T::Key>,
+) {
+  let mut interval = tokio::time::interval(check_interval);
+  interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+  interval.tick().await;
+
+  loop {
+    interval.tick().await;
+
+    match data.upgrade() {
+      Some(data) => evict_cold::<T>(&data, &watchers, &invalidations),
+      None => break,
+    }
+  }
+}
+
+fn evict_cold<T: TaskHandler>(
+  data: &CacheData<T>,
+  watchers: &HashMap<T::Key, Arc<()>>,
+  invalidations: &Invalidations<T::Key>,
+) {
+  let watchers_guard = watchers.guard();
+  let guard = data.guard();
+
+  let cold_keys: Vec<T::Key> = watchers
+    .iter(&watchers_guard)
+    .filter(|(_, watcher)| Arc::strong_count(watcher).eq(&1))
+    .map(|(key, _)| key.to_owned())
+    .collect();
+
+  for key in cold_keys {
+    match data.get(&key, &guard) {
+      Some(rx) if matches!(&*rx.borrow(), LoadState::Pending) => continue,
+      Some(_) => {
+        data.remove(&key, &guard);
+        invalidations.send(&key);
+      }
+      None => {}
+    }
+
+    watchers.remove(&key, &watchers_guard);
+  }
+}
+
+#[cfg(feature = "global-cache")]
+static GLOBAL_CACHES: std::sync::OnceLock<
+  std::sync::Mutex<
+    std::collections::HashMap<std::any::TypeId, &'static (dyn std::any::Any + Send + Sync)>,
+  >,
+> = std::sync::OnceLock::new();
+
+#[cfg(feature = "global-cache")]
+impl<T> ContextCache<T>
+where
+  T: TaskHandler,
+{
+  /// A process-wide cache shared by the thread local loaders of every thread, deduplicating loads across threads at the cost of contention on a single cache and of values being retained for the lifetime of the program. Lookup locks a process-wide registry and so is a cold path; [`crate::loader::DataLoader::global_cached_load_by`] looks up the cache once per thread local loader
+  pub fn global() -> &'static ContextCache<T> {
+    let mut caches = GLOBAL_CACHES.get_or_init(Default::default).lock().unwrap();
+
+    let cache = *caches
+      .entry(std::any::TypeId::of::<T>())
+      .or_insert_with(|| Box::leak(Box::new(ContextCache::<T>::new())));
+
+    cache.downcast_ref::<ContextCache<T>>().unwrap()
+  }
+}
+
+impl<T> AsRef<ContextCache<T>> for ContextCache<T>
+where
+  T: TaskHandler,
+{
+  fn as_ref(&self) -> &ContextCache<T> {
+    self
+  }
+}
+
+/// A request cache from which loads by key are made, being any [`AsRef<ContextCache>`] or a [`PartitionedCache`] selecting the [`ContextCache`] of each key
+pub trait KeyedCache<T: TaskHandler> {
+  fn cache_for(&self, key: &T::Key) -> &ContextCache<T>;
+}
+
+impl<T, C> KeyedCache<T> for C
+where
+  T: TaskHandler,
+  C: AsRef<ContextCache<T>>,
+{
+  fn cache_for(&self, _key: &T::Key) -> &ContextCache<T> {
+    self.as_ref()
+  }
+}
+
+/// A [`ContextCache`] sharded by key hash across `N` independent caches, such that loads of keys in different shards never contend on the same map. For caches shared by many threads under very high load, such as a process-wide cache; a single [`ContextCache`] is otherwise preferable, as each shard evicts and broadcasts invalidations independently
+pub struct PartitionedCache<T, const N: usize = 16>
+where
+  T: TaskHandler,
+{
+  shards: [ContextCache<T>; N],
+  hasher: RandomState,
+}
+
+impl<T, const N: usize> Default for PartitionedCache<T, N>
+where
+  T: TaskHandler,
+{
+  fn default() -> Self {
+    PartitionedCache::new()
+  }
+}
+
+impl<T, const N: usize> PartitionedCache<T, N>
+where
+  T: TaskHandler,
+{
+  pub fn new() -> Self {
+    PartitionedCache::from_shards(std::array::from_fn(|_| ContextCache::new()))
+  }
+
+  /// Shards evicting entries as per `refetch_policy`; see [`ContextCache::with_refetch_policy`]
+  pub fn with_refetch_policy(refetch_policy: RefetchPolicy) -> Self {
+    PartitionedCache::from_shards(std::array::from_fn(|_| {
+      ContextCache::with_refetch_policy(refetch_policy)
+    }))
+  }
+
+  fn from_shards(shards: [ContextCache<T>; N]) -> Self {
+    assert!(N > 0, "PartitionedCache must have at least one shard");
+
+    PartitionedCache {
+      shards,
+      hasher: RandomState::new(),
+    }
+  }
+
+  /// The shard caching `key`
+  pub fn shard(&self, key: &T::Key) -> &ContextCache<T> {
+    let hash = self.hasher.hash_one(key);
+
+    &self.shards[(hash % N as u64) as usize]
+  }
+
+  /// Insert a loaded value into the shard of its key; see [`ContextCache::warm`]
+  pub fn warm(&self, key: T::Key, value: Arc<T::Value>) {
+    self.shard(&key).warm(key, value);
+  }
+
+  /// Remove a key from its shard; see [`ContextCache::invalidate`]
+  pub fn invalidate(&self, key: &T::Key) {
+    self.shard(key).invalidate(key);
+  }
+
+  /// The keys removed from every shard, interleaved in the order received; see [`ContextCache::subscribe_invalidations`]
+  pub fn subscribe_invalidations(&self) -> impl Stream<Item = T::Key> {
+    futures_util::stream::select_all(
+      self
+        .shards
+        .iter()
+        .map(|shard| Box::pin(shard.subscribe_invalidations())),
+    )
+  }
+
+  /// Every value loaded into any shard; see [`ContextCache::load_all_cached`]
+  pub fn load_all_cached(&self) -> std::collections::HashMap<T::Key, Arc<T::Value>> {
+    self
+      .shards
+      .iter()
+      .flat_map(|shard| shard.load_all_cached())
+      .collect()
+  }
+
+  /// The number of values loaded into every shard
+  pub fn load_all_cached_count(&self) -> usize {
+    self
+      .shards
+      .iter()
+      .map(ContextCache::load_all_cached_count)
+      .sum()
+  }
+}
+
+impl<T, const N: usize> KeyedCache<T> for PartitionedCache<T, N>
+where
+  T: TaskHandler,
+{
+  fn cache_for(&self, key: &T::Key) -> &ContextCache<T> {
+    self.shard(key)
+  }
+}
+
+/// A [`ContextCache`] that tracks keys confirmed to not exist so that they can be re-fetched once their TTL has elapsed, set via [`NegativeCache::with_ttl`]. Absence is confirmed upon resolution. Expiry is checked lazily upon access rather than swept: an expired key stays cached until next loaded, whereupon it's evicted, re-fetched and received by [`NegativeCache::subscribe_invalidations`]. Positive results are retained for the lifetime of the cache
+pub struct NegativeCache<T>
+where
+  T: TaskHandler,
+{
+  cache: ContextCache<T>,
+  negative: Arc<HashMap<T::Key, Instant>>,
+  negative_ttl: Duration,
+}
+
+impl<T> Default for NegativeCache<T>
+where
+  T: TaskHandler,
+{
+  fn default() -> Self {
+    NegativeCache::new()
+  }
+}
+
+impl<T> NegativeCache<T>
+where
+  T: TaskHandler,
+{
+  /// TTL of keys confirmed absent of a [`NegativeCache`] created via [`NegativeCache::new`]
+  pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+  pub fn new() -> Self {
+    NegativeCache::with_ttl(Self::DEFAULT_TTL)
+  }
+
+  pub fn with_ttl(negative_ttl: Duration) -> Self {
+    NegativeCache {
+      cache: ContextCache::new(),
+      negative: Arc::new(HashMap::new()),
+      negative_ttl,
+    }
+  }
+
+  /// A stream of keys confirmed absent whose negative TTL has expired, as with [`ContextCache::subscribe_invalidations`]. As expiry is lazy, keys are received upon being accessed after expiring rather than at the moment of expiry
+  pub fn subscribe_invalidations(&self) -> impl Stream<Item = T::Key> {
+    self.cache.subscribe_invalidations()
+  }
+
+  /// Whether a key has been confirmed to not exist within the negative TTL
+  pub fn is_confirmed_absent(&self, key: &T::Key) -> bool {
+    match self.negative.pin().get(key) {
+      Some(confirmed_at) => confirmed_at.elapsed() < self.negative_ttl,
+      None => false,
+    }
+  }
+
+  pub(crate) fn get_or_create(&self, key: &T::Key) -> CacheLookup<T> {
+    let guard = self.negative.guard();
+
+    if let Some(confirmed_at) = self.negative.get(key, &guard) {
+      if confirmed_at.elapsed() >= self.negative_ttl {
+        self.negative.remove(key, &guard);
+        self.cache.invalidate(key);
+      }
+    }
+
+    let (rx, req) = self.cache.get_or_create(key);
+
+    // Confirmed absent as of resolving, such that the negative TTL runs regardless of whether the key is read again
+    let req = req.map(|mut req| {
+      let negative = Arc::downgrade(&self.negative);
+
+      req.set_on_resolve(Box::new(move |key, value| {
+        if let (Some(Ok(None)), Some(negative)) = (value, negative.upgrade()) {
+          negative.pin().insert(key.clone(), Instant::now());
+        }
+      }));
+
+      req
+    });
+
+    (rx, req)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment};
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use deque_loader_derive::Loader;
+  use futures_util::StreamExt;
+  use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  static LOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<AbsentLoader>")]
+  pub struct AbsentLoader;
+
+  impl MockBackend for AbsentLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(_keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      LOAD_COUNT.fetch_add(1, Ordering::SeqCst);
+      Ok(HashMap::new())
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<EvenLoader>")]
+  pub struct EvenLoader;
+
+  impl MockBackend for EvenLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(
+        keys
+          .iter()
+          .filter(|key| *key % 2 == 0)
+          .map(|key| (*key, Arc::new(*key)))
+          .collect(),
+      )
+    }
+  }
+
+  async fn load(
+    cache: &NegativeCache<MockHandler<AbsentLoader>>,
+  ) -> Result<Option<Arc<i32>>, TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    <AbsentLoader as LocalLoader<DataStore>>::loader()
+      .with(|loader| loader.negative_cached_load_by(1, cache))
+      .await
+  }
+
+  #[tokio::test]
+  async fn it_reports_load_progress() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let progress = <AbsentLoader as LocalLoader<DataStore>>::loader().with(|loader| loader.load(2));
+
+    assert!(!progress.is_done());
+    assert!(progress.current().is_none());
+
+    let rx = progress.subscribe();
+
+    assert_eq!(progress.await?, None);
+    assert_eq!(rx.peek(), Some(Ok(None)));
+
+    Ok(())
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<PanickingLoader>")]
+  pub struct PanickingLoader;
+
+  impl MockBackend for PanickingLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(_keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      panic!("handler failure")
+    }
+  }
+
+  #[tokio::test]
+  async fn it_reports_cancelled_loads() {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let result = <PanickingLoader as LocalLoader<DataStore>>::loader()
+      .with(|loader| loader.load_result(1))
+      .await;
+
+    assert_eq!(result, Err(LoadError::Cancelled(RecvCancelled)));
+
+    let (req, rx) = Request::<i32, i32, TestError>::new_oneshot(1);
+    req.resolve(Err(TestError("unavailable")));
+
+    assert_eq!(rx.try_recv().await, Ok(Err(TestError("unavailable"))));
+  }
+
+  #[cfg(feature = "global-cache")]
+  static GLOBAL_LOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  #[cfg(feature = "global-cache")]
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<GlobalLoader>")]
+  pub struct GlobalLoader;
+
+  #[cfg(feature = "global-cache")]
+  #[async_trait::async_trait]
+  impl MockBackend for GlobalLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn before_load(_keys: &[i32]) {
+      tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      GLOBAL_LOAD_COUNT.fetch_add(keys.len(), Ordering::SeqCst);
+
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[cfg(feature = "global-cache")]
+  #[test]
+  fn it_shares_global_cache_across_threads() {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let handles: Vec<_> = (0..2)
+      .map(|_| {
+        std::thread::spawn(|| {
+          let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+          rt.block_on(async {
+            <GlobalLoader as LocalLoader<DataStore>>::loader()
+              .with(|loader| loader.global_cached_load_by(42))
+              .recv()
+              .await
+          })
+        })
+      })
+      .collect();
+
+    for handle in handles {
+      assert_eq!(handle.join().unwrap(), Ok(Some(Arc::new(42))));
+    }
+
+    assert_eq!(GLOBAL_LOAD_COUNT.load(Ordering::SeqCst), 1);
+    assert_eq!(
+      ContextCache::<MockHandler<GlobalLoader>>::global().load_all_cached_count(),
+      1
+    );
+  }
+
+  #[tokio::test]
+  async fn it_broadcasts_shared_results() -> Result<(), TestError> {
+    let (req, rx) = Request::<i32, i32, TestError>::new_watch(1);
+
+    let shared = rx.shared();
+    let dropped = shared.clone();
+    let handles: Vec<_> = (0..4).map(|_| tokio::task::spawn(shared.clone())).collect();
+
+    drop(dropped);
+
+    req.resolve(Ok(Some(Arc::new(7))));
+
+    for handle in handles {
+      assert_eq!(handle.await.unwrap()?, Some(Arc::new(7)));
+    }
+
+    assert_eq!(shared.subscribe().peek(), Some(Ok(Some(Arc::new(7)))));
+    assert_eq!(shared.await?, Some(Arc::new(7)));
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  #[should_panic(expected = "load cancelled without a shutdown error")]
+  async fn it_panics_upon_receiving_cancelled_loads() {
+    let (req, rx) = Request::<i32, i32, TestError>::new_watch(1);
+
+    req.cancel(None);
+
+    let _ = rx.recv().await;
+  }
+
+  #[tokio::test]
+  async fn it_warms_without_replacing_loads_in_flight() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<MockHandler<EvenLoader>> = ContextCache::new();
+
+    let rx = <EvenLoader as LocalLoader<DataStore>>::loader()
+      .with(|loader| loader.cached_load_by(2, &cache));
+
+    assert!(!cache.warm_if_absent(2, Arc::new(100)));
+    assert!(cache.warm_if_absent(8, Arc::new(8)));
+
+    assert_eq!(rx.recv().await?, Some(Arc::new(2)));
+    assert_eq!(cache.peek(&8), Some(Ok(Some(Arc::new(8)))));
+
+    Ok(())
+  }
+
+  #[cfg(feature = "serde")]
+  #[tokio::test]
+  async fn it_round_trips_exported_snapshots() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<MockHandler<EvenLoader>> = ContextCache::new();
+
+    let receivers: Vec<_> = <EvenLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+      vec![1, 2]
+        .into_iter()
+        .map(|key| loader.cached_load_by(key, &cache))
+        .collect()
+    });
+
+    for rx in receivers {
+      rx.recv().await?;
+    }
+
+    let json = serde_json::to_string(&cache.export_snapshot()).unwrap();
+    let snapshot: std::collections::HashMap<i32, Result<Option<Arc<i32>>, ()>> =
+      serde_json::from_str(&json).unwrap();
+
+    assert_eq!(
+      snapshot,
+      std::collections::HashMap::from([(1, Ok(None)), (2, Ok(Some(Arc::new(2))))])
+    );
+
+    let state: LoadState<i32, ()> = serde_json::from_str(
+      &serde_json::to_string(&LoadState::<i32, ()>::Ready(Ok(Some(Arc::new(7))))).unwrap(),
+    )
+    .unwrap();
+
+    assert!(matches!(state, LoadState::Ready(Ok(Some(value))) if *value == 7));
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn it_snapshots_cached_values() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<MockHandler<EvenLoader>> = ContextCache::new();
+
+    let receivers: Vec<_> = <EvenLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+      (0..6)
+        .map(|key| loader.cached_load_by(key, &cache))
+        .collect()
+    });
+
+    assert_eq!(cache.load_all_cached_count(), 0);
+
+    futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+    let mut cached: Vec<(i32, i32)> = cache
+      .load_all_cached()
+      .into_iter()
+      .map(|(key, value)| (key, *value))
+      .collect();
+
+    cached.sort_unstable();
+
+    assert_eq!(cached, vec![(0, 0), (2, 2), (4, 4)]);
+    assert_eq!(cache.load_all_cached_count(), 3);
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn it_broadcasts_invalidations() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<MockHandler<EvenLoader>> = ContextCache::new();
+
+    let first = cache.subscribe_invalidations();
+    let second = cache.subscribe_invalidations();
+
+    let receivers: Vec<_> = <EvenLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+      vec![
+        loader.cached_load_by(2, &cache),
+        loader.cached_load_by(3, &cache),
+      ]
+    });
+
+    futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+    cache.invalidate(&3);
+    cache.invalidate(&5);
+    cache.invalidate(&2);
+
+    drop(cache);
+
+    assert_eq!(first.collect::<Vec<i32>>().await, vec![3, 2]);
+    assert_eq!(second.collect::<Vec<i32>>().await, vec![3, 2]);
+
+    Ok(())
+  }
+
+  static UNCACHED_LOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(Loader)]
+  #[data_loader(handler = "UncachedLoader")]
+  pub struct UncachedLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for UncachedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn should_cache(_key: &i32, value: &i32) -> bool {
+      value.lt(&100)
+    }
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let keys = task.keys();
+          UNCACHED_LOAD_COUNT.fetch_add(keys.len(), Ordering::SeqCst);
+
+          let bypass_keys = keys.iter().filter(|key| *key % 2 == 1).cloned().collect();
+          let data = keys.into_iter().map(|key| (key, Arc::new(key))).collect();
+
+          task.resolve_with_cache_bypass(Ok(data), bypass_keys)
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_bypasses_caching_of_uncacheable_results() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<UncachedLoader> = ContextCache::new();
+
+    for _ in 0..2 {
+      let receivers: Vec<_> = <UncachedLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+        vec![2, 3, 100]
+          .into_iter()
+          .map(|key| loader.cached_load_by(key, &cache))
+          .collect()
+      });
+
+      let values =
+        futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+      assert_eq!(
+        values,
+        vec![Some(Arc::new(2)), Some(Arc::new(3)), Some(Arc::new(100))]
+      );
+    }
+
+    assert_eq!(cache.peek(&2), Some(Ok(Some(Arc::new(2)))));
+    assert_eq!(cache.peek(&3), None);
+    assert_eq!(cache.peek(&100), None);
+    assert_eq!(UNCACHED_LOAD_COUNT.load(Ordering::SeqCst), 5);
+
+    Ok(())
+  }
+
+  #[test]
+  fn it_inspects_entries_by_state() {
+    let cache: ContextCache<MockHandler<AbsentLoader>> = ContextCache::new();
+
+    assert!(!cache.contains(&1));
+    assert!(!cache.is_ready(&1));
+    assert_eq!(cache.peek(&1), None);
+
+    let requests: Vec<_> = (1..=3)
+      .filter_map(|key| cache.get_or_create(&key).1)
+      .collect();
+
+    for key in 1..=3 {
+      assert!(cache.contains(&key));
+      assert!(!cache.is_ready(&key));
+      assert_eq!(cache.peek(&key), None);
+    }
+
+    for (req, result) in requests.into_iter().zip(vec![
+      Ok(Some(Arc::new(10))),
+      Ok(None),
+      Err(TestError("unavailable")),
+    ]) {
+      req.resolve(result);
+    }
+
+    assert!((1..=3).all(|key| cache.contains(&key) && cache.is_ready(&key)));
+    assert_eq!(cache.peek(&1), Some(Ok(Some(Arc::new(10)))));
+    assert_eq!(cache.peek(&2), Some(Ok(None)));
+    assert_eq!(cache.peek(&3), Some(Err(TestError("unavailable"))));
+
+    cache.invalidate(&1);
+
+    assert!(!cache.contains(&1));
+    assert!(!cache.is_ready(&1));
+    assert_eq!(cache.peek(&1), None);
+  }
+
+  #[tokio::test]
+  async fn it_partitions_loads_across_shards() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: PartitionedCache<MockHandler<EvenLoader>, 4> = PartitionedCache::new();
+    let invalidations = cache.subscribe_invalidations();
+
+    let receivers: Vec<_> = <EvenLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+      (0..8)
+        .map(|key| loader.cached_load_by(key, &cache))
+        .collect()
+    });
+
+    futures_util::future::try_join_all(receivers.into_iter().map(|rx| rx.recv())).await?;
+
+    for key in (0..8).step_by(2) {
+      assert!(cache.shard(&key).load_all_cached().contains_key(&key));
+    }
+
+    assert_eq!(cache.load_all_cached_count(), 4);
+
+    cache.invalidate(&2);
+    cache.invalidate(&6);
+
+    assert_eq!(cache.load_all_cached_count(), 2);
+
+    drop(cache);
+
+    let mut invalidated = invalidations.collect::<Vec<i32>>().await;
+    invalidated.sort_unstable();
+
+    assert_eq!(invalidated, vec![2, 6]);
+
+    Ok(())
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_drops_cold_entries() -> Result<(), TestError> {
+    use crate::loader::{DataStore, LocalLoader};
+
+    let cache: ContextCache<MockHandler<EvenLoader>> =
+      ContextCache::with_refetch_policy(RefetchPolicy::DropWhenCold {
+        check_interval: Duration::from_secs(1),
+      });
+    let mut invalidations = Box::pin(cache.subscribe_invalidations());
+
+    let (cold, watched) = <EvenLoader as LocalLoader<DataStore>>::loader().with(|loader| {
+      (
+        loader.cached_load_by(2, &cache),
+        loader.cached_load_by(4, &cache),
+      )
+    });
+
+    assert_eq!(cold.recv().await?, Some(Arc::new(2)));
+    assert_eq!(watched.clone().recv().await?, Some(Arc::new(4)));
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    assert_eq!(invalidations.next().await, Some(2));
+    assert_eq!(
+      cache.load_all_cached().into_keys().collect::<Vec<_>>(),
+      vec![4]
+    );
+    assert_eq!(watched.clone().recv().await?, Some(Arc::new(4)));
+
+    drop(watched);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(invalidations.next().await, Some(4));
+    assert_eq!(cache.load_all_cached_count(), 0);
+
+    Ok(())
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_expires_negative_ttl_from_resolution() {
+    let cache: NegativeCache<MockHandler<AbsentLoader>> =
+      NegativeCache::with_ttl(Duration::from_secs(60));
+
+    let (_, req) = cache.get_or_create(&3);
+    req.unwrap().resolve(Ok(None));
+
+    assert!(cache.is_confirmed_absent(&3));
+
+    tokio::time::advance(Duration::from_secs(61)).await;
+
+    assert!(!cache.is_confirmed_absent(&3));
+    assert!(cache.get_or_create(&3).1.is_some());
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_refetches_after_negative_ttl() -> Result<(), TestError> {
+    let cache: NegativeCache<MockHandler<AbsentLoader>> =
+      NegativeCache::with_ttl(Duration::from_secs(60));
+    let mut invalidations = Box::pin(cache.subscribe_invalidations());
+
+    assert_eq!(load(&cache).await?, None);
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 1);
+
+    assert_eq!(load(&cache).await?, None);
+    assert!(cache.is_confirmed_absent(&1));
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 1);
+
+    tokio::time::advance(Duration::from_secs(30)).await;
+
+    assert_eq!(load(&cache).await?, None);
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 1);
+
+    tokio::time::advance(Duration::from_secs(31)).await;
+
+    assert!(!cache.is_confirmed_absent(&1));
+    assert_eq!(load(&cache).await?, None);
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 2);
+
+    assert_eq!(invalidations.next().await, Some(1));
+
+    Ok(())
   }
 }
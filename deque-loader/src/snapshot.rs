@@ -0,0 +1,109 @@
+//! Capture the keys of a batch so that it can be replayed elsewhere, such as against a staging environment
+//!
+//! ```rust
+//! match task.get_assignment::<Self>().await {
+//!   TaskAssignment::LoadBatch(task) => {
+//!     let snapshot = bincode::serialize(&task.serialize_snapshot())?;
+//!     // ...
+//!   }
+//!   TaskAssignment::NoAssignment(receipt) => receipt,
+//! }
+//!
+//! let snapshot: BatchSnapshot<UserId> = bincode::deserialize(&snapshot)?;
+//! let results = replay_snapshot::<DieselHandler<UserLoader>>(snapshot).await;
+//! ```
+
+use crate::{
+  loader::DataLoader,
+  task::{LoadBatch, Task, TaskHandler},
+  Key,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The unique keys of a [`LoadBatch`] along with the number of requests they were batched from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchSnapshot<K> {
+  pub keys: Vec<K>,
+  pub request_count: usize,
+}
+
+impl<K, V, E> Task<LoadBatch<K, V, E>>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  pub fn serialize_snapshot(&self) -> BatchSnapshot<K> {
+    BatchSnapshot {
+      keys: self.keys(),
+      request_count: self.0.requests.len(),
+    }
+  }
+}
+
+/// Re-submit the keys of a snapshot through a fresh [`DataLoader`], returning results in the order of [`BatchSnapshot::keys`]
+pub async fn replay_snapshot<T>(
+  snapshot: BatchSnapshot<T::Key>,
+) -> Vec<Result<Option<Arc<T::Value>>, T::Error>>
+where
+  T: TaskHandler,
+{
+  let loader: DataLoader<T> = DataLoader::default();
+
+  let receivers: Vec<_> = snapshot
+    .keys
+    .into_iter()
+    .map(|key| loader.load_by(key))
+    .collect();
+
+  futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::request::Request;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use std::collections::HashMap;
+
+  pub struct DoubleLoader;
+
+  impl MockBackend for DoubleLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(key * 2))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_replays_snapshot() {
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 2, 3]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let task = Task::from_requests(requests);
+    let snapshot = task.serialize_snapshot();
+    let _ = task.resolve(Err(TestError("unavailable")));
+
+    let data = bincode::serialize(&snapshot).unwrap();
+    let restored: BatchSnapshot<i32> = bincode::deserialize(&data).unwrap();
+
+    assert_eq!(restored, snapshot);
+    assert_eq!(restored.request_count, 4);
+
+    let mut keys = restored.keys.clone();
+    keys.sort_unstable();
+    assert_eq!(keys, vec![1, 2, 3]);
+
+    let results = replay_snapshot::<MockHandler<DoubleLoader>>(restored.clone()).await;
+
+    for (key, result) in restored.keys.into_iter().zip(results) {
+      assert_eq!(result, Ok(Some(Arc::new(key * 2))));
+    }
+  }
+}
@@ -0,0 +1,219 @@
+//! Live statistics of the batches dispatched by a [`crate::loader::DataLoader`], for inspecting batching behavior during development without enabling metrics
+//!
+//! ```rust
+//! UserLoader::loader().with(|loader| loader.enable_batch_stats());
+//!
+//! async fn loader_stats() -> String {
+//!   UserLoader::loader().with(|loader| loader.batch_stats().to_string())
+//! }
+//! ```
+use std::{
+  fmt,
+  sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Mutex,
+  },
+};
+use tokio::time::Instant;
+
+/// A snapshot of the batches dispatched by a loader since creation or since last reset via [`crate::loader::DataLoader::reset_batch_stats`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchStats {
+  pub batches_dispatched: u64,
+  /// Requests across every batch dispatched, including requests for the same key
+  pub total_requests_served: u64,
+  /// Requests resolved without a backend call of their own, being requests for keys already requested within the same batch
+  pub deduplicated_requests: u64,
+  /// The proportion of requests served that were deduplicated
+  pub deduplication_efficiency: f64,
+  pub avg_batch_size: f64,
+  pub last_batch_size: usize,
+  /// Not serialized, as instants are only meaningful within the process that measured them
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub last_batch_dispatch_at: Option<Instant>,
+}
+
+impl fmt::Display for BatchStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "{:<24}{:>12}",
+      "batches dispatched", self.batches_dispatched
+    )?;
+    writeln!(
+      f,
+      "{:<24}{:>12}",
+      "requests served", self.total_requests_served
+    )?;
+    writeln!(
+      f,
+      "{:<24}{:>12}",
+      "deduplicated requests", self.deduplicated_requests
+    )?;
+    writeln!(
+      f,
+      "{:<24}{:>12.2}",
+      "deduplication efficiency", self.deduplication_efficiency
+    )?;
+    writeln!(f, "{:<24}{:>12.2}", "avg batch size", self.avg_batch_size)?;
+    writeln!(f, "{:<24}{:>12}", "last batch size", self.last_batch_size)?;
+
+    match self.last_batch_dispatch_at {
+      Some(dispatched_at) => write!(
+        f,
+        "{:<24}{:>12}",
+        "last dispatched",
+        format!("{:?} ago", dispatched_at.elapsed())
+      ),
+      None => write!(f, "{:<24}{:>12}", "last dispatched", "never"),
+    }
+  }
+}
+
+#[derive(Default)]
+pub(crate) struct BatchCounters {
+  batches_dispatched: AtomicU64,
+  total_requests_served: AtomicU64,
+  deduplicated_requests: AtomicU64,
+  last_batch_size: AtomicUsize,
+  last_batch_dispatch_at: Mutex<Option<Instant>>,
+}
+
+impl BatchCounters {
+  pub(crate) fn record_dispatch(&self, batch_size: usize, deduplicated_requests: usize) {
+    self.batches_dispatched.fetch_add(1, Ordering::Relaxed);
+    self
+      .total_requests_served
+      .fetch_add(batch_size as u64, Ordering::Relaxed);
+    self
+      .deduplicated_requests
+      .fetch_add(deduplicated_requests as u64, Ordering::Relaxed);
+    self.last_batch_size.store(batch_size, Ordering::Relaxed);
+    *self.last_batch_dispatch_at.lock().unwrap() = Some(Instant::now());
+  }
+
+  pub(crate) fn snapshot(&self) -> BatchStats {
+    let batches_dispatched = self.batches_dispatched.load(Ordering::Relaxed);
+    let total_requests_served = self.total_requests_served.load(Ordering::Relaxed);
+    let deduplicated_requests = self.deduplicated_requests.load(Ordering::Relaxed);
+
+    let (avg_batch_size, deduplication_efficiency) = if batches_dispatched.eq(&0) {
+      (0.0, 0.0)
+    } else {
+      (
+        total_requests_served as f64 / batches_dispatched as f64,
+        deduplicated_requests as f64 / total_requests_served as f64,
+      )
+    };
+
+    BatchStats {
+      batches_dispatched,
+      total_requests_served,
+      deduplicated_requests,
+      deduplication_efficiency,
+      avg_batch_size,
+      last_batch_size: self.last_batch_size.load(Ordering::Relaxed),
+      last_batch_dispatch_at: *self.last_batch_dispatch_at.lock().unwrap(),
+    }
+  }
+
+  pub(crate) fn reset(&self) {
+    self.batches_dispatched.store(0, Ordering::Relaxed);
+    self.total_requests_served.store(0, Ordering::Relaxed);
+    self.deduplicated_requests.store(0, Ordering::Relaxed);
+    self.last_batch_size.store(0, Ordering::Relaxed);
+    self.last_batch_dispatch_at.lock().unwrap().take();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::loader::DataLoader;
+  use crate::testing::{MockBackend, MockHandler, TestError};
+  use std::{collections::HashMap, sync::Arc, time::Duration};
+
+  pub struct BatchingLoader;
+
+  impl MockBackend for BatchingLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(4);
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_counts_dispatched_batches() {
+    let loader: DataLoader<MockHandler<BatchingLoader>> = DataLoader::default();
+
+    loader.enable_batch_stats();
+
+    assert_eq!(loader.batch_stats().batches_dispatched, 0);
+    assert!(loader.batch_stats().to_string().ends_with("never"));
+
+    let receivers: Vec<_> = (0..6).map(|key| loader.load_by(key)).collect();
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let receivers: Vec<_> = vec![10, 10, 11]
+      .into_iter()
+      .map(|key| loader.load_by(key))
+      .collect();
+
+    for rx in receivers {
+      rx.recv().await.unwrap();
+    }
+
+    let stats = loader.batch_stats();
+
+    // The first six keys are split into batches of four and two
+    assert_eq!(stats.batches_dispatched, 3);
+    assert_eq!(stats.total_requests_served, 9);
+    assert_eq!(stats.deduplicated_requests, 1);
+    assert_eq!(stats.deduplication_efficiency, 1.0 / 9.0);
+    assert_eq!(stats.avg_batch_size, 3.0);
+    assert_eq!(stats.last_batch_size, 3);
+    assert!(stats.last_batch_dispatch_at.is_some());
+
+    loader.reset_batch_stats();
+
+    let stats = loader.batch_stats();
+
+    assert_eq!(stats.batches_dispatched, 0);
+    assert_eq!(stats.avg_batch_size, 0.0);
+    assert_eq!(stats.last_batch_dispatch_at, None);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn it_round_trips_batch_stats() {
+    let stats = super::BatchStats {
+      batches_dispatched: 2,
+      total_requests_served: 5,
+      deduplicated_requests: 1,
+      deduplication_efficiency: 0.2,
+      avg_batch_size: 2.5,
+      last_batch_size: 3,
+      last_batch_dispatch_at: Some(tokio::time::Instant::now()),
+    };
+
+    let json = serde_json::to_string(&stats).unwrap();
+    let restored: super::BatchStats = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(
+      restored,
+      super::BatchStats {
+        last_batch_dispatch_at: None,
+        ..stats
+      }
+    );
+  }
+}
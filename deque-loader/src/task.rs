@@ -1,42 +1,526 @@
-use crate::{key::Key, request::Request};
-use rayon::prelude::*;
+#[cfg(not(feature = "ordered"))]
+use crate::key::{KeySet, SMALL_BATCH_THRESHOLD};
+#[cfg(feature = "prometheus-metrics")]
+use crate::prometheus_metrics::{DataLoaderMetrics, Outcome};
+use crate::{
+  buckets::RequestBuckets,
+  dedup::RequestDeduplicator,
+  interceptor::Interceptors,
+  key::Key,
+  observer::{BatchObservation, BatchObserver},
+  request::{LoadResult, OneshotReceiver, Request},
+  stats::BatchCounters,
+};
+#[cfg(feature = "ordered")]
+use indexmap::{IndexMap, IndexSet};
+use rayon::{prelude::*, ThreadPool};
+#[cfg(feature = "prometheus-metrics")]
+use std::time::Instant;
 use std::{
-  collections::{HashMap, HashSet},
+  any::TypeId,
+  collections::{HashMap, HashSet, VecDeque},
+  future::Future,
+  hash::Hash,
   marker::PhantomData,
-  sync::Arc,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock,
+  },
+  time::Duration,
 };
 use swap_queue::Stealer;
+use tokio::{
+  sync::{mpsc, oneshot},
+  task::JoinHandle,
+};
+
+// The default constants and functions of [`TaskHandler`], shared by the handler traits implementing it such as [`crate::batch::BatchLoader`] so that defaults are declared once
+macro_rules! task_handler_defaults {
+  ($error:ty) => {
+    const CORES_PER_WORKER_GROUP: usize = 4;
+    const MAX_BATCH_SIZE: Option<usize> = None;
+    /// Upper bound on the estimated total size of unique keys within a batch, as measured by [`crate::task::TaskHandler::key_size_bytes`]
+    const MAX_BATCH_BYTES: Option<usize> = None;
+    /// Estimated size of a key for the purpose of [`crate::task::TaskHandler::MAX_BATCH_BYTES`]; override for variable length keys such as [`String`]
+    fn key_size_bytes(_key: &Self::Key) -> usize {
+      std::mem::size_of::<Self::Key>()
+    }
+    /// Upper bound on the total weight of unique keys within a batch, as measured by [`crate::task::TaskHandler::key_weight`]
+    const MAX_BATCH_WEIGHT: Option<usize> = None;
+    /// Relative cost of loading a key for the purpose of [`crate::task::TaskHandler::MAX_BATCH_WEIGHT`]
+    fn key_weight(_key: &Self::Key) -> usize {
+      1
+    }
+    /// The error with which requests still queued upon [`crate::loader::DataLoader::drain`] are resolved. When `None` such requests are instead [`crate::request::LoadState::Cancelled`], to be received by [`crate::request::WatchReceiver::try_recv`]
+    fn shutdown_error() -> Option<$error> {
+      None
+    }
+    /// Reject a key before it's loaded, such as one exceeding a maximum length or of an invalid format. Run in parallel via rayon by [`crate::task::Task::validate`], resolving requests of the key as the error and removing them from the batch
+    fn validate_key(_key: &Self::Key) -> Result<(), $error> {
+      Ok(())
+    }
+    /// Reject a batch before it's loaded, such as so as not to waste a connection on a batch violating invariants. Run by [`crate::task::Task::validate`] upon the keys remaining once [`crate::task::TaskHandler::validate_key`] has been run, resolving every request of the batch as the error
+    fn validate_batch(_keys: &[Self::Key]) -> Result<(), $error> {
+      Ok(())
+    }
+    /// Whether a loaded value is to be retained by the [`crate::request::ContextCache`] the load was made through. Values not to be cached, such as those being time-sensitive, are still resolved to loads awaiting them and are then removed such that subsequent loads re-fetch, as with [`crate::task::Task::resolve_with_cache_bypass`]
+    fn should_cache(_key: &Self::Key, _value: &Self::Value) -> bool {
+      true
+    }
+    /// Priority of loads made without specifying one, such as via [`crate::loader::DataLoader::load_by`]
+    const DEFAULT_PRIORITY: $crate::task::Priority = $crate::task::Priority::Normal;
+    /// Number of requests resolved per rayon job before yielding to other rayon jobs, so that large batches completing simultaneously don't monopolize the thread pool. Set to 0 to disable yielding
+    const RESOLVE_YIELD_INTERVAL: usize = 256;
+  };
+}
+
+pub(crate) use task_handler_defaults;
+
+/// Forward the constants and functions of [`TaskHandler`] other than `handle_task` to `$handler`, within the impl of a handler wrapping another handler or implementing a handler trait of its own. Forwarding of [`TaskHandler::MAX_BATCH_SIZE`] can be skipped so as to declare it otherwise
+#[doc(hidden)]
+#[macro_export]
+macro_rules! forward_task_handler {
+  ($handler:ty) => {
+    const MAX_BATCH_SIZE: Option<usize> = <$handler>::MAX_BATCH_SIZE;
+    $crate::forward_task_handler!($handler, except MAX_BATCH_SIZE);
+  };
+  ($handler:ty, except MAX_BATCH_SIZE) => {
+    const CORES_PER_WORKER_GROUP: usize = <$handler>::CORES_PER_WORKER_GROUP;
+    const MAX_BATCH_BYTES: Option<usize> = <$handler>::MAX_BATCH_BYTES;
+    const MAX_BATCH_WEIGHT: Option<usize> = <$handler>::MAX_BATCH_WEIGHT;
+    const DEFAULT_PRIORITY: $crate::task::Priority = <$handler>::DEFAULT_PRIORITY;
+    const RESOLVE_YIELD_INTERVAL: usize = <$handler>::RESOLVE_YIELD_INTERVAL;
+
+    fn key_size_bytes(key: &Self::Key) -> usize {
+      <$handler>::key_size_bytes(key)
+    }
+
+    fn key_weight(key: &Self::Key) -> usize {
+      <$handler>::key_weight(key)
+    }
+
+    fn shutdown_error() -> Option<Self::Error> {
+      <$handler>::shutdown_error()
+    }
+
+    fn validate_key(key: &Self::Key) -> Result<(), Self::Error> {
+      <$handler>::validate_key(key)
+    }
+
+    fn validate_batch(keys: &[Self::Key]) -> Result<(), Self::Error> {
+      <$handler>::validate_batch(keys)
+    }
+
+    fn should_cache(key: &Self::Key, value: &Self::Value) -> bool {
+      <$handler>::should_cache(key, value)
+    }
+  };
+}
+
+/// The bounds of [`TaskHandler::Error`] and the error types of other handler traits. With the `std-error` feature, errors must additionally implement [`std::error::Error`] so as to interoperate with `?` and error handling crates
+#[cfg(not(feature = "std-error"))]
+pub trait TaskError: Send + Sync + Clone + 'static {}
+
+#[cfg(not(feature = "std-error"))]
+impl<E> TaskError for E where E: Send + Sync + Clone + 'static {}
+
+/// The bounds of [`TaskHandler::Error`] and the error types of other handler traits. With the `std-error` feature, errors must additionally implement [`std::error::Error`] so as to interoperate with `?` and error handling crates
+#[cfg(feature = "std-error")]
+pub trait TaskError: std::error::Error + Send + Sync + Clone + 'static {}
+
+#[cfg(feature = "std-error")]
+impl<E> TaskError for E where E: std::error::Error + Send + Sync + Clone + 'static {}
 
 /// A type-state control flow for driving tasks from assignment to completion. As task assignment can be deferred until connection acquisition and likewise loads batched by key, this enables opportunistic batching when connection acquisition becomes a bottleneck and also enables connection yielding as a consequence of work cancellation
 #[async_trait::async_trait]
 pub trait TaskHandler: Sized + Send + Sync + 'static {
   type Key: Key;
   type Value: Send + Sync + Clone + 'static;
-  type Error: Send + Sync + Clone + 'static;
-  const CORES_PER_WORKER_GROUP: usize = 4;
-  const MAX_BATCH_SIZE: Option<usize> = None;
+  type Error: TaskError;
+  task_handler_defaults!(Self::Error);
+
   async fn handle_task(
     task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
   ) -> Task<CompletionReceipt>;
 }
+
+/// Assert invariants of the constants of a task handler, such that nonsensical configurations fail to compile rather than stall at runtime. Evaluated for each handler of [`crate::Loader`] by `const _: () = assert_valid_constants::<Handler>();`, and upon monomorphization of [`crate::loader::DataLoader`] otherwise
+#[doc(hidden)]
+pub const fn assert_valid_constants<T: TaskHandler>() {
+  assert!(
+    T::CORES_PER_WORKER_GROUP > 0,
+    "TaskHandler::CORES_PER_WORKER_GROUP must be greater than 0"
+  );
+  assert!(
+    !matches!(T::MAX_BATCH_SIZE, Some(0)),
+    "TaskHandler::MAX_BATCH_SIZE must be greater than 0 when set"
+  );
+  assert!(
+    !matches!(T::MAX_BATCH_BYTES, Some(0)),
+    "TaskHandler::MAX_BATCH_BYTES must be greater than 0 when set"
+  );
+  assert!(
+    !matches!(T::MAX_BATCH_WEIGHT, Some(0)),
+    "TaskHandler::MAX_BATCH_WEIGHT must be greater than 0 when set"
+  );
+}
+
+pub(crate) struct ValidConstants<T>(PhantomData<T>);
+
+impl<T> ValidConstants<T>
+where
+  T: TaskHandler,
+{
+  pub(crate) const ASSERTED: () = assert_valid_constants::<T>();
+}
+
 pub struct Task<T>(pub(crate) T);
+
+/// Loads are batched separately per priority, such that loads are only coalesced with loads of the same priority. Batches are assigned in order of priority: a task handler is spawned for every batch dispatched, and upon beginning assignment takes the batch of highest priority yet to be taken rather than the batch it was spawned for. Whenever handlers contend, such as for connections, batches of [`Priority::High`] are thereby assigned ahead of batches of [`Priority::Normal`] and then [`Priority::Low`] queued earlier. As each handler takes one batch every batch is assigned, though batches of lower priority wait for as long as batches of higher priority continue to be dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+  High,
+  Normal,
+  Low,
+}
+
+impl Priority {
+  const ORDER: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+  fn index(self) -> usize {
+    match self {
+      Priority::High => 0,
+      Priority::Normal => 1,
+      Priority::Low => 2,
+    }
+  }
+}
+
+// A batch queued onto a swap_queue::Worker along with the shadows of its requests, if tracked
+pub(crate) struct QueuedBatch<
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+> {
+  stealer: Stealer<Request<K, V, E>>,
+  queued_keys: Option<QueuedKeys<K>>,
+  queued_count: Option<QueuedCount>,
+}
+
+impl<K, V, E> QueuedBatch<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn len(&self) -> usize {
+    match (&self.stealer, &self.queued_count) {
+      (Stealer::Owner(batch), _) => batch.len(),
+      (Stealer::Taker(_), Some(queued_count)) => queued_count.len(),
+      (Stealer::Taker(_), None) => 0,
+    }
+  }
+
+  async fn take(self) -> Vec<Request<K, V, E>> {
+    let batch = self.stealer.take().await;
+
+    if let Some(queued_keys) = self.queued_keys {
+      queued_keys.taken();
+    }
+
+    batch
+  }
+}
+
+/// The batches of a [`crate::loader::DataLoader`] yet to be taken by a task handler, per [`Priority`]. Batches are pushed as they're dispatched, and each task handler spawned pops the batch of highest priority upon beginning assignment
+pub(crate) struct PriorityQueues<
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+>(Mutex<[VecDeque<QueuedBatch<K, V, E>>; 3]>);
+
+impl<K, V, E> Default for PriorityQueues<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn default() -> Self {
+    PriorityQueues(Mutex::new(Default::default()))
+  }
+}
+
+impl<K, V, E> PriorityQueues<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn push(&self, priority: Priority, batch: QueuedBatch<K, V, E>) {
+    self.0.lock().unwrap()[priority.index()].push_back(batch);
+  }
+
+  fn pop(&self) -> Option<(Priority, QueuedBatch<K, V, E>)> {
+    let mut queues = self.0.lock().unwrap();
+
+    Priority::ORDER
+      .iter()
+      .find_map(|&priority| Some((priority, queues[priority.index()].pop_front()?)))
+  }
+
+  // The number of requests of the batch to be popped next
+  fn next_len(&self) -> usize {
+    self
+      .0
+      .lock()
+      .unwrap()
+      .iter()
+      .find_map(VecDeque::front)
+      .map_or(0, QueuedBatch::len)
+  }
+}
+
 /// A handle for deferred task assignment via work-stealing. Task assignement is deferred until connection acquisition to allow for opportunistic batching to occur
 pub struct PendingAssignment<
   K: Key,
   V: Send + Sync + Clone + 'static,
   E: Send + Sync + Clone + 'static,
 > {
-  pub(crate) stealer: Stealer<Request<K, V, E>>,
+  pub(crate) stealer: DeferredStealer<K, V, E>,
   pub(crate) requests: Vec<Request<K, V, E>>,
+  pub(crate) interceptors: Interceptors<K, V, E>,
+  pub(crate) stats: Option<Arc<BatchCounters>>,
+  pub(crate) observer: Option<Arc<dyn BatchObserver<K>>>,
+  pub(crate) draining: Option<&'static AtomicBool>,
+  pub(crate) startup_timeout: Option<Duration>,
+  pub(crate) deduplicate_in_flight: bool,
+  pub(crate) pool: Option<Arc<ThreadPool>>,
+  #[cfg(feature = "prometheus-metrics")]
+  pub(crate) metrics: Option<Arc<DataLoaderMetrics>>,
+}
+
+/// The [`Stealer`] of a pending assignment, signalling upon being taken so that a handler not beginning assignment within the startup timeout of [`crate::loader::DataLoader::set_worker_startup_timeout`] can be detected. Batches queued by [`Priority`] are claimed from the [`PriorityQueues`] of their loader upon being taken, being whichever batch is then of the highest priority. Should it be dropped without being taken, a batch is nonetheless taken so that the queue isn't left stalled, and its requests cancelled
+pub(crate) struct DeferredStealer<
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+> {
+  batch: Option<QueuedBatch<K, V, E>>,
+  // The queues a batch is to be claimed from, and once claimed the priority it was queued at
+  queues: Option<Arc<PriorityQueues<K, V, E>>>,
+  priority: Option<Priority>,
+  started: Option<oneshot::Sender<()>>,
+}
+
+impl<K, V, E> DeferredStealer<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  // Queue the batch at `priority`, to be claimed by whichever handler of the same queues next takes a batch
+  fn prioritize(&mut self, priority: Priority, queues: Arc<PriorityQueues<K, V, E>>) {
+    if let Some(batch) = self.batch.take() {
+      queues.push(priority, batch);
+    }
+
+    self.queues = Some(queues);
+  }
+
+  fn batch_mut(&mut self) -> Option<&mut QueuedBatch<K, V, E>> {
+    self.batch.as_mut()
+  }
+
+  fn queued_len(&self) -> usize {
+    match (&self.batch, &self.queues, self.priority) {
+      (Some(batch), _, _) => batch.len(),
+      (None, Some(queues), None) => queues.next_len(),
+      _ => 0,
+    }
+  }
+
+  fn claim(&mut self) {
+    if let (None, Some(queues), None) = (&self.batch, &self.queues, self.priority) {
+      if let Some((priority, batch)) = queues.pop() {
+        self.batch = Some(batch);
+        self.priority = Some(priority);
+      }
+    }
+  }
+
+  fn on_take(&mut self, started: oneshot::Sender<()>) {
+    self.started = Some(started);
+  }
+
+  async fn take(mut self) -> Vec<Request<K, V, E>> {
+    self.take_in_place().await
+  }
+
+  // Take the batch, after which this stealer yields nothing further
+  async fn take_in_place(&mut self) -> Vec<Request<K, V, E>> {
+    if let Some(started) = self.started.take() {
+      started.send(()).ok();
+    }
+
+    self.claim();
+
+    match self.batch.take() {
+      Some(batch) => batch.take().await,
+      None => vec![],
+    }
+  }
+
+  // The priority and queues of the batch taken, at which batches split from it are queued
+  #[allow(clippy::type_complexity)]
+  fn requeue(&self) -> Option<(Priority, Arc<PriorityQueues<K, V, E>>)> {
+    self.priority.zip(self.queues.clone())
+  }
+}
+
+impl<K, V, E> From<Stealer<Request<K, V, E>>> for DeferredStealer<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn from(stealer: Stealer<Request<K, V, E>>) -> Self {
+    DeferredStealer {
+      batch: Some(QueuedBatch {
+        stealer,
+        queued_keys: None,
+        queued_count: None,
+      }),
+      queues: None,
+      priority: None,
+      started: None,
+    }
+  }
+}
+
+impl<K, V, E> Drop for DeferredStealer<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn drop(&mut self) {
+    self.claim();
+
+    if let Some(batch) = self.batch.take() {
+      match batch.stealer {
+        Stealer::Owner(requests) => drop(requests),
+        Stealer::Taker(_) => {
+          if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+              drop(batch.take().await);
+            });
+          }
+        }
+      }
+    }
+  }
+}
+
+/// A shadow of the keys pushed onto a [`swap_queue::Worker`] for a batch not yet taken by its [`Stealer`], as work-stealing queues can't be iterated by their owner. Keys pushed once the batch is taken are discarded
+#[derive(Clone)]
+pub(crate) struct QueuedKeys<K>(Arc<Mutex<Option<Vec<K>>>>);
+
+impl<K: Key> QueuedKeys<K> {
+  pub(crate) fn new(keys: Vec<K>) -> Self {
+    QueuedKeys(Arc::new(Mutex::new(Some(keys))))
+  }
+
+  pub(crate) fn push(&self, key: K) {
+    if let Some(keys) = self.0.lock().unwrap().as_mut() {
+      keys.push(key);
+    }
+  }
+
+  pub(crate) fn snapshot(&self) -> Vec<K> {
+    self.0.lock().unwrap().clone().unwrap_or_default()
+  }
+
+  fn taken(&self) {
+    self.0.lock().unwrap().take();
+  }
+}
+
+// The number of requests pushed onto a queue since it was last taken, such that the requests of a pending assignment can be counted without shadowing their keys
+#[derive(Clone)]
+pub(crate) struct QueuedCount(Arc<AtomicUsize>);
+
+impl QueuedCount {
+  pub(crate) fn new() -> Self {
+    QueuedCount(Arc::new(AtomicUsize::new(1)))
+  }
+
+  pub(crate) fn increment(&self) {
+    self.0.fetch_add(1, Ordering::Relaxed);
+  }
+
+  fn len(&self) -> usize {
+    self.0.load(Ordering::Relaxed)
+  }
 }
 
 /// A batch of load requests, unique by key, to be loaded and the result resolved
 pub struct LoadBatch<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
   pub(crate) requests: Vec<Request<K, V, E>>,
+  pub(crate) pool: Option<Arc<ThreadPool>>,
+  pub(crate) batch_id: u64,
+  pub(crate) yield_interval: usize,
+  pub(crate) interceptors: Interceptors<K, V, E>,
+  pub(crate) observation: Option<BatchObservation<K>>,
+  #[cfg(feature = "prometheus-metrics")]
+  pub(crate) metrics: Option<Arc<DataLoaderMetrics>>,
+  #[cfg(feature = "prometheus-metrics")]
+  pub(crate) assigned_at: Instant,
+  // Requests yet to be resolved or cancelled of this batch and of any batch it was split from or into
+  #[cfg(debug_assertions)]
+  pub(crate) unresolved: Arc<AtomicUsize>,
+}
+
+/// A [`LoadBatch`] zipped with per-key metadata the task handler has already fetched, such as access control lists or shard hints, for use in loading without a second lookup; see [`Task::zip_with_metadata`]
+pub struct LoadBatchWithMeta<
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+  M: Send + 'static,
+> {
+  batch: Task<LoadBatch<K, V, E>>,
+  keys: Vec<K>,
+  metadata: HashMap<K, M>,
 }
+
 /// An acknowledgement of task completion as to enforce a design contract that allows ownership of requests to be taken by the task handler.
-/// This is a workaround to [rust-lang/rust#59337](https://github.com/rust-lang/rust/issues/59337) that enables task assignment to occur within a [`tokio::task::spawn_blocking`] closure
-pub struct CompletionReceipt(PhantomData<fn() -> ()>);
+/// This is a workaround to [rust-lang/rust#59337](https://github.com/rust-lang/rust/issues/59337) that enables task assignment to occur within a [`tokio::task::spawn_blocking`] closure.
+/// With debug assertions, dropping the receipt of a batch panics should requests of the batch, or of batches split from the same batch, have been dropped without being resolved, such as when only some sub-batches of [`Task::split_by_shard`] are resolved. Dropping a batch without producing a receipt of it cancels its requests
+pub struct CompletionReceipt {
+  _marker: PhantomData<fn() -> ()>,
+  #[cfg(debug_assertions)]
+  unresolved: Option<Arc<AtomicUsize>>,
+}
+
+// Checked once the receipt is the last holder of the counter, as the batches and requests yet to be resolved each hold it, and so receipts of sub-batches resolved in sequence don't observe sub-batches not yet resolved
+#[cfg(debug_assertions)]
+impl Drop for CompletionReceipt {
+  fn drop(&mut self) {
+    if std::thread::panicking() {
+      return;
+    }
+
+    if let Some(unresolved) = self.unresolved.take() {
+      let count = unresolved.load(Ordering::Acquire);
+
+      if Arc::strong_count(&unresolved).eq(&1) && count.gt(&0) {
+        panic!(
+          "CompletionReceipt dropped with {} unresolved requests; every batch must be resolved before completion",
+          count
+        );
+      }
+    }
+  }
+}
 
 /// A conditional assignment of work as a [`LoadBatch`]
 pub enum TaskAssignment<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static>
@@ -56,97 +540,2287 @@ where
   #[must_use]
   pub(crate) fn new(stealer: Stealer<Request<K, V, E>>) -> Self {
     let requests = vec![];
-    Task(PendingAssignment { stealer, requests })
+    Task(PendingAssignment {
+      stealer: stealer.into(),
+      requests,
+      interceptors: vec![],
+      stats: None,
+      observer: None,
+      draining: None,
+      startup_timeout: None,
+      deduplicate_in_flight: false,
+      pool: None,
+      #[cfg(feature = "prometheus-metrics")]
+      metrics: None,
+    })
   }
 
-  // Work-steal all pending load tasks
-  pub async fn get_assignment<T>(self) -> TaskAssignment<K, V, E>
-  where
-    T: TaskHandler,
-    Self: Into<Task<PendingAssignment<T::Key, T::Value, T::Error>>>,
-  {
-    let PendingAssignment {
-      stealer,
-      mut requests,
-    } = self.0;
+  /// The number of requests that would be assigned were the queue taken now. This is advisory only: loads continue to be queued until the queue is taken, so the count can grow as soon as it's read, and a handler must still take its assignment via [`Task::get_assignment`] rather than exit early on a count of 0. Use it to tune batching, such as by waiting for more loads before acquiring a connection
+  ///
+  /// ```rust
+  /// if task.request_count() < 10 {
+  ///   tokio::time::sleep(Duration::from_millis(1)).await;
+  /// }
+  ///
+  /// let conn = get_connection().await?;
+  /// ```
+  pub fn request_count(&self) -> usize {
+    self.0.requests.len() + self.0.stealer.queued_len()
+  }
+
+  /// Move up to `n` requests into `dest` in the order they were queued, returning the number moved, for handlers filling batches in rounds. The first call work-steals the queue in its entirety, an O(1) swap after which further loads are batched separately, and requests not yet collected are retained in order and assigned as usual by [`Task::get_assignment`]. Each call is then O(n) plus the shifting of retained requests, and stops early once no requests remain. Requests collected can be dispatched as a task of their own via [`Task::from_collected`]
+  ///
+  /// ```rust
+  /// let mut collected = vec![];
+  ///
+  /// while task.collect_n(10, &mut collected).await.eq(&10) {
+  ///   tokio::time::sleep(Duration::from_millis(1)).await;
+  /// }
+  ///
+  /// match Task::from_collected(collected).get_assignment::<Self>().await {
+  ///   TaskAssignment::LoadBatch(batch) => { ... }
+  ///   TaskAssignment::NoAssignment(receipt) => receipt,
+  /// }
+  /// ```
+  pub async fn collect_n(&mut self, n: usize, dest: &mut Vec<Request<K, V, E>>) -> usize {
+    let batch = self.0.stealer.take_in_place().await;
 
-    let batch = stealer.take().await;
+    self.0.requests.extend(batch);
 
-    requests.extend(batch.into_iter());
+    let n = n.min(self.0.requests.len());
+    dest.extend(self.0.requests.drain(..n));
 
-    TaskAssignment::LoadBatch(Task::from_requests(requests))
+    n
   }
-}
 
-impl<K, V, E> Task<LoadBatch<K, V, E>>
-where
-  K: Key,
-  V: Send + Sync + Clone + 'static,
-  E: Send + Sync + Clone + 'static,
-{
-  pub(crate) fn from_requests(requests: Vec<Request<K, V, E>>) -> Self {
-    Task(LoadBatch { requests })
+  /// A pending assignment of requests collected by [`Task::collect_n`], to be assigned by [`Task::get_assignment`] as though work-stolen
+  #[must_use]
+  pub fn from_collected(requests: Vec<Request<K, V, E>>) -> Self {
+    Task::new(Stealer::Owner(requests))
   }
 
-  pub fn keys(&self) -> Vec<K> {
-    let keys: HashSet<K> =
-      HashSet::from_par_iter(self.0.requests.par_iter().map(|req| req.key().to_owned()));
-    keys.into_par_iter().collect()
+  /// Requests taken from the queue ahead of task assignment, to be loaded alongside the requests stolen
+  pub(crate) fn with_requests(mut self, requests: Vec<Request<K, V, E>>) -> Self {
+    self.0.requests = requests;
+    self
   }
 
-  #[must_use]
-  pub fn resolve(self, results: Result<HashMap<K, Arc<V>>, E>) -> Task<CompletionReceipt> {
-    let Task(LoadBatch { requests }) = self;
+  pub(crate) fn with_queued_keys(mut self, queued_keys: QueuedKeys<K>) -> Self {
+    if let Some(batch) = self.0.stealer.batch_mut() {
+      batch.queued_keys = Some(queued_keys);
+    }
 
-    rayon::spawn(move || {
-      match results {
-        Ok(values) => {
-          requests.into_par_iter().for_each(|req| {
-            let value = values.get(req.key()).cloned();
-            req.resolve(Ok(value));
-          });
-        }
+    self
+  }
 
-        Err(e) => {
-          requests
-            .into_par_iter()
-            .for_each(|req| req.resolve(Err(e.clone())));
-        }
-      };
-    });
+  pub(crate) fn with_queued_count(mut self, queued_count: QueuedCount) -> Self {
+    if let Some(batch) = self.0.stealer.batch_mut() {
+      batch.queued_count = Some(queued_count);
+    }
 
-    Task::<CompletionReceipt>::completion_receipt()
+    self
   }
 
-  #[must_use]
-  pub(crate) fn apply_partial_results(
-    self,
-    results: HashMap<K, Arc<V>>,
-  ) -> TaskAssignment<K, V, E> {
-    let Task(LoadBatch { requests }) = self;
+  /// Queue the batch at `priority`, such that the task handler of this task takes whichever batch of `queues` is of the highest priority rather than this batch
+  pub(crate) fn with_priority(
+    mut self,
+    priority: Priority,
+    queues: Arc<PriorityQueues<K, V, E>>,
+  ) -> Self {
+    self.0.stealer.prioritize(priority, queues);
+    self
+  }
 
-    let requests: Vec<Request<K, V, E>> = requests
-      .into_par_iter()
-      .filter_map(|req| {
-        if let Some(value) = results.get(req.key()).cloned() {
-          req.resolve(Ok(Some(value)));
-          None
-        } else {
-          Some(req)
-        }
-      })
-      .collect();
+  pub(crate) fn with_interceptors(mut self, interceptors: Interceptors<K, V, E>) -> Self {
+    self.0.interceptors = interceptors;
+    self
+  }
 
-    if requests.len().gt(&0) {
-      TaskAssignment::LoadBatch(Task::from_requests(requests))
-    } else {
-      TaskAssignment::NoAssignment(Task::completion_receipt())
-    }
+  pub(crate) fn with_stats(mut self, stats: Arc<BatchCounters>) -> Self {
+    self.0.stats = Some(stats);
+    self
   }
-}
 
-impl Task<CompletionReceipt> {
-  pub(crate) fn completion_receipt() -> Self {
-    Task(CompletionReceipt(PhantomData))
+  pub(crate) fn with_observer(mut self, observer: Option<Arc<dyn BatchObserver<K>>>) -> Self {
+    self.0.observer = observer;
+    self
+  }
+
+  pub(crate) fn with_draining(mut self, draining: &'static AtomicBool) -> Self {
+    self.0.draining = Some(draining);
+    self
+  }
+
+  pub(crate) fn with_startup_timeout(mut self, startup_timeout: Option<Duration>) -> Self {
+    self.0.startup_timeout = startup_timeout;
+    self
+  }
+
+  pub(crate) fn with_in_flight_deduplication(mut self, deduplicate_in_flight: bool) -> Self {
+    self.0.deduplicate_in_flight = deduplicate_in_flight;
+    self
+  }
+
+  pub(crate) fn with_thread_pool(mut self, pool: Option<Arc<ThreadPool>>) -> Self {
+    self.0.pool = pool;
+    self
+  }
+
+  #[cfg(feature = "prometheus-metrics")]
+  pub(crate) fn with_metrics(mut self, metrics: Option<Arc<DataLoaderMetrics>>) -> Self {
+    self.0.metrics = metrics;
+    self
+  }
+
+  // Work-steal all pending load tasks, splitting them into `n` pending assignments of roughly equal numbers of unique keys
+  pub(crate) async fn split_into(self, n: usize) -> Vec<Task<PendingAssignment<K, V, E>>> {
+    let PendingAssignment {
+      stealer,
+      mut requests,
+      interceptors,
+      stats,
+      observer,
+      draining,
+      startup_timeout,
+      deduplicate_in_flight,
+      pool,
+      #[cfg(feature = "prometheus-metrics")]
+      metrics,
+    } = self.0;
+
+    requests.extend(stealer.take().await);
+
+    let n = n.max(1);
+    let unique_keys = requests
+      .iter()
+      .map(Request::key)
+      .collect::<HashSet<_>>()
+      .len();
+    let max_batch_size = unique_keys.div_ceil(n).max(1);
+
+    RequestBuckets::new(requests)
+      .split_by_size(max_batch_size)
+      .into_iter()
+      .filter(|bucket| !bucket.is_empty())
+      .map(|bucket| {
+        Task(PendingAssignment {
+          stealer: Stealer::Owner(bucket).into(),
+          requests: vec![],
+          interceptors: interceptors.clone(),
+          stats: stats.clone(),
+          observer: observer.clone(),
+          draining,
+          startup_timeout,
+          deduplicate_in_flight,
+          pool: pool.clone(),
+          #[cfg(feature = "prometheus-metrics")]
+          metrics: metrics.clone(),
+        })
+      })
+      .collect()
+  }
+
+  // Work-steal all pending load tasks, splitting off batches in excess of [`TaskHandler::MAX_BATCH_SIZE`], [`TaskHandler::MAX_BATCH_BYTES`] or [`TaskHandler::MAX_BATCH_WEIGHT`] to be handled separately
+  pub async fn get_assignment<T>(self) -> TaskAssignment<K, V, E>
+  where
+    T: TaskHandler<Key = K, Value = V, Error = E>,
+  {
+    match self.get_assignments::<T>(1).await.pop() {
+      Some(task) => TaskAssignment::LoadBatch(task),
+      None => TaskAssignment::NoAssignment(Task::completion_receipt()),
+    }
+  }
+
+  // As with [`Task::get_assignment`], but retaining up to `depth` batches to be loaded in sequence by this task
+  pub(crate) async fn get_assignments<T>(self, depth: usize) -> Vec<Task<LoadBatch<K, V, E>>>
+  where
+    T: TaskHandler<Key = K, Value = V, Error = E>,
+  {
+    let () = ValidConstants::<T>::ASSERTED;
+
+    let PendingAssignment {
+      mut stealer,
+      mut requests,
+      interceptors,
+      stats,
+      observer,
+      draining,
+      startup_timeout,
+      deduplicate_in_flight,
+      pool,
+      #[cfg(feature = "prometheus-metrics")]
+      metrics,
+    } = self.0;
+
+    requests.extend(stealer.take_in_place().await);
+
+    // Batches split off are queued at the priority of the batch taken
+    let requeue = stealer.requeue();
+    drop(stealer);
+
+    if draining
+      .unwrap_or_else(draining_flag::<T>)
+      .load(Ordering::Acquire)
+    {
+      log::debug!(
+        "{} draining {} queued requests",
+        tynm::type_name::<T>(),
+        requests.len()
+      );
+
+      requests
+        .into_iter()
+        .for_each(|req| req.cancel(T::shutdown_error()));
+
+      return vec![];
+    }
+
+    if deduplicate_in_flight {
+      requests = RequestDeduplicator::<T>::global().dedup(requests);
+    }
+
+    let mut buckets = RequestBuckets::new(requests);
+
+    if let Some(max_batch_size) = T::MAX_BATCH_SIZE {
+      buckets = buckets.split_by_size(max_batch_size);
+    }
+
+    if let Some(max_batch_bytes) = T::MAX_BATCH_BYTES {
+      buckets = buckets.split_by_bytes(max_batch_bytes, T::key_size_bytes);
+    }
+
+    if let Some(max_batch_weight) = T::MAX_BATCH_WEIGHT {
+      buckets = buckets.split_by_weight(max_batch_weight, |key| {
+        let weight = T::key_weight(key);
+
+        // Weightless keys never count towards the budget, and so batches of them are never split
+        debug_assert!(
+          weight > 0,
+          "TaskHandler::key_weight must be greater than 0 when MAX_BATCH_WEIGHT is set"
+        );
+
+        weight
+      });
+    }
+
+    let mut buckets = buckets.into_iter().filter(|bucket| !bucket.is_empty());
+
+    #[cfg(feature = "tracing")]
+    let traced = tracing::enabled!(tracing::Level::TRACE);
+    #[cfg(not(feature = "tracing"))]
+    let traced = false;
+
+    let assignments: Vec<Task<LoadBatch<K, V, E>>> = buckets
+      .by_ref()
+      .take(depth.max(1))
+      .map(|requests| {
+        #[cfg(feature = "prometheus-metrics")]
+        if let Some(metrics) = &metrics {
+          metrics.observe_batch_size(requests.len());
+        }
+
+        let mut task = Task::from_requests(requests)
+          .with_pool(pool.clone())
+          .with_yield_interval(T::RESOLVE_YIELD_INTERVAL)
+          .with_interceptors(interceptors.clone());
+
+        if let Some(observer) = &observer {
+          task.0.observation = Some(BatchObservation::dispatched(
+            observer.clone(),
+            task.keys(),
+            task.batch_id(),
+          ));
+        }
+
+        // Counting duplicates hashes every key of the batch, and so is skipped unless reported
+        let deduplicated_requests =
+          (stats.is_some() || traced).then(|| task.deduplicated_request_count());
+
+        if let (Some(stats), Some(deduplicated_requests)) = (&stats, deduplicated_requests) {
+          stats.record_dispatch(task.0.requests.len(), deduplicated_requests);
+        }
+
+        #[cfg(feature = "tracing")]
+        if let (true, Some(deduplicated_requests)) = (traced, deduplicated_requests) {
+          tracing::trace!(
+            batch_id = task.batch_id(),
+            requests = task.0.requests.len(),
+            deduplicated_requests,
+            deduplication_efficiency = deduplicated_requests as f64 / task.0.requests.len() as f64,
+            "batch assigned"
+          );
+        }
+
+        #[cfg(feature = "prometheus-metrics")]
+        let task = task.with_metrics(metrics.clone());
+
+        task
+      })
+      .collect();
+
+    for bucket in buckets {
+      let mut stealer = DeferredStealer::from(Stealer::Owner(bucket));
+
+      if let Some((priority, queues)) = &requeue {
+        stealer.prioritize(*priority, queues.clone());
+      }
+
+      let task = Task(PendingAssignment {
+        stealer,
+        requests: vec![],
+        interceptors: interceptors.clone(),
+        stats: stats.clone(),
+        observer: observer.clone(),
+        draining,
+        startup_timeout,
+        deduplicate_in_flight,
+        pool: pool.clone(),
+        #[cfg(feature = "prometheus-metrics")]
+        metrics: metrics.clone(),
+      });
+
+      let handle_task = async move {
+        handle_with_startup_timeout::<T>(task).await;
+      };
+
+      #[cfg(feature = "tracing")]
+      let handle_task = tracing::Instrument::in_current_span(handle_task);
+
+      tokio::task::spawn(handle_task);
+    }
+
+    if !interceptors.is_empty() {
+      for task in assignments.iter() {
+        let keys = task.keys();
+
+        for interceptor in interceptors.iter() {
+          interceptor.before_dispatch(&keys).await;
+        }
+      }
+    }
+
+    assignments
+  }
+}
+
+/// Run the task handler of `task`, abandoning it should it not begin assignment within the startup timeout of its loader, such as when connection acquisition hangs, in which case the queued requests are cancelled rather than left waiting indefinitely
+pub(crate) async fn handle_with_startup_timeout<T: TaskHandler>(
+  mut task: Task<PendingAssignment<T::Key, T::Value, T::Error>>,
+) {
+  let startup_timeout = match task.0.startup_timeout {
+    Some(startup_timeout) => startup_timeout,
+    None => {
+      T::handle_task(task).await;
+      return;
+    }
+  };
+
+  let (tx, rx) = oneshot::channel();
+  task.0.stealer.on_take(tx);
+
+  let handle_task = T::handle_task(task);
+  tokio::pin!(handle_task);
+
+  tokio::select! {
+    _ = &mut handle_task => return,
+    started = tokio::time::timeout(startup_timeout, rx) => {
+      if started.is_err() {
+        log::error!(
+          "{} task handler failed to begin assignment within {:?}; cancelling queued requests",
+          tynm::type_name::<T>(),
+          startup_timeout
+        );
+
+        return;
+      }
+    }
+  }
+
+  handle_task.await;
+}
+
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(1);
+
+static DRAINING: OnceLock<Mutex<HashMap<TypeId, &'static AtomicBool>>> = OnceLock::new();
+
+// The draining flag of a handler, shared by every thread local loader of the handler. Lookup locks a process-wide registry, and so loaders look up the flag once and attach it to their tasks
+pub(crate) fn draining_flag<T: TaskHandler>() -> &'static AtomicBool {
+  DRAINING
+    .get_or_init(Default::default)
+    .lock()
+    .unwrap()
+    .entry(TypeId::of::<T>())
+    .or_insert_with(|| Box::leak(Box::new(AtomicBool::new(false))))
+}
+
+// Draining is permanent and applies to every thread local loader of the handler
+pub(crate) fn drain<T: TaskHandler>() {
+  draining_flag::<T>().store(true, Ordering::Release);
+}
+
+pub(crate) fn spawn_on<F>(pool: Option<&ThreadPool>, op: F)
+where
+  F: FnOnce() + Send + 'static,
+{
+  // Resolve within the span of the task handler, which requires the `std` feature of tracing
+  #[cfg(feature = "tracing")]
+  let op = {
+    let span = tracing::Span::current();
+    move || span.in_scope(op)
+  };
+
+  match pool {
+    Some(pool) => pool.spawn(op),
+    None => rayon::spawn(op),
+  }
+}
+
+// Resolve requests sequentially, those of keys within `order` in that order followed by the remainder in the order they were requested
+fn resolve_in_order<K, V, E>(
+  requests: Vec<Request<K, V, E>>,
+  order: &[K],
+  values: &HashMap<K, Arc<V>>,
+  bypass_keys: &HashSet<K>,
+) where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  let mut pending: HashMap<K, Vec<usize>> = HashMap::new();
+
+  for (i, req) in requests.iter().enumerate() {
+    pending.entry(req.key().to_owned()).or_default().push(i);
+  }
+
+  let mut requests: Vec<Option<Request<K, V, E>>> = requests.into_iter().map(Some).collect();
+
+  let resolve = |req: Request<K, V, E>| {
+    let value = values.get(req.key()).cloned();
+    let bypass_cache = bypass_keys.contains(req.key());
+    req.resolve_bypassing_cache(Ok(value), bypass_cache);
+  };
+
+  for key in order.iter() {
+    for i in pending.remove(key).unwrap_or_default() {
+      if let Some(req) = requests[i].take() {
+        resolve(req);
+      }
+    }
+  }
+
+  requests.into_iter().flatten().for_each(resolve);
+}
+
+// Resolve requests in parallel, yielding to other rayon jobs after every `yield_interval` requests resolved by a job
+fn resolve_each<K, V, E, F>(requests: Vec<Request<K, V, E>>, yield_interval: usize, op: F)
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+  F: Fn(Request<K, V, E>) + Send + Sync,
+{
+  if yield_interval.eq(&0) {
+    requests.into_par_iter().for_each(op);
+  } else {
+    requests
+      .into_par_iter()
+      .chunks(yield_interval)
+      .for_each(|chunk| {
+        chunk.into_iter().for_each(&op);
+        rayon::yield_now();
+      });
+  }
+}
+
+impl<K, V, E> Task<LoadBatch<K, V, E>>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  pub(crate) fn from_requests(requests: Vec<Request<K, V, E>>) -> Self {
+    let task = Task(LoadBatch {
+      requests,
+      pool: None,
+      batch_id: NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed),
+      yield_interval: 0,
+      interceptors: vec![],
+      observation: None,
+      #[cfg(feature = "prometheus-metrics")]
+      metrics: None,
+      #[cfg(feature = "prometheus-metrics")]
+      assigned_at: Instant::now(),
+      #[cfg(debug_assertions)]
+      unresolved: Arc::default(),
+    });
+
+    #[cfg(debug_assertions)]
+    let task = task.tracking_unresolved(Arc::default());
+
+    task
+  }
+
+  // Count each request towards `unresolved`, being the counter of this batch or of the batch it was split from
+  #[cfg(debug_assertions)]
+  fn tracking_unresolved(mut self, unresolved: Arc<AtomicUsize>) -> Self {
+    for req in self.0.requests.iter_mut() {
+      req.set_unresolved(unresolved.clone());
+    }
+
+    self.0.unresolved = unresolved;
+    self
+  }
+
+  // A receipt of this batch, checking upon drop that its requests were resolved
+  fn receipt(&self) -> Task<CompletionReceipt> {
+    Task(CompletionReceipt {
+      _marker: PhantomData,
+      #[cfg(debug_assertions)]
+      unresolved: Some(self.0.unresolved.clone()),
+    })
+  }
+
+  /// A process-wide unique identifier for distinguishing log events of batches in flight concurrently
+  pub fn batch_id(&self) -> u64 {
+    self.0.batch_id
+  }
+
+  /// The number of requests that will be resolved without a backend call of their own, being requests for keys already requested within the batch
+  pub fn deduplicated_request_count(&self) -> usize {
+    let unique_keys = self
+      .0
+      .requests
+      .iter()
+      .map(Request::key)
+      .collect::<HashSet<_>>()
+      .len();
+
+    self.0.requests.len() - unique_keys
+  }
+
+  /// The proportion of requests deduplicated, from 0.0 when every key is unique to approaching 1.0 when every request is for the same key
+  pub fn deduplication_efficiency(&self) -> f64 {
+    if self.0.requests.is_empty() {
+      return 0.0;
+    }
+
+    self.deduplicated_request_count() as f64 / self.0.requests.len() as f64
+  }
+
+  fn with_pool(mut self, pool: Option<Arc<ThreadPool>>) -> Self {
+    self.0.pool = pool;
+    self
+  }
+
+  fn with_yield_interval(mut self, yield_interval: usize) -> Self {
+    self.0.yield_interval = yield_interval;
+    self
+  }
+
+  fn with_interceptors(mut self, interceptors: Interceptors<K, V, E>) -> Self {
+    self.0.interceptors = interceptors;
+    self
+  }
+
+  #[cfg(feature = "prometheus-metrics")]
+  fn with_metrics(mut self, metrics: Option<Arc<DataLoaderMetrics>>) -> Self {
+    self.0.metrics = metrics;
+    self
+  }
+
+  #[cfg(feature = "prometheus-metrics")]
+  fn assigned_at(mut self, assigned_at: Instant) -> Self {
+    self.0.assigned_at = assigned_at;
+    self
+  }
+
+  // Record request outcomes and the time since assignment ahead of resolution
+  #[cfg(feature = "prometheus-metrics")]
+  fn observe_resolution<F>(&self, found: F)
+  where
+    F: Fn(&K) -> bool,
+  {
+    if let Some(metrics) = &self.0.metrics {
+      let request_count = self.0.requests.len();
+      let found_count = self
+        .0
+        .requests
+        .iter()
+        .filter(|req| found(req.key()))
+        .count();
+
+      metrics.observe_requests(Outcome::Found, found_count);
+      metrics.observe_requests(Outcome::NotFound, request_count - found_count);
+      metrics.observe_batch_duration(self.0.assigned_at.elapsed());
+    }
+  }
+
+  #[cfg(feature = "prometheus-metrics")]
+  fn observe_error(&self) {
+    if let Some(metrics) = &self.0.metrics {
+      metrics.observe_requests(Outcome::Error, self.0.requests.len());
+      metrics.observe_batch_duration(self.0.assigned_at.elapsed());
+    }
+  }
+
+  fn install<R, F>(&self, op: F) -> R
+  where
+    R: Send,
+    F: FnOnce() -> R + Send,
+  {
+    match &self.0.pool {
+      Some(pool) => pool.install(op),
+      None => op(),
+    }
+  }
+
+  // Requests taken from the batch no longer count towards its unresolved requests, being either resolved by the caller or handed to another batch
+  pub(crate) fn into_requests(mut self) -> Vec<Request<K, V, E>> {
+    let mut requests = std::mem::take(&mut self.0.requests);
+    requests.iter_mut().for_each(Request::release);
+    requests
+  }
+
+  /// Metadata of type `M` attached to the first request for `key` to have any, such as a trace id for correlating the load of `key` with the request that made it. See [`crate::loader::DataLoader::load_with_metadata`]
+  pub fn request_metadata<M: 'static>(&self, key: &K) -> Option<&M> {
+    self
+      .0
+      .requests
+      .iter()
+      .filter(|req| req.key().eq(key))
+      .find_map(|req| req.metadata())
+  }
+
+  /// Call `f` with each request of this batch, including requests for duplicate keys, such as for debugging or auditing
+  pub fn inspect_requests<F>(&self, f: F)
+  where
+    F: Fn(&Request<K, V, E>),
+  {
+    self.0.requests.iter().for_each(f);
+  }
+
+  #[cfg(not(feature = "ordered"))]
+  pub fn keys(&self) -> Vec<K> {
+    log::trace!(
+      "batch_id={} collecting keys of {} requests",
+      self.0.batch_id,
+      self.0.requests.len()
+    );
+
+    if self.0.requests.len() <= SMALL_BATCH_THRESHOLD {
+      let keys: KeySet<K> = self
+        .0
+        .requests
+        .iter()
+        .map(|req| req.key().to_owned())
+        .collect();
+
+      return keys.into();
+    }
+
+    self.install(|| {
+      let keys: HashSet<K> =
+        HashSet::from_par_iter(self.0.requests.par_iter().map(|req| req.key().to_owned()));
+      keys.into_par_iter().collect()
+    })
+  }
+
+  /// Unique keys in the order they were first requested
+  #[cfg(feature = "ordered")]
+  pub fn keys(&self) -> Vec<K> {
+    log::trace!(
+      "batch_id={} collecting keys of {} requests",
+      self.0.batch_id,
+      self.0.requests.len()
+    );
+
+    let keys: IndexSet<K> = self
+      .0
+      .requests
+      .iter()
+      .map(|req| req.key().to_owned())
+      .collect();
+    keys.into_iter().collect()
+  }
+
+  #[must_use]
+  pub fn resolve(self, results: Result<HashMap<K, Arc<V>>, E>) -> Task<CompletionReceipt> {
+    self.resolve_with_cache_bypass(results, HashSet::new())
+  }
+
+  // As with Task::resolve, though resolving on the calling thread and returning once every request is resolved, so that waiters are woken before a paused clock can auto-advance
+  #[cfg(any(test, feature = "testing"))]
+  pub(crate) fn resolve_inline(
+    self,
+    results: Result<HashMap<K, Arc<V>>, E>,
+  ) -> Task<CompletionReceipt> {
+    self.resolve_all(results, HashSet::new(), None, true)
+  }
+
+  /// Resolve as with [`Task::resolve`], though without caching the results of `bypass_keys`: their entries are removed from the [`crate::request::ContextCache`] once resolved, such that loads already awaiting them receive the result while subsequent loads re-fetch. Removal isn't broadcast as an invalidation. See [`TaskHandler::should_cache`] for deciding per value instead
+  #[must_use]
+  pub fn resolve_with_cache_bypass(
+    self,
+    results: Result<HashMap<K, Arc<V>>, E>,
+    bypass_keys: HashSet<K>,
+  ) -> Task<CompletionReceipt> {
+    self.resolve_all(results, bypass_keys, None, false)
+  }
+
+  // The resolution shared by every resolve method, observing and recording metrics of the batch and calling interceptors alike. Requests are resolved in parallel, or should `order` be given sequentially as per resolve_in_order. Resolution is spawned on the thread pool of the batch unless `inline`
+  fn resolve_all(
+    mut self,
+    results: Result<HashMap<K, Arc<V>>, E>,
+    bypass_keys: HashSet<K>,
+    order: Option<Vec<K>>,
+    inline: bool,
+  ) -> Task<CompletionReceipt> {
+    log::trace!(
+      "batch_id={} resolving {} requests",
+      self.0.batch_id,
+      self.0.requests.len()
+    );
+
+    #[cfg(feature = "prometheus-metrics")]
+    match &results {
+      Ok(values) => self.observe_resolution(|key| values.contains_key(key)),
+      Err(_) => self.observe_error(),
+    }
+
+    let pool = self.0.pool.clone();
+    let yield_interval = self.0.yield_interval;
+    let interceptors = std::mem::take(&mut self.0.interceptors);
+    let observation = self.0.observation.take();
+    let receipt = self.receipt();
+    let requests = self.into_requests();
+    let request_count = requests.len();
+
+    // Interceptors are called from the runtime resolving this batch once resolution completes
+    let runtime_handle = (!interceptors.is_empty())
+      .then(tokio::runtime::Handle::try_current)
+      .and_then(Result::ok);
+
+    let resolve = move || {
+      // Observed ahead of resolving requests, so that observers see resolution before any awaiting request is woken
+      if let Some(observation) = &observation {
+        match &results {
+          Ok(_) => observation.resolved(request_count, 0),
+          Err(_) => observation.resolved(0, request_count),
+        }
+      }
+
+      match results {
+        Ok(values) => {
+          match order {
+            Some(order) => resolve_in_order(requests, &order, &values, &bypass_keys),
+            None => resolve_each(requests, yield_interval, |req| {
+              let value = values.get(req.key()).cloned();
+              let bypass_cache = bypass_keys.contains(req.key());
+              req.resolve_bypassing_cache(Ok(value), bypass_cache);
+            }),
+          }
+
+          if let Some(runtime_handle) = runtime_handle {
+            runtime_handle.spawn(async move {
+              for interceptor in interceptors.iter() {
+                interceptor.after_resolve(&values).await;
+              }
+            });
+          }
+        }
+
+        Err(e) => {
+          resolve_each(requests, yield_interval, |req| {
+            let bypass_cache = bypass_keys.contains(req.key());
+            req.resolve_bypassing_cache(Err(e.clone()), bypass_cache);
+          });
+
+          for interceptor in interceptors.iter() {
+            interceptor.on_error(&e);
+          }
+        }
+      };
+    };
+
+    if inline {
+      resolve();
+    } else {
+      spawn_on(pool.as_deref(), resolve);
+    }
+
+    receipt
+  }
+
+  /// Separate the unique keys of this batch as a parallel iterator from the callback that resolves it, for loads that compute values per key on the rayon thread pool
+  ///
+  /// ```rust
+  /// let (keys, resolve) = task.split_for_parallel_load();
+  /// let data: HashMap<UserId, Arc<Avatar>> = keys.map(|key| (key, Arc::new(render(key)))).collect();
+  /// resolve(Ok(data))
+  /// ```
+  #[allow(clippy::type_complexity)]
+  pub fn split_for_parallel_load(
+    self,
+  ) -> (
+    rayon::vec::IntoIter<K>,
+    impl FnOnce(Result<HashMap<K, Arc<V>>, E>) -> Task<CompletionReceipt>,
+  ) {
+    let keys = self.keys().into_par_iter();
+
+    (keys, move |results| self.resolve(results))
+  }
+
+  /// Resolve from key-value pairs, such as rows of a query selecting the key alongside the value
+  ///
+  /// ```rust
+  /// let results = users::table
+  ///   .filter(users::id.eq_any(task.keys()))
+  ///   .select((users::id, users::all_columns))
+  ///   .load::<(UserId, User)>(&mut conn)
+  ///   .map(|rows| rows.into_iter().map(|(id, user)| (id, Arc::new(user))));
+  ///
+  /// task.resolve_pairs(results)
+  /// ```
+  #[must_use]
+  pub fn resolve_pairs<I>(self, results: Result<I, E>) -> Task<CompletionReceipt>
+  where
+    I: IntoIterator<Item = (K, Arc<V>)>,
+  {
+    self.resolve(results.map(|pairs| pairs.into_iter().collect()))
+  }
+
+  /// Resolve from values containing their own key, as extracted by `key_fn`
+  ///
+  /// ```rust
+  /// let results = content::table
+  ///   .filter(content::id.eq_any(task.keys()))
+  ///   .load::<Content>(&mut conn)
+  ///   .map(|rows| rows.into_iter().map(Arc::new).collect());
+  ///
+  /// task.resolve_keyed(results, |content| content.id)
+  /// ```
+  #[must_use]
+  pub fn resolve_keyed<F>(
+    self,
+    results: Result<Vec<Arc<V>>, E>,
+    key_fn: F,
+  ) -> Task<CompletionReceipt>
+  where
+    F: Fn(&V) -> K,
+  {
+    self
+      .resolve_pairs(results.map(|values| values.into_iter().map(|value| (key_fn(&value), value))))
+  }
+
+  /// Resolve requests sequentially in the insertion order of `results`, followed by requests for keys not found within `results` in the order they were requested. Otherwise resolution is as with [`Task::resolve`], including interceptors, observers and metrics
+  #[cfg(feature = "ordered")]
+  #[must_use]
+  pub fn resolve_ordered(self, results: Result<IndexMap<K, Arc<V>>, E>) -> Task<CompletionReceipt> {
+    match results {
+      Ok(values) => {
+        let order = values.keys().cloned().collect();
+        self.resolve_all(
+          Ok(values.into_iter().collect()),
+          HashSet::new(),
+          Some(order),
+          false,
+        )
+      }
+      Err(e) => self.resolve_all(Err(e), HashSet::new(), None, false),
+    }
+  }
+
+  /// Partition requests by shard label into sub-batches that can each be dispatched and resolved independently, one per shard in the order each shard was first requested. The shard of a sub-batch is that of any of its keys. Resolving every sub-batch collectively resolves all requests of the original batch
+  pub fn split_by_shard<S, F>(mut self, shards: F) -> Vec<Task<LoadBatch<K, V, E>>>
+  where
+    S: Hash + Eq,
+    F: Fn(&K) -> S,
+  {
+    let pool = self.0.pool.clone();
+    let yield_interval = self.0.yield_interval;
+    #[cfg(feature = "prometheus-metrics")]
+    let (metrics, assigned_at) = (self.0.metrics.clone(), self.0.assigned_at);
+    let interceptors = self.0.interceptors.clone();
+    let observation = self.0.observation.take();
+    #[cfg(debug_assertions)]
+    let unresolved = self.0.unresolved.clone();
+    let requests = self.into_requests();
+
+    let mut shard_index: HashMap<S, usize> = HashMap::new();
+    let mut partitions: Vec<Vec<Request<K, V, E>>> = vec![];
+
+    for req in requests.into_iter() {
+      let i = *shard_index.entry(shards(req.key())).or_insert_with(|| {
+        partitions.push(vec![]);
+        partitions.len() - 1
+      });
+
+      partitions[i].push(req);
+    }
+
+    partitions
+      .into_iter()
+      .map(|requests| {
+        let mut task = Task::from_requests(requests)
+          .with_pool(pool.clone())
+          .with_yield_interval(yield_interval)
+          .with_interceptors(interceptors.clone());
+
+        task.0.observation = observation.clone();
+
+        // Sub-batches share the counter of the batch they were split from, such that receipts of any check the requests of all
+        #[cfg(debug_assertions)]
+        let task = task.tracking_unresolved(unresolved.clone());
+
+        // Sub-batches are timed from the assignment of the batch they were split from
+        #[cfg(feature = "prometheus-metrics")]
+        let task = task.with_metrics(metrics.clone()).assigned_at(assigned_at);
+
+        task
+      })
+      .collect()
+  }
+
+  /// Validate the batch as per [`TaskHandler::validate_key`] and then [`TaskHandler::validate_batch`] ahead of loading it, returning the batch of requests remaining to be loaded, if any. Handlers of this crate validate upon assignment
+  ///
+  /// ```rust
+  /// match task.get_assignment::<Self>().await {
+  ///   TaskAssignment::LoadBatch(task) => match task.validate::<Self>() {
+  ///     TaskAssignment::LoadBatch(task) => task.resolve(load(task.keys()).await),
+  ///     TaskAssignment::NoAssignment(receipt) => receipt,
+  ///   },
+  ///   TaskAssignment::NoAssignment(receipt) => receipt,
+  /// }
+  /// ```
+  #[must_use]
+  pub fn validate<T>(mut self) -> TaskAssignment<K, V, E>
+  where
+    T: TaskHandler<Key = K, Value = V, Error = E>,
+  {
+    let keys = self.keys();
+
+    let invalid_keys: HashMap<K, E> = self.install(|| {
+      keys
+        .into_par_iter()
+        .filter_map(|key| T::validate_key(&key).err().map(|err| (key, err)))
+        .collect()
+    });
+
+    if !invalid_keys.is_empty() {
+      log::debug!(
+        "batch_id={} rejected {} invalid keys",
+        self.0.batch_id,
+        invalid_keys.len()
+      );
+
+      let request_count = self.0.requests.len();
+
+      self.0.requests = std::mem::take(&mut self.0.requests)
+        .into_iter()
+        .filter_map(|req| match invalid_keys.get(req.key()) {
+          Some(err) => {
+            req.resolve(Err(err.clone()));
+            None
+          }
+          None => Some(req),
+        })
+        .collect();
+
+      if let Some(observation) = self.0.observation.as_mut() {
+        observation.partially_failed(request_count - self.0.requests.len());
+      }
+
+      if self.0.requests.is_empty() {
+        return TaskAssignment::NoAssignment(self.receipt());
+      }
+    }
+
+    match T::validate_batch(&self.keys()) {
+      Ok(()) => TaskAssignment::LoadBatch(self),
+      Err(err) => {
+        log::debug!("batch_id={} rejected as invalid", self.0.batch_id);
+        TaskAssignment::NoAssignment(self.resolve(Err(err)))
+      }
+    }
+  }
+
+  // Resolve each request as the outcome of its key, cancelling requests of keys without one, for handlers that load keys of a batch separately such as [`crate::decorator::RetryDecorator`]. Interceptors aren't called, as outcomes needn't share a result
+  pub(crate) fn resolve_outcomes(
+    mut self,
+    outcomes: HashMap<K, Result<Option<Arc<V>>, E>>,
+    shutdown_error: Option<E>,
+  ) -> Task<CompletionReceipt> {
+    let observation = self.0.observation.take();
+    let receipt = self.receipt();
+    let requests = self.into_requests();
+
+    if let Some(observation) = &observation {
+      let err_count = requests
+        .iter()
+        .filter(|req| matches!(outcomes.get(req.key()), Some(Err(_)) | None))
+        .count();
+
+      observation.resolved(requests.len() - err_count, err_count);
+    }
+
+    for req in requests.into_iter() {
+      match outcomes.get(req.key()) {
+        Some(outcome) => req.resolve(outcome.clone()),
+        None => req.cancel(shutdown_error.clone()),
+      }
+    }
+
+    receipt
+  }
+
+  #[must_use]
+  pub(crate) fn apply_partial_results(
+    mut self,
+    results: HashMap<K, Arc<V>>,
+  ) -> TaskAssignment<K, V, E> {
+    let pool = self.0.pool.clone();
+    let batch_id = self.0.batch_id;
+    let yield_interval = self.0.yield_interval;
+    let interceptors = self.0.interceptors.clone();
+    let mut observation = self.0.observation.take();
+    #[cfg(feature = "prometheus-metrics")]
+    let (metrics, assigned_at) = (self.0.metrics.clone(), self.0.assigned_at);
+    let receipt = self.receipt();
+    #[cfg(debug_assertions)]
+    let unresolved = self.0.unresolved.clone();
+    let requests = self.into_requests();
+    let request_count = requests.len();
+
+    let requests: Vec<Request<K, V, E>> = requests
+      .into_par_iter()
+      .filter_map(|req| {
+        if let Some(value) = results.get(req.key()).cloned() {
+          req.resolve(Ok(Some(value)));
+          None
+        } else {
+          Some(req)
+        }
+      })
+      .collect();
+
+    log::trace!(
+      "batch_id={} resolved {} of {} requests from partial results",
+      batch_id,
+      request_count - requests.len(),
+      request_count
+    );
+
+    #[cfg(feature = "prometheus-metrics")]
+    if let Some(metrics) = &metrics {
+      metrics.observe_requests(Outcome::Found, request_count - requests.len());
+    }
+
+    if let Some(observation) = observation.as_mut() {
+      observation.partially_resolved(request_count - requests.len());
+    }
+
+    if requests.len().gt(&0) {
+      let task = Task(LoadBatch {
+        requests,
+        pool,
+        batch_id,
+        yield_interval,
+        interceptors,
+        observation,
+        #[cfg(feature = "prometheus-metrics")]
+        metrics,
+        #[cfg(feature = "prometheus-metrics")]
+        assigned_at,
+        #[cfg(debug_assertions)]
+        unresolved: unresolved.clone(),
+      });
+
+      #[cfg(debug_assertions)]
+      let task = task.tracking_unresolved(unresolved);
+
+      TaskAssignment::LoadBatch(task)
+    } else {
+      TaskAssignment::NoAssignment(receipt)
+    }
+  }
+}
+
+/// Resolve requests progressively as results become available, such as when streaming rows from a database cursor. Each value is sent to the requests awaiting its key immediately, and [`Task::finish`] resolves the requests that remain as `Ok(None)`
+impl<K, V, E> Extend<(K, Arc<V>)> for Task<LoadBatch<K, V, E>>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn extend<I: IntoIterator<Item = (K, Arc<V>)>>(&mut self, iter: I) {
+    let mut pending = PendingRequests::new(std::mem::take(&mut self.0.requests));
+
+    for (key, value) in iter {
+      self.resolve_found(pending.take(&key), value);
+    }
+
+    self.0.requests = pending.into_remaining();
+  }
+}
+
+// The requests of a batch indexed by key, such that results arriving one at a time resolve without rescanning the batch. Requests not yet resolved retain their order
+struct PendingRequests<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static> {
+  requests: Vec<Option<Request<K, V, E>>>,
+  positions: HashMap<K, Vec<usize>>,
+}
+
+impl<K, V, E> PendingRequests<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn new(requests: Vec<Request<K, V, E>>) -> Self {
+    let mut positions: HashMap<K, Vec<usize>> = HashMap::new();
+
+    for (i, req) in requests.iter().enumerate() {
+      positions.entry(req.key().to_owned()).or_default().push(i);
+    }
+
+    PendingRequests {
+      requests: requests.into_iter().map(Some).collect(),
+      positions,
+    }
+  }
+
+  fn take(&mut self, key: &K) -> Vec<Request<K, V, E>> {
+    self
+      .positions
+      .remove(key)
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|i| self.requests[i].take())
+      .collect()
+  }
+
+  fn into_remaining(self) -> Vec<Request<K, V, E>> {
+    self.requests.into_iter().flatten().collect()
+  }
+}
+
+impl<K, V, E> Task<LoadBatch<K, V, E>>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  fn resolve_found(&mut self, resolved: Vec<Request<K, V, E>>, value: Arc<V>) {
+    #[cfg(feature = "prometheus-metrics")]
+    if let Some(metrics) = &self.0.metrics {
+      metrics.observe_requests(Outcome::Found, resolved.len());
+    }
+
+    if let Some(observation) = self.0.observation.as_mut() {
+      observation.partially_resolved(resolved.len());
+    }
+
+    resolved
+      .into_iter()
+      .for_each(|req| req.resolve(Ok(Some(value.clone()))));
+  }
+
+  /// Resolve all requests not yet resolved by [`Extend::extend`] as `Ok(None)`
+  #[must_use]
+  pub fn finish(self) -> Task<CompletionReceipt> {
+    log::trace!(
+      "batch_id={} finishing {} remaining requests",
+      self.0.batch_id,
+      self.0.requests.len()
+    );
+
+    #[cfg(feature = "prometheus-metrics")]
+    self.observe_resolution(|_| false);
+
+    let mut task = self;
+    let observation = task.0.observation.take();
+    let receipt = task.receipt();
+    let requests = task.into_requests();
+    let request_count = requests.len();
+
+    if let Some(observation) = observation {
+      observation.resolved(request_count, 0);
+    }
+
+    requests.into_iter().for_each(|req| req.resolve(Ok(None)));
+
+    receipt
+  }
+
+  /// Resolve requests as values are sent to the returned channel, from a spawned task that [`Task::finish`]es the batch once every sender is dropped. This allows results to be streamed from the backend without buffering the whole batch in memory
+  ///
+  /// ```rust
+  /// let keys = task.keys();
+  /// let (tx, handle) = task.resolve_via_channel(1024);
+  ///
+  /// tokio::task::spawn_blocking(move || -> Result<(), DieselError> {
+  ///   let mut conn = get_connection()?;
+  ///
+  ///   for user in users::table
+  ///     .filter(users::id.eq_any(keys))
+  ///     .load_iter::<User, DefaultLoadingMode>(&mut conn)?
+  ///   {
+  ///     let user = user?;
+  ///
+  ///     if tx.blocking_send((user.id, Arc::new(user))).is_err() {
+  ///       break;
+  ///     }
+  ///   }
+  ///
+  ///   Ok(())
+  /// })
+  /// .await
+  /// .unwrap()?;
+  ///
+  /// handle.await.unwrap()
+  /// ```
+  #[allow(clippy::type_complexity)]
+  pub fn resolve_via_channel(
+    mut self,
+    buffer: usize,
+  ) -> (
+    mpsc::Sender<(K, Arc<V>)>,
+    JoinHandle<Task<CompletionReceipt>>,
+  ) {
+    let (tx, mut rx) = mpsc::channel(buffer);
+
+    let handle = tokio::task::spawn(async move {
+      let mut pending = PendingRequests::new(std::mem::take(&mut self.0.requests));
+
+      while let Some((key, value)) = rx.recv().await {
+        self.resolve_found(pending.take(&key), value);
+      }
+
+      self.0.requests = pending.into_remaining();
+      self.finish()
+    });
+
+    (tx, handle)
+  }
+
+  /// Pair each key of this batch with metadata by key. Keys without metadata are retained and paired with `None`, whereas metadata of keys not within this batch is kept but never yielded
+  ///
+  /// ```rust
+  /// let acls = fetch_acls(&task.keys()).await?;
+  /// let task = task.zip_with_metadata(acls);
+  ///
+  /// let permitted: Vec<UserId> = task
+  ///   .keyed_metadata()
+  ///   .filter(|(_, acl)| acl.is_some_and(|acl| acl.can_read()))
+  ///   .map(|(key, _)| *key)
+  ///   .collect();
+  ///
+  /// task.resolve(load_users(permitted).await)
+  /// ```
+  pub fn zip_with_metadata<M: Send + 'static>(
+    self,
+    metadata: HashMap<K, M>,
+  ) -> Task<LoadBatchWithMeta<K, V, E, M>> {
+    let keys = self.keys();
+
+    Task(LoadBatchWithMeta {
+      batch: self,
+      keys,
+      metadata,
+    })
+  }
+
+  /// Split this batch into a future per unique key alongside the [`TaskResolver`] that fulfills them, decoupling consumers of individual keys from assembling the result map. Each future resolves as [`TaskResolver::resolve`] is called, and panics as with [`OneshotReceiver::recv`] should the resolver be dropped unresolved
+  ///
+  /// ```rust
+  /// let (keyed_futures, resolver) = task.into_keyed_futures();
+  ///
+  /// let mut audits: FuturesUnordered<_> = keyed_futures
+  ///   .into_iter()
+  ///   .map(|(user_id, user)| async move { audit::record_load(user_id, user.await) })
+  ///   .collect();
+  ///
+  /// tokio::task::spawn(async move { while audits.next().await.is_some() {} });
+  ///
+  /// let keys = resolver.keys();
+  /// resolver.resolve(load_users(keys).await)
+  /// ```
+  #[allow(clippy::type_complexity)]
+  pub fn into_keyed_futures(
+    self,
+  ) -> (
+    Vec<(
+      K,
+      impl Future<Output = Result<Option<Arc<V>>, E>> + Send + 'static,
+    )>,
+    TaskResolver<K, V, E>,
+  ) {
+    let (keyed_futures, senders) = self
+      .keys()
+      .into_iter()
+      .map(|key| {
+        let (tx, rx) = oneshot::channel();
+
+        ((key.clone(), OneshotReceiver::from(rx).recv()), (key, tx))
+      })
+      .unzip();
+
+    (
+      keyed_futures,
+      TaskResolver {
+        batch: self,
+        senders,
+      },
+    )
+  }
+}
+
+impl<K, V, E, M> Task<LoadBatchWithMeta<K, V, E, M>>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+  M: Send + 'static,
+{
+  pub fn batch_id(&self) -> u64 {
+    self.0.batch.batch_id()
+  }
+
+  /// Unique keys as returned by [`Task::keys`] upon being zipped
+  pub fn keys(&self) -> Vec<K> {
+    self.0.keys.clone()
+  }
+
+  /// Each key of the batch paired with its metadata, if any
+  pub fn keyed_metadata(&self) -> impl Iterator<Item = (&K, Option<&M>)> {
+    self
+      .0
+      .keys
+      .iter()
+      .map(move |key| (key, self.0.metadata.get(key)))
+  }
+
+  #[must_use]
+  pub fn resolve(self, results: Result<HashMap<K, Arc<V>>, E>) -> Task<CompletionReceipt> {
+    self.0.batch.resolve(results)
+  }
+}
+
+/// Fulfills the per-key futures of [`Task::into_keyed_futures`] alongside the batch they were split from
+pub struct TaskResolver<K: Key, V: Send + Sync + Clone + 'static, E: Send + Sync + Clone + 'static>
+{
+  batch: Task<LoadBatch<K, V, E>>,
+  senders: Vec<(K, oneshot::Sender<LoadResult<V, E>>)>,
+}
+
+impl<K, V, E> TaskResolver<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + Clone + 'static,
+  E: Send + Sync + Clone + 'static,
+{
+  pub fn batch_id(&self) -> u64 {
+    self.batch.batch_id()
+  }
+
+  /// Unique keys, in the order of the keyed futures
+  pub fn keys(&self) -> Vec<K> {
+    self.senders.iter().map(|(key, _)| key.clone()).collect()
+  }
+
+  /// Resolve the batch, fulfilling each keyed future with the value of its key, or `Ok(None)` if absent
+  #[must_use]
+  pub fn resolve(self, results: Result<HashMap<K, Arc<V>>, E>) -> Task<CompletionReceipt> {
+    let TaskResolver { batch, senders } = self;
+
+    for (key, tx) in senders {
+      let result = match &results {
+        Ok(values) => Ok(values.get(&key).cloned()),
+        Err(err) => Err(err.clone()),
+      };
+
+      tx.send(result).ok();
+    }
+
+    batch.resolve(results)
+  }
+}
+
+impl Task<CompletionReceipt> {
+  pub(crate) fn completion_receipt() -> Self {
+    Task(CompletionReceipt {
+      _marker: PhantomData,
+      #[cfg(debug_assertions)]
+      unresolved: None,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{BatchRecorder, MockBackend, MockHandler, TestError};
+  use crate::{
+    loader::DataLoader,
+    request::{ContextCache, RecvCancelled},
+  };
+  use futures_util::{stream::FuturesUnordered, StreamExt};
+  use std::{iter, sync::atomic::AtomicBool, time::Duration};
+
+  #[tokio::test]
+  async fn it_collects_up_to_n_requests() {
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..25).map(Request::new_oneshot).unzip();
+
+    let mut task = Task::new(Stealer::Owner(requests));
+    let mut collected = vec![];
+
+    assert_eq!(task.collect_n(10, &mut collected).await, 10);
+    assert_eq!(task.request_count(), 15);
+    assert_eq!(task.collect_n(10, &mut collected).await, 10);
+    assert_eq!(task.collect_n(10, &mut collected).await, 5);
+    assert_eq!(task.collect_n(10, &mut collected).await, 0);
+
+    assert_eq!(
+      collected
+        .iter()
+        .map(Request::key)
+        .copied()
+        .collect::<Vec<_>>(),
+      (0..25).collect::<Vec<_>>()
+    );
+  }
+
+  #[tokio::test]
+  async fn it_splits_by_shard() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..10).map(Request::new_oneshot).unzip();
+
+    let mut shards = Task::from_requests(requests).split_by_shard(|key| key % 2);
+
+    assert_eq!(shards.len(), 2);
+
+    let even = shards.remove(0);
+    let odd = shards.remove(0);
+
+    assert_eq!(even.keys().len(), 5);
+    assert!(even.keys().iter().all(|key| key % 2 == 0));
+    assert!(odd.keys().iter().all(|key| key % 2 == 1));
+
+    let even_results: HashMap<i32, Arc<i32>> = even
+      .keys()
+      .into_iter()
+      .zip(iter::repeat(Arc::new(0)))
+      .collect();
+
+    let _ = even.resolve(Ok(even_results));
+    let _ = odd.resolve(Err(TestError("unavailable")));
+
+    for (key, rx) in (0..10).zip(receivers) {
+      let result = rx.recv().await;
+
+      if key % 2 == 0 {
+        assert_eq!(result, Ok(Some(Arc::new(0))));
+      } else {
+        assert_eq!(result, Err(TestError("unavailable")));
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_resolves_keyed_futures() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 3, 2]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let (keyed_futures, resolver) = Task::from_requests(requests).into_keyed_futures();
+
+    let mut keys: Vec<i32> = keyed_futures.iter().map(|(key, _)| *key).collect();
+    keys.sort_unstable();
+
+    assert_eq!(keys, vec![1, 2, 3]);
+    assert_eq!(resolver.keys().len(), 3);
+
+    let observed = tokio::task::spawn(async move {
+      let mut observed: Vec<(i32, LoadResult<i32, TestError>)> = keyed_futures
+        .into_iter()
+        .map(|(key, value)| async move { (key, value.await) })
+        .collect::<FuturesUnordered<_>>()
+        .collect()
+        .await;
+
+      observed.sort_unstable_by_key(|(key, _)| *key);
+      observed
+    });
+
+    let results: HashMap<i32, Arc<i32>> = vec![(1, Arc::new(10)), (2, Arc::new(20))]
+      .into_iter()
+      .collect();
+
+    let _ = resolver.resolve(Ok(results));
+
+    assert_eq!(
+      observed.await.unwrap(),
+      vec![
+        (1, Ok(Some(Arc::new(10)))),
+        (2, Ok(Some(Arc::new(20)))),
+        (3, Ok(None))
+      ]
+    );
+
+    let values = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(10))),
+        Ok(Some(Arc::new(20))),
+        Ok(None),
+        Ok(Some(Arc::new(20)))
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn it_zips_batches_with_metadata() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 3, 2]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let shard_hints: HashMap<i32, &str> = vec![(1, "east"), (3, "west"), (9, "north")]
+      .into_iter()
+      .collect();
+
+    let task = Task::from_requests(requests).zip_with_metadata(shard_hints);
+
+    let mut keyed_metadata: Vec<(i32, Option<&str>)> = task
+      .keyed_metadata()
+      .map(|(key, hint)| (*key, hint.copied()))
+      .collect();
+
+    keyed_metadata.sort_unstable();
+
+    assert_eq!(
+      keyed_metadata,
+      vec![(1, Some("east")), (2, None), (3, Some("west"))]
+    );
+
+    let results: HashMap<i32, Arc<i32>> = task
+      .keyed_metadata()
+      .filter(|(_, hint)| hint.is_some())
+      .map(|(key, _)| (*key, Arc::new(key * 10)))
+      .collect();
+
+    let _ = task.resolve(Ok(results));
+
+    let values = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(10))),
+        Ok(None),
+        Ok(Some(Arc::new(30))),
+        Ok(None)
+      ]
+    );
+  }
+
+  pub struct IsolatedLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for IsolatedLoader {
+    type Key = i32;
+    type Value = String;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let thread_name = task.install(|| std::thread::current().name().map(String::from));
+
+          let data: HashMap<i32, Arc<String>> = task
+            .keys()
+            .into_iter()
+            .zip(iter::repeat(Arc::new(thread_name.unwrap_or_default())))
+            .collect();
+
+          task.resolve(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  pub struct PipelinedLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for PipelinedLoader {
+    type Key = i32;
+    type Value = usize;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(2);
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      let assignments = task.get_assignments::<Self>(3).await;
+      let depth = assignments.len();
+
+      let mut receipt = Task::completion_receipt();
+
+      for task in assignments {
+        let data: HashMap<i32, Arc<usize>> = task
+          .keys()
+          .into_iter()
+          .zip(iter::repeat(Arc::new(depth)))
+          .collect();
+
+        receipt = task.resolve(Ok(data));
+      }
+
+      receipt
+    }
+  }
+
+  #[tokio::test]
+  async fn it_retains_pipelined_assignments() {
+    let loader: DataLoader<PipelinedLoader> = DataLoader::default();
+
+    let receivers: Vec<_> = (0..10).map(|key| loader.load_by(key)).collect();
+
+    let depths: Vec<usize> =
+      futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv()))
+        .await
+        .into_iter()
+        .map(|result| *result.unwrap().unwrap())
+        .collect();
+
+    // Of 5 batches, 3 are retained by the first task and the remaining 2 are dispatched to tasks of their own
+    assert_eq!(depths, vec![3, 3, 3, 3, 3, 3, 1, 1, 1, 1]);
+  }
+
+  #[tokio::test]
+  async fn it_assigns_distinct_batch_ids() {
+    let handles = (0..8).map(|key| {
+      tokio::task::spawn(async move {
+        let (req, _rx) = Request::<i32, i32, TestError>::new_oneshot(key);
+        let task = Task::from_requests(vec![req]);
+        let batch_id = task.batch_id();
+        let _ = task.resolve(Err(TestError("unavailable")));
+        batch_id
+      })
+    });
+
+    let batch_ids: std::collections::HashSet<u64> = futures_util::future::join_all(handles)
+      .await
+      .into_iter()
+      .map(|batch_id| batch_id.unwrap())
+      .collect();
+
+    assert_eq!(batch_ids.len(), 8);
+  }
+
+  #[tokio::test]
+  async fn it_resolves_on_the_thread_pool_of_the_loader() {
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(2)
+      .thread_name(|i| format!("isolated-rayon-{}", i))
+      .build()
+      .unwrap();
+
+    let loader: DataLoader<IsolatedLoader> = DataLoader::default();
+    loader.set_thread_pool(Arc::new(pool));
+
+    let thread_name = loader.load_by(1).recv().await.unwrap().unwrap();
+
+    assert!(thread_name.starts_with("isolated-rayon-"));
+  }
+
+  #[tokio::test]
+  async fn it_yields_while_resolving() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..1000).map(Request::new_oneshot).unzip();
+
+    let task = Task::from_requests(requests).with_yield_interval(16);
+
+    let data: HashMap<i32, Arc<i32>> = task
+      .keys()
+      .into_iter()
+      .filter(|key| key % 2 == 0)
+      .map(|key| (key, Arc::new(key)))
+      .collect();
+
+    let _ = task.resolve(Ok(data));
+
+    for (key, rx) in (0..1000).zip(receivers) {
+      let expected = if key % 2 == 0 {
+        Some(Arc::new(key))
+      } else {
+        None
+      };
+
+      assert_eq!(rx.recv().await, Ok(expected));
+    }
+  }
+
+  #[tokio::test]
+  async fn it_splits_for_parallel_load() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 2, 3]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let (keys, resolve) = Task::from_requests(requests).split_for_parallel_load();
+
+    let data: HashMap<i32, Arc<i32>> = keys.map(|key| (key, Arc::new(key * key))).collect();
+
+    assert_eq!(data.len(), 3);
+
+    let _ = resolve(Ok(data));
+
+    let values = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(1))),
+        Ok(Some(Arc::new(4))),
+        Ok(Some(Arc::new(4))),
+        Ok(Some(Arc::new(9))),
+      ]
+    );
+  }
+
+  #[test]
+  fn it_measures_deduplication() {
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..1000).map(|i| Request::new_oneshot(i % 10)).unzip();
+
+    let task = Task::from_requests(requests);
+
+    assert_eq!(task.deduplicated_request_count(), 990);
+    assert!((task.deduplication_efficiency() - 0.99).abs() < f64::EPSILON);
+
+    let _ = task.resolve(Err(TestError("unavailable")));
+
+    let empty: Task<LoadBatch<i32, i32, TestError>> = Task::from_requests(vec![]);
+
+    assert_eq!(empty.deduplicated_request_count(), 0);
+    assert_eq!(empty.deduplication_efficiency(), 0.0);
+  }
+
+  #[tokio::test]
+  async fn it_inspects_every_request() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 2, 3]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let task = Task::from_requests(requests);
+
+    let inspected = Mutex::new(vec![]);
+    task.inspect_requests(|req| inspected.lock().unwrap().push(*req.key()));
+
+    assert_eq!(*inspected.lock().unwrap(), vec![1, 2, 2, 3]);
+    assert_eq!(task.keys().len(), 3);
+
+    let _ = task.resolve(Err(TestError("unavailable")));
+
+    for rx in receivers {
+      assert_eq!(rx.recv().await, Err(TestError("unavailable")));
+    }
+  }
+
+  #[tokio::test]
+  async fn it_resolves_pairs_and_keyed_values() {
+    let (requests, receivers): (Vec<Request<i32, _, TestError>>, Vec<_>) =
+      (0..4).map(Request::new_oneshot).unzip();
+
+    let mut shards = Task::from_requests(requests).split_by_shard(|key| key % 2);
+
+    let even = shards.remove(0);
+    let odd = shards.remove(0);
+
+    let pairs = even
+      .keys()
+      .into_iter()
+      .map(|key| (key, Arc::new((key, "pair"))));
+    let _ = even.resolve_pairs(Ok(pairs));
+
+    let values = vec![Arc::new((1, "keyed"))];
+    let _ = odd.resolve_keyed(Ok(values), |(key, _)| *key);
+
+    let results = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      results,
+      vec![
+        Ok(Some(Arc::new((0, "pair")))),
+        Ok(Some(Arc::new((1, "keyed")))),
+        Ok(Some(Arc::new((2, "pair")))),
+        Ok(None),
+      ]
+    );
+  }
+
+  #[cfg(feature = "ordered")]
+  struct ResolvedInterceptor(Mutex<Option<oneshot::Sender<usize>>>);
+
+  #[cfg(feature = "ordered")]
+  #[async_trait::async_trait]
+  impl crate::interceptor::BatchInterceptor<i32, i32, TestError> for ResolvedInterceptor {
+    async fn after_resolve(&self, results: &HashMap<i32, Arc<i32>>) {
+      if let Some(tx) = self.0.lock().unwrap().take() {
+        tx.send(results.len()).ok();
+      }
+    }
+  }
+
+  #[cfg(feature = "ordered")]
+  #[tokio::test]
+  async fn it_resolves_in_order() {
+    let (tx, after_resolve) = oneshot::channel();
+
+    let resolved: Arc<Mutex<Vec<i32>>> = Arc::new(Mutex::new(vec![]));
+
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![5, 3, 8, 3, 1, 9]
+      .into_iter()
+      .map(|key| {
+        let (mut req, rx) = Request::new_oneshot(key);
+        let resolved = resolved.clone();
+        req.set_cache_cb(Arc::new(move |key, _| resolved.lock().unwrap().push(*key)));
+        (req, rx)
+      })
+      .unzip();
+
+    let task = Task::from_requests(requests)
+      .with_interceptors(vec![Arc::new(ResolvedInterceptor(Mutex::new(Some(tx))))]);
+
+    assert_eq!(task.keys(), vec![5, 3, 8, 1, 9]);
+
+    let results: IndexMap<i32, Arc<i32>> = vec![9, 3, 5, 1]
+      .into_iter()
+      .map(|key| (key, Arc::new(key)))
+      .collect();
+
+    let _ = task.resolve_ordered(Ok(results));
+
+    let values = futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Some(Arc::new(5))),
+        Ok(Some(Arc::new(3))),
+        Ok(None),
+        Ok(Some(Arc::new(3))),
+        Ok(Some(Arc::new(1))),
+        Ok(Some(Arc::new(9))),
+      ]
+    );
+
+    assert_eq!(*resolved.lock().unwrap(), vec![9, 3, 3, 5, 1]);
+    assert_eq!(after_resolve.await, Ok(4));
+  }
+
+  #[tokio::test]
+  async fn it_resolves_progressively() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) = vec![1, 2, 1, 3]
+      .into_iter()
+      .map(Request::new_oneshot)
+      .unzip();
+
+    let mut receivers = receivers.into_iter();
+    let (first, missing, second, last) = (
+      receivers.next().unwrap(),
+      receivers.next().unwrap(),
+      receivers.next().unwrap(),
+      receivers.next().unwrap(),
+    );
+
+    let mut task = Task::from_requests(requests);
+
+    task.extend(Some((1, Arc::new(10))));
+
+    // Callers of resolved keys unblock before the batch is finished
+    assert_eq!(first.recv().await, Ok(Some(Arc::new(10))));
+    assert_eq!(second.recv().await, Ok(Some(Arc::new(10))));
+
+    task.extend(vec![(3, Arc::new(30)), (4, Arc::new(40))]);
+
+    assert_eq!(last.recv().await, Ok(Some(Arc::new(30))));
+
+    let _ = task.finish();
+
+    assert_eq!(missing.recv().await, Ok(None));
+  }
+
+  #[tokio::test]
+  async fn it_resolves_via_channel() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      vec![1, 2, 3].into_iter().map(Request::new_oneshot).unzip();
+
+    let mut receivers = receivers.into_iter();
+    let (first, missing, last) = (
+      receivers.next().unwrap(),
+      receivers.next().unwrap(),
+      receivers.next().unwrap(),
+    );
+
+    let (tx, handle) = Task::from_requests(requests).resolve_via_channel(1);
+
+    tx.send((1, Arc::new(10))).await.unwrap();
+
+    // Requests resolve as values are sent, while the channel remains open
+    assert_eq!(first.recv().await, Ok(Some(Arc::new(10))));
+
+    tx.send((3, Arc::new(30))).await.unwrap();
+
+    assert_eq!(last.recv().await, Ok(Some(Arc::new(30))));
+    assert!(!handle.is_finished());
+
+    drop(tx);
+
+    let _ = handle.await.unwrap();
+
+    assert_eq!(missing.recv().await, Ok(None));
+  }
+
+  static REQUEST_COUNTS: Mutex<Vec<usize>> = Mutex::new(vec![]);
+
+  pub struct CountingLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for CountingLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      let request_count = task.request_count();
+
+      REQUEST_COUNTS.lock().unwrap().push(request_count);
+
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let data: HashMap<i32, Arc<i32>> = task
+            .keys()
+            .into_iter()
+            .map(|key| (key, Arc::new(key)))
+            .collect();
+
+          task.resolve(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_counts_pending_requests() {
+    let loader: DataLoader<CountingLoader> = DataLoader::default();
+
+    let receivers: Vec<_> = vec![1, 2, 2]
+      .into_iter()
+      .map(|key| loader.load_by(key))
+      .collect();
+
+    for (rx, key) in receivers.into_iter().zip(vec![1, 2, 2]) {
+      assert_eq!(rx.recv().await, Ok(Some(Arc::new(key))));
+    }
+
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      vec![4, 5].into_iter().map(Request::new_oneshot).unzip();
+
+    let owned = Task::new(Stealer::Owner(requests));
+
+    assert_eq!(owned.request_count(), 2);
+
+    let _ = CountingLoader::handle_task(owned).await;
+
+    // An empty queue is counted as such, and yields no assignment
+    let _ = CountingLoader::handle_task(Task::new(Stealer::Owner(vec![]))).await;
+
+    assert_eq!(*REQUEST_COUNTS.lock().unwrap(), vec![3, 2, 0]);
+  }
+
+  // Rejects odd keys, and batches including key 0
+  pub struct ValidatedLoader;
+
+  impl MockBackend for ValidatedLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn validate_key(key: &i32) -> Result<(), TestError> {
+      if key % 2 == 0 {
+        Ok(())
+      } else {
+        Err(TestError("odd"))
+      }
+    }
+
+    fn validate_batch(keys: &[i32]) -> Result<(), TestError> {
+      if keys.contains(&0) {
+        Err(TestError("contains zero"))
+      } else {
+        Ok(())
+      }
+    }
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_rejects_invalid_keys_and_batches() {
+    let recorder = BatchRecorder::<ValidatedLoader>::start();
+    let loader: DataLoader<MockHandler<ValidatedLoader>> = DataLoader::default();
+
+    let receivers: Vec<_> = vec![1, 2, 4, 1]
+      .into_iter()
+      .map(|key| loader.load_by(key))
+      .collect();
+
+    let mut results = vec![];
+
+    for rx in receivers {
+      results.push(rx.recv().await);
+    }
+
+    assert_eq!(
+      results,
+      vec![
+        Err(TestError("odd")),
+        Ok(Some(Arc::new(2))),
+        Ok(Some(Arc::new(4))),
+        Err(TestError("odd"))
+      ]
+    );
+
+    let (zero, odd) = (loader.load_by(0), loader.load_by(3));
+
+    assert_eq!(zero.recv().await, Err(TestError("contains zero")));
+    assert_eq!(odd.recv().await, Err(TestError("odd")));
+
+    // Neither the invalid keys nor the invalid batch were loaded
+    assert_eq!(recorder.batches(), vec![vec![2, 4]]);
+  }
+
+  static HUNG: AtomicBool = AtomicBool::new(false);
+
+  pub struct HangingLoader;
+
+  #[async_trait::async_trait]
+  impl TaskHandler for HangingLoader {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      // Only the first handler hangs, as if acquiring a connection from an exhausted pool
+      if !HUNG.swap(true, Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+      }
+
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let data = task.keys().into_iter().map(|key| (key, Arc::new(key)));
+
+          task.resolve_pairs(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_cancels_requests_upon_startup_timeout() {
+    let loader: DataLoader<HangingLoader> = DataLoader::default();
+    loader.set_worker_startup_timeout(Some(Duration::from_millis(20)));
+
+    let cache = ContextCache::new();
+    let start = std::time::Instant::now();
+
+    let cached = loader.cached_load_by(3, &cache);
+
+    assert_eq!(loader.load_by(1).try_recv().await, Err(RecvCancelled));
+    assert_eq!(cached.try_recv().await, Err(RecvCancelled));
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // The queue of the abandoned handler isn't left stalled
+    assert_eq!(loader.load_by(2).recv().await, Ok(Some(Arc::new(2))));
+
+    // Cancelled entries are evicted rather than handing out receivers already cancelled
+    assert_eq!(
+      loader.cached_load_by(3, &cache).recv().await,
+      Ok(Some(Arc::new(3)))
+    );
+  }
+
+  #[cfg(feature = "tracing")]
+  static HANDLER_SPAN: Mutex<Option<tracing::Id>> = Mutex::new(None);
+
+  #[cfg(feature = "tracing")]
+  pub struct TracedLoader;
+
+  #[cfg(feature = "tracing")]
+  #[async_trait::async_trait]
+  impl TaskHandler for TracedLoader {
+    type Key = i32;
+    type Value = Option<tracing::Id>;
+    type Error = TestError;
+
+    async fn handle_task(
+      task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+    ) -> Task<CompletionReceipt> {
+      *HANDLER_SPAN.lock().unwrap() = tracing::Span::current().id();
+
+      match task.get_assignment::<Self>().await {
+        TaskAssignment::LoadBatch(task) => {
+          let data: HashMap<i32, Arc<Option<tracing::Id>>> = task
+            .keys()
+            .into_iter()
+            .map(|key| (key, Arc::new(None)))
+            .collect();
+
+          task.resolve(Ok(data))
+        }
+        TaskAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[cfg(feature = "tracing")]
+  #[tokio::test]
+  async fn it_propagates_span_context() {
+    let subscriber = tracing_subscriber::fmt()
+      .with_writer(tracing_subscriber::fmt::TestWriter::new())
+      .finish();
+
+    let _ = tracing::subscriber::set_global_default(subscriber);
+
+    let span = tracing::info_span!("load");
+    let resolved_in: Arc<Mutex<Option<tracing::Id>>> = Arc::new(Mutex::new(None));
+
+    let (mut req, rx) = Request::<i32, Option<tracing::Id>, TestError>::new_oneshot(1);
+
+    let resolved = resolved_in.clone();
+    req.set_cache_cb(Arc::new(move |_, _| {
+      *resolved.lock().unwrap() = tracing::Span::current().id();
+    }));
+
+    let loader: DataLoader<TracedLoader> = DataLoader::default();
+
+    span.in_scope(|| {
+      let task = Task::from_requests(vec![req]);
+      let _ = task.resolve(Ok(HashMap::from([(1, Arc::new(None))])));
+    });
+
+    rx.recv().await.unwrap();
+
+    let rx = span.in_scope(|| loader.load_by(2));
+
+    rx.recv().await.unwrap();
+
+    assert!(span.id().is_some());
+    assert_eq!(*resolved_in.lock().unwrap(), span.id());
+    assert_eq!(*HANDLER_SPAN.lock().unwrap(), span.id());
+  }
+
+  #[cfg(debug_assertions)]
+  #[tokio::test]
+  #[should_panic(expected = "CompletionReceipt dropped with 5 unresolved requests")]
+  async fn it_panics_on_unresolved_shard() {
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..10).map(Request::new_oneshot).unzip();
+
+    let mut shards = Task::from_requests(requests).split_by_shard(|key| key % 2);
+
+    let receipt = shards.remove(0).resolve(Err(TestError("unavailable")));
+
+    drop(shards);
+    drop(receipt);
+  }
+
+  #[cfg(debug_assertions)]
+  #[tokio::test]
+  async fn it_checks_receipts_once_every_shard_is_resolved() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..10).map(Request::new_oneshot).unzip();
+
+    let shards = Task::from_requests(requests).split_by_shard(|key| key % 3);
+
+    // Receipts of shards resolved earlier are dropped while later shards remain unresolved
+    let receipt = shards
+      .into_iter()
+      .fold(Task::completion_receipt(), |_, shard| {
+        shard.resolve(Err(TestError("unavailable")))
+      });
+
+    drop(receipt);
+
+    for rx in receivers {
+      assert_eq!(rx.recv().await, Err(TestError("unavailable")));
+    }
+  }
+
+  #[tokio::test]
+  async fn it_cancels_requests_of_dropped_batches() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..3).map(Request::new_oneshot).unzip();
+
+    let batch = Task::from_requests(requests);
+
+    // Dropped without producing a receipt, such as when the future of a task handler is dropped
+    drop(batch);
+
+    for rx in receivers {
+      assert_eq!(rx.try_recv().await, Err(RecvCancelled));
+    }
+  }
+
+  #[cfg(debug_assertions)]
+  #[tokio::test]
+  async fn it_doesnt_panic_on_requests_handed_to_another_batch() {
+    let (requests, receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..4).map(Request::new_oneshot).unzip();
+
+    let mut shards = Task::from_requests(requests).split_by_shard(|key| key % 2);
+    let handed_off = shards.pop().unwrap().into_requests();
+
+    let receipt = shards.remove(0).resolve(Err(TestError("unavailable")));
+    drop(receipt);
+
+    let receipt = Task::from_requests(handed_off).resolve(Err(TestError("unavailable")));
+    drop(receipt);
+
+    for rx in receivers {
+      assert_eq!(rx.recv().await, Err(TestError("unavailable")));
+    }
+  }
+
+  #[cfg(debug_assertions)]
+  #[test]
+  #[should_panic(expected = "handler failed")]
+  fn it_doesnt_panic_on_unresolved_batch_while_unwinding() {
+    let (requests, _receivers): (Vec<Request<i32, i32, TestError>>, Vec<_>) =
+      (0..4).map(Request::new_oneshot).unzip();
+
+    let mut shards = Task::from_requests(requests).split_by_shard(|key| key % 2);
+
+    let _receipt = shards.remove(0).finish();
+    let _unresolved = shards;
+
+    panic!("handler failed");
   }
 }
@@ -0,0 +1,732 @@
+//! Utilities for verifying batching behavior under simulated concurrency
+//!
+//! ```rust
+//! #[derive(Loader)]
+//! #[data_loader(handler = "MockHandler<UserBackend>")]
+//! pub struct UserBackend;
+//!
+//! impl MockBackend for UserBackend {
+//!   type Key = i32;
+//!   type Value = i32;
+//!   type Error = ();
+//!
+//!   fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, ()> {
+//!     Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+//!   }
+//! }
+//!
+//! #[tokio::test]
+//! async fn it_batches() {
+//!   TestHarness::<UserBackend>::new((0..100).collect())
+//!     .tasks(50)
+//!     .loads_per_task(4)
+//!     .inject_delay(Duration::from_millis(10))
+//!     .run()
+//!     .await
+//!     .assert_batch_count_at_most(8)
+//!     .assert_avg_batch_size_gt(25.0);
+//! }
+//! ```
+
+use crate::{
+  loader::{DataLoader, DataStore, LocalLoader, StoreType},
+  request::LoadResult,
+  task::{
+    task_handler_defaults, CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskError,
+    TaskHandler,
+  },
+  Key,
+};
+use std::{
+  any::{Any, TypeId},
+  collections::HashMap,
+  future::Future,
+  marker::PhantomData,
+  sync::{Arc, Mutex, OnceLock},
+  time::Duration,
+};
+
+/// A synchronous in-memory backend driven by [`MockHandler`]
+#[async_trait::async_trait]
+pub trait MockBackend: Sized + Send + Sync + 'static {
+  type Key: Key;
+  type Value: Send + Sync + Clone + 'static;
+  type Error: TaskError;
+  task_handler_defaults!(Self::Error);
+
+  /// Awaited upon assignment of each batch ahead of [`MockBackend::load`], such as to simulate backend latency or to hold a batch in flight
+  async fn before_load(_keys: &[Self::Key]) {}
+
+  #[allow(clippy::type_complexity)]
+  fn load(keys: &[Self::Key]) -> Result<HashMap<Self::Key, Arc<Self::Value>>, Self::Error>;
+}
+
+#[cfg(feature = "testing-insta")]
+#[doc(hidden)]
+pub use insta;
+
+/// An error for handlers under test, described by a static message. Unlike `()` or `&'static str` this implements [`std::error::Error`], and so satisfies [`TaskError`] with the `std-error` feature enabled
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[error("{0}")]
+pub struct TestError(pub &'static str);
+
+#[derive(Default)]
+struct BackendState {
+  batch_sizes: Vec<usize>,
+  delay: Option<Duration>,
+  recorded: Option<Vec<Box<dyn Any + Send>>>,
+}
+
+static BACKEND_STATE: OnceLock<Mutex<HashMap<TypeId, BackendState>>> = OnceLock::new();
+
+fn with_backend_state<T, F, R>(f: F) -> R
+where
+  T: MockBackend,
+  F: FnOnce(&mut BackendState) -> R,
+{
+  let mut state = BACKEND_STATE.get_or_init(Default::default).lock().unwrap();
+  f(state.entry(TypeId::of::<T>()).or_default())
+}
+
+/// A [`TaskHandler`] that records the size of every batch received by a [`MockBackend`]
+pub struct MockHandler<T: MockBackend>(T);
+
+#[async_trait::async_trait]
+impl<T> TaskHandler for MockHandler<T>
+where
+  T: MockBackend,
+{
+  type Key = T::Key;
+  type Value = T::Value;
+  type Error = T::Error;
+  crate::forward_task_handler!(T);
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    // Injected delay simulates connection acquisition, during which loads continue to be enqueued
+    if let Some(delay) = with_backend_state::<T, _, _>(|state| state.delay) {
+      tokio::time::sleep(delay).await;
+    }
+
+    let assignment = match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.validate::<Self>(),
+      assignment => assignment,
+    };
+
+    match assignment {
+      TaskAssignment::LoadBatch(task) => {
+        let keys = task.keys();
+
+        with_backend_state::<T, _, _>(|state| {
+          state.batch_sizes.push(keys.len());
+
+          if let Some(recorded) = state.recorded.as_mut() {
+            let mut keys = keys.clone();
+            keys.sort();
+            recorded.push(Box::new(keys));
+          }
+        });
+
+        T::before_load(&keys).await;
+
+        // Resolved on the runtime thread so that waiters are woken before a paused clock can auto-advance
+        task.resolve_inline(T::load(&keys))
+      }
+      TaskAssignment::NoAssignment(receipt) => receipt,
+    }
+  }
+}
+
+impl<Loader, Store> LocalLoader<Store> for MockHandler<Loader>
+where
+  Loader: MockBackend + LocalLoader<Store>,
+  Store: StoreType,
+{
+  type Handler = <Loader as LocalLoader<Store>>::Handler;
+  fn loader() -> &'static std::thread::LocalKey<DataLoader<Self::Handler>> {
+    Loader::loader()
+  }
+}
+
+/// Records the keys of every batch received by a [`MockBackend`] from the time recording starts until the recorder is dropped. Keys within each batch are sorted so that recordings are deterministic and can be compared against stored snapshots with [`assert_batch_snapshot`](crate::assert_batch_snapshot)
+///
+/// ```rust
+/// #[tokio::test]
+/// async fn it_deduplicates_keys() {
+///   let recorder = BatchRecorder::<UserBackend>::start();
+///
+///   let (a, b) = tokio::join!(User::load_by(1), User::load_by(1));
+///
+///   assert_eq!(recorder.batches(), vec![vec![1]]);
+/// }
+/// ```
+pub struct BatchRecorder<T: MockBackend> {
+  backend: PhantomData<fn() -> T>,
+}
+
+impl<T> BatchRecorder<T>
+where
+  T: MockBackend,
+{
+  pub fn start() -> Self {
+    with_backend_state::<T, _, _>(|state| state.recorded = Some(vec![]));
+
+    BatchRecorder {
+      backend: PhantomData,
+    }
+  }
+
+  /// Have the handler sleep for `delay` prior to task assignment until the recorder is dropped, such that batches dispatch once `delay` elapses
+  pub fn inject_delay(self, delay: Duration) -> Self {
+    with_backend_state::<T, _, _>(|state| state.delay = Some(delay));
+    self
+  }
+
+  /// Sorted keys of each batch in the order batches were received
+  pub fn batches(&self) -> Vec<Vec<T::Key>> {
+    with_backend_state::<T, _, _>(|state| {
+      state
+        .recorded
+        .iter()
+        .flatten()
+        .filter_map(|keys| keys.downcast_ref::<Vec<T::Key>>().cloned())
+        .collect()
+    })
+  }
+}
+
+impl<T> Drop for BatchRecorder<T>
+where
+  T: MockBackend,
+{
+  fn drop(&mut self) {
+    with_backend_state::<T, _, _>(|state| {
+      state.recorded = None;
+      state.delay = None;
+    });
+  }
+}
+
+/// Run `f` with the tokio clock paused so that batch timers, such as delays injected into a [`MockHandler`], elapse deterministically via [`advance_batch_timer`] rather than in real time. While paused, the clock also auto-advances to the next timer whenever the runtime has no other work. Requires a current thread runtime
+///
+/// ```rust
+/// #[tokio::test]
+/// async fn it_dispatches_after_delay() {
+///   run_with_paused_time(async {
+///     let recorder = BatchRecorder::<UserBackend>::start().inject_delay(Duration::from_millis(10));
+///     let rx = UserBackend::loader().with(|loader| loader.load_by(1));
+///
+///     advance_batch_timer(5).await;
+///     assert!(recorder.batches().is_empty());
+///
+///     advance_batch_timer(6).await;
+///     rx.recv().await.unwrap();
+///     assert_eq!(recorder.batches(), vec![vec![1]]);
+///   })
+///   .await;
+/// }
+/// ```
+pub async fn run_with_paused_time<F: Future>(f: F) -> F::Output {
+  tokio::time::pause();
+
+  let output = f.await;
+
+  tokio::time::resume();
+
+  output
+}
+
+/// Advance the paused clock of [`run_with_paused_time`] by `ms` milliseconds, firing batch timers that elapse in the meantime. Timers have millisecond resolution and fire upon the first tick after their deadline, so advance one millisecond past a deadline to ensure it fires
+pub async fn advance_batch_timer(ms: u64) {
+  tokio::time::advance(Duration::from_millis(ms)).await;
+}
+
+/// Compare the batches of a [`BatchRecorder`] against a stored [`insta`] snapshot. As with [`insta::assert_debug_snapshot`], snapshots are stored relative to the calling test
+///
+/// ```rust
+/// #[loader_snapshot_test(UserBackend)]
+/// async fn it_batches_users() {
+///   tokio::join!(User::load_by(1), User::load_by(2));
+/// }
+///
+/// // Expands to
+/// #[tokio::test]
+/// async fn it_batches_users() {
+///   let recorder = deque_loader::testing::BatchRecorder::<UserBackend>::start();
+///   async move { tokio::join!(User::load_by(1), User::load_by(2)); }.await;
+///   deque_loader::assert_batch_snapshot!(recorder, "it_batches_users");
+/// }
+/// ```
+#[cfg(feature = "testing-insta")]
+#[macro_export]
+macro_rules! assert_batch_snapshot {
+  ($recorder:expr, $name:expr) => {
+    $crate::testing::insta::assert_debug_snapshot!($name, $recorder.batches())
+  };
+}
+
+/// Spawns concurrent tasks making loads against a [`MockBackend`] and records the batches the backend received. Time is paused for the duration of the run so that injected delays are deterministic and don't depend on wall-clock scheduling; this requires a current thread runtime
+pub struct TestHarness<T: MockBackend> {
+  keys: Vec<T::Key>,
+  tasks: usize,
+  loads_per_task: usize,
+  delay: Option<Duration>,
+  seed: u64,
+  backend: PhantomData<fn() -> T>,
+}
+
+impl<T> TestHarness<T>
+where
+  T: MockBackend + LocalLoader<DataStore, Handler = MockHandler<T>>,
+{
+  /// Create a harness that loads keys sampled from `keys`
+  pub fn new(keys: Vec<T::Key>) -> Self {
+    assert!(!keys.is_empty(), "TestHarness requires at least one key");
+
+    TestHarness {
+      keys,
+      tasks: 1,
+      loads_per_task: 1,
+      delay: None,
+      seed: 0x2545_f491_4f6c_dd1d,
+      backend: PhantomData,
+    }
+  }
+
+  /// Number of concurrently spawned tasks
+  pub fn tasks(mut self, tasks: usize) -> Self {
+    self.tasks = tasks;
+    self
+  }
+
+  /// Number of sequential loads each task makes
+  pub fn loads_per_task(mut self, loads_per_task: usize) -> Self {
+    self.loads_per_task = loads_per_task;
+    self
+  }
+
+  /// Have the handler sleep for `delay` prior to task assignment, maximizing the opportunity for loads to coalesce
+  pub fn inject_delay(mut self, delay: Duration) -> Self {
+    self.delay = Some(delay);
+    self
+  }
+
+  /// Seed for the pseudo-random key selection
+  pub fn seed(mut self, seed: u64) -> Self {
+    self.seed = seed;
+    self
+  }
+
+  pub async fn run(self) -> HarnessReport {
+    let TestHarness {
+      keys,
+      tasks,
+      loads_per_task,
+      delay,
+      seed,
+      ..
+    } = self;
+
+    with_backend_state::<T, _, _>(|state| {
+      state.batch_sizes.clear();
+      state.delay = delay;
+    });
+
+    let keys = Arc::new(keys);
+
+    run_with_paused_time(async move {
+      let handles = (0..tasks).map(|i| {
+        let keys = keys.clone();
+        let mut rng = XorShift::new(seed.wrapping_add(i as u64));
+
+        tokio::task::spawn(async move {
+          for _ in 0..loads_per_task {
+            let key = keys[rng.next() as usize % keys.len()].to_owned();
+            let rx = T::loader().with(|loader| loader.load_by(key));
+            rx.recv().await.ok();
+          }
+        })
+      });
+
+      for result in futures_util::future::join_all(handles).await {
+        result.expect("load task panicked");
+      }
+    })
+    .await;
+
+    let batch_sizes = with_backend_state::<T, _, _>(|state| {
+      state.delay = None;
+      std::mem::take(&mut state.batch_sizes)
+    });
+
+    HarnessReport { batch_sizes }
+  }
+}
+
+/// Batches received by the backend during a [`TestHarness`] run
+#[derive(Debug, Clone)]
+pub struct HarnessReport {
+  batch_sizes: Vec<usize>,
+}
+
+impl HarnessReport {
+  /// Number of unique keys within each batch in the order batches were received
+  pub fn batch_sizes(&self) -> &[usize] {
+    &self.batch_sizes
+  }
+
+  pub fn batch_count(&self) -> usize {
+    self.batch_sizes.len()
+  }
+
+  pub fn avg_batch_size(&self) -> f64 {
+    if self.batch_sizes.is_empty() {
+      0.0
+    } else {
+      self.batch_sizes.iter().sum::<usize>() as f64 / self.batch_sizes.len() as f64
+    }
+  }
+
+  pub fn assert_batch_count_at_most(&self, max: usize) -> &Self {
+    assert!(
+      self.batch_count().le(&max),
+      "expected at most {} batches, received {}: {:?}",
+      max,
+      self.batch_count(),
+      self.batch_sizes
+    );
+    self
+  }
+
+  pub fn assert_avg_batch_size_gt(&self, min: f64) -> &Self {
+    assert!(
+      self.avg_batch_size() > min,
+      "expected an average batch size greater than {}, received {}: {:?}",
+      min,
+      self.avg_batch_size(),
+      self.batch_sizes
+    );
+    self
+  }
+}
+
+/// An in-memory stand-in for a loader with pre-programmed responses, for unit testing code that loads without a backend or [`TaskHandler`]. Clones share responses and call counts, such that a clone can be moved into each handler under test
+///
+/// ```rust
+/// async fn display_name(loader: &FakeDataLoader<i32, User, ApiError>, user_id: i32) -> String {
+///   match loader.load(user_id).await {
+///     Ok(Some(user)) => user.name.clone(),
+///     Ok(None) => String::from("unknown"),
+///     Err(_) => String::from("unavailable"),
+///   }
+/// }
+///
+/// #[tokio::test]
+/// async fn it_displays_names() {
+///   let loader = FakeDataLoader::new().with_delay(Duration::from_millis(5));
+///
+///   loader.set(1, User { name: "Ada".into() });
+///   loader.set_error(2, ApiError::Unavailable);
+///
+///   assert_eq!(display_name(&loader, 1).await, "Ada");
+///   assert_eq!(display_name(&loader, 2).await, "unavailable");
+///   assert_eq!(display_name(&loader, 3).await, "unknown");
+///   assert_eq!(loader.call_count(&1), 1);
+/// }
+/// ```
+pub struct FakeDataLoader<K: Key, V, E> {
+  responses: Arc<Mutex<HashMap<K, LoadResult<V, E>>>>,
+  calls: Arc<Mutex<HashMap<K, usize>>>,
+  delay: Option<Duration>,
+}
+
+impl<K, V, E> FakeDataLoader<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+  E: TaskError,
+{
+  pub fn new() -> Self {
+    FakeDataLoader {
+      responses: Arc::new(Mutex::new(HashMap::new())),
+      calls: Arc::new(Mutex::new(HashMap::new())),
+      delay: None,
+    }
+  }
+
+  /// Sleep for `delay` upon every load to simulate backend latency
+  pub fn with_delay(mut self, delay: Duration) -> Self {
+    self.delay = Some(delay);
+    self
+  }
+
+  pub fn set(&self, key: K, value: V) {
+    self
+      .responses
+      .lock()
+      .unwrap()
+      .insert(key, Ok(Some(Arc::new(value))));
+  }
+
+  pub fn set_error(&self, key: K, error: E) {
+    self.responses.lock().unwrap().insert(key, Err(error));
+  }
+
+  /// The pre-programmed response of `key`, or `Ok(None)` for keys without a response
+  pub async fn load(&self, key: K) -> Result<Option<Arc<V>>, E> {
+    *self.calls.lock().unwrap().entry(key.clone()).or_default() += 1;
+
+    if let Some(delay) = self.delay {
+      tokio::time::sleep(delay).await;
+    }
+
+    self
+      .responses
+      .lock()
+      .unwrap()
+      .get(&key)
+      .cloned()
+      .unwrap_or(Ok(None))
+  }
+
+  /// Number of times `key` was loaded, across every clone
+  pub fn call_count(&self, key: &K) -> usize {
+    self.calls.lock().unwrap().get(key).copied().unwrap_or(0)
+  }
+}
+
+impl<K, V, E> Clone for FakeDataLoader<K, V, E>
+where
+  K: Key,
+{
+  fn clone(&self) -> Self {
+    FakeDataLoader {
+      responses: self.responses.clone(),
+      calls: self.calls.clone(),
+      delay: self.delay,
+    }
+  }
+}
+
+impl<K, V, E> Default for FakeDataLoader<K, V, E>
+where
+  K: Key,
+  V: Send + Sync + 'static,
+  E: TaskError,
+{
+  fn default() -> Self {
+    FakeDataLoader::new()
+  }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+  fn new(seed: u64) -> Self {
+    XorShift(seed.max(1))
+  }
+
+  fn next(&mut self) -> u64 {
+    let mut x = self.0;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    self.0 = x;
+    x
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use deque_loader_derive::Loader;
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<EchoBackend>")]
+  pub struct EchoBackend;
+
+  impl MockBackend for EchoBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<RecordedBackend>")]
+  pub struct RecordedBackend;
+
+  impl MockBackend for RecordedBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(10);
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<DelayedBackend>")]
+  pub struct DelayedBackend;
+
+  impl MockBackend for DelayedBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<BoundedBackend>")]
+  pub struct BoundedBackend;
+
+  impl MockBackend for BoundedBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(10);
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[derive(Loader)]
+  #[data_loader(handler = "MockHandler<TimedBackend>")]
+  pub struct TimedBackend;
+
+  impl MockBackend for TimedBackend {
+    type Key = i32;
+    type Value = i32;
+    type Error = TestError;
+
+    fn load(keys: &[i32]) -> Result<HashMap<i32, Arc<i32>>, TestError> {
+      Ok(keys.iter().map(|key| (*key, Arc::new(*key))).collect())
+    }
+  }
+
+  #[tokio::test]
+  async fn it_dispatches_upon_the_batch_deadline() {
+    run_with_paused_time(async {
+      let recorder = BatchRecorder::<TimedBackend>::start().inject_delay(Duration::from_millis(10));
+      let start = tokio::time::Instant::now();
+
+      let receivers: Vec<_> =
+        TimedBackend::loader().with(|loader| (0..3).map(|key| loader.load_by(key)).collect());
+
+      // Let the handler defer by priority and begin sleeping at the current instant
+      for _ in 0..3 {
+        tokio::task::yield_now().await;
+      }
+
+      advance_batch_timer(9).await;
+      assert!(recorder.batches().is_empty());
+
+      advance_batch_timer(2).await;
+
+      for (key, rx) in receivers.into_iter().enumerate() {
+        assert_eq!(rx.recv().await, Ok(Some(Arc::new(key as i32))));
+      }
+
+      assert_eq!(start.elapsed(), Duration::from_millis(11));
+      assert_eq!(recorder.batches(), vec![vec![0, 1, 2]]);
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn it_records_batches() {
+    let recorder = BatchRecorder::<RecordedBackend>::start();
+
+    let receivers: Vec<_> = RecordedBackend::loader().with(|loader| {
+      [3, 1, 3, 2]
+        .iter()
+        .map(|key| loader.load_by(*key))
+        .collect()
+    });
+
+    futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+
+    assert_eq!(recorder.batches(), vec![vec![1, 2, 3]]);
+  }
+
+  #[cfg(feature = "testing-insta")]
+  #[deque_loader_derive::loader_snapshot_test(RecordedBackend)]
+  async fn it_matches_batch_snapshot() {
+    let receivers: Vec<_> = RecordedBackend::loader()
+      .with(|loader| (0..15).rev().map(|key| loader.load_by(key)).collect());
+
+    futures_util::future::join_all(receivers.into_iter().map(|rx| rx.recv())).await;
+  }
+
+  #[tokio::test]
+  async fn it_batches_concurrent_loads() {
+    TestHarness::<EchoBackend>::new((0..1000).collect())
+      .tasks(100)
+      .loads_per_task(1)
+      .run()
+      .await
+      .assert_batch_count_at_most(1)
+      .assert_avg_batch_size_gt(50.0);
+  }
+
+  #[tokio::test]
+  async fn it_coalesces_during_injected_delay() {
+    TestHarness::<DelayedBackend>::new((0..1000).collect())
+      .tasks(100)
+      .loads_per_task(5)
+      .inject_delay(Duration::from_millis(50))
+      .run()
+      .await
+      .assert_batch_count_at_most(5)
+      .assert_avg_batch_size_gt(50.0);
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn it_returns_programmed_responses() {
+    let loader: FakeDataLoader<i32, i32, TestError> =
+      FakeDataLoader::new().with_delay(Duration::from_millis(20));
+
+    loader.set(1, 10);
+    loader.set_error(2, TestError("unavailable"));
+
+    let handler = loader.clone();
+    let start = tokio::time::Instant::now();
+
+    assert_eq!(handler.load(1).await, Ok(Some(Arc::new(10))));
+    assert_eq!(handler.load(2).await, Err(TestError("unavailable")));
+    assert_eq!(loader.load(3).await, Ok(None));
+    assert_eq!(loader.load(1).await, Ok(Some(Arc::new(10))));
+
+    assert_eq!(start.elapsed(), Duration::from_millis(80));
+    assert_eq!(loader.call_count(&1), 2);
+    assert_eq!(handler.call_count(&2), 1);
+    assert_eq!(loader.call_count(&4), 0);
+  }
+
+  #[tokio::test]
+  async fn it_respects_max_batch_size() {
+    let report = TestHarness::<BoundedBackend>::new((0..1000).collect())
+      .tasks(50)
+      .loads_per_task(2)
+      .inject_delay(Duration::from_millis(10))
+      .run()
+      .await;
+
+    report
+      .assert_batch_count_at_most(12)
+      .assert_avg_batch_size_gt(5.0);
+
+    assert!(report.batch_sizes().iter().all(|size| size.le(&10)));
+  }
+}
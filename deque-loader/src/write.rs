@@ -0,0 +1,311 @@
+//! Batched writes, coalescing writes queued concurrently into a single backend write such as a bulk insert, with the same type-state flow from assignment to completion as [`crate::task::Task`]
+//!
+//! ```rust
+//! pub struct UserWriter;
+//!
+//! #[async_trait::async_trait]
+//! impl WriteTaskHandler for UserWriter {
+//!   type Key = String;
+//!   type Value = NewUser;
+//!   type Ack = i32;
+//!   type Error = SimpleDieselError;
+//!   const MAX_BATCH_SIZE: Option<usize> = Some(1000);
+//!
+//!   async fn handle_write_task(task: WriteTask<PendingWrites<Self>>) -> WriteTask<WriteReceipt> {
+//!     let conn = match get_connection() {
+//!       Ok(conn) => conn,
+//!       Err(err) => return task.resolve(Err(err.into())).await,
+//!     };
+//!
+//!     match task.get_assignment().await {
+//!       WriteAssignment::WriteBatch(batch) => {
+//!         let rows: Vec<&NewUser> = batch.writes().map(|(_, user)| user).collect();
+//!
+//!         let inserted: Result<Vec<(String, i32)>, DieselError> = diesel::insert_into(users::table)
+//!           .values(rows)
+//!           .returning((users::email, users::id))
+//!           .get_results(&conn)
+//!           .map_err(DieselError::from);
+//!
+//!         batch.resolve(
+//!           inserted
+//!             .map(|ids| ids.into_iter().collect())
+//!             .map_err(SimpleDieselError::from),
+//!         )
+//!       }
+//!       WriteAssignment::NoAssignment(receipt) => receipt,
+//!     }
+//!   }
+//! }
+//!
+//! let writer: DataWriter<UserWriter> = DataWriter::default();
+//!
+//! let id = writer.write(new_user.email.clone(), new_user).await?;
+//! ```
+use crate::{request::RecvCancelled, task::TaskError, Key};
+use std::{collections::HashMap, future::Future, marker::PhantomData};
+use swap_queue::{Stealer, Worker};
+use tokio::sync::oneshot;
+
+/// A handler of batched writes; the write counterpart of [`crate::task::TaskHandler`]
+#[async_trait::async_trait]
+pub trait WriteTaskHandler: Sized + Send + Sync + 'static {
+  type Key: Key;
+  type Value: Send + Sync + 'static;
+  /// The acknowledgement of a write, such as a generated ID
+  type Ack: Send + Sync + Clone + 'static;
+  type Error: TaskError;
+  /// Upper bound on the number of writes within a batch, beyond which writes are split off to be handled separately
+  const MAX_BATCH_SIZE: Option<usize> = None;
+  async fn handle_write_task(task: WriteTask<PendingWrites<Self>>) -> WriteTask<WriteReceipt>;
+}
+
+pub struct WriteTask<T>(pub(crate) T);
+
+pub(crate) struct WriteRequest<T: WriteTaskHandler> {
+  key: T::Key,
+  value: T::Value,
+  tx: oneshot::Sender<Result<T::Ack, T::Error>>,
+}
+
+/// A handle for deferred assignment of queued writes via work-stealing, such that writes continue to coalesce until connection acquisition
+pub struct PendingWrites<T: WriteTaskHandler> {
+  stealer: Stealer<WriteRequest<T>>,
+}
+
+/// A batch of writes in the order queued. Every write is retained, including writes of the same key
+pub struct WriteBatch<T: WriteTaskHandler> {
+  requests: Vec<WriteRequest<T>>,
+}
+
+/// An acknowledgement of write task completion; see [`crate::task::CompletionReceipt`]
+pub struct WriteReceipt(PhantomData<fn() -> ()>);
+
+pub enum WriteAssignment<T: WriteTaskHandler> {
+  WriteBatch(WriteTask<WriteBatch<T>>),
+  /// Other write handlers opportunistically took every queued write
+  NoAssignment(WriteTask<WriteReceipt>),
+}
+
+impl WriteTask<WriteReceipt> {
+  #[must_use]
+  pub fn completion_receipt() -> Self {
+    WriteTask(WriteReceipt(PhantomData))
+  }
+}
+
+impl<T> WriteTask<PendingWrites<T>>
+where
+  T: WriteTaskHandler,
+{
+  // Work-steal all queued writes, splitting off writes in excess of [`WriteTaskHandler::MAX_BATCH_SIZE`] to be handled separately
+  pub async fn get_assignment(self) -> WriteAssignment<T> {
+    let mut requests = self.0.stealer.take().await;
+
+    if requests.is_empty() {
+      return WriteAssignment::NoAssignment(WriteTask::completion_receipt());
+    }
+
+    if let Some(max_batch_size) = T::MAX_BATCH_SIZE {
+      let max_batch_size = max_batch_size.max(1);
+
+      if requests.len() > max_batch_size {
+        let task = WriteTask(PendingWrites {
+          stealer: Stealer::Owner(requests.split_off(max_batch_size)),
+        });
+
+        tokio::task::spawn(async move {
+          T::handle_write_task(task).await;
+        });
+      }
+    }
+
+    WriteAssignment::WriteBatch(WriteTask(WriteBatch { requests }))
+  }
+
+  /// Resolve every queued write as `result` without taking an assignment, such as upon failing to acquire a connection
+  pub async fn resolve(self, result: Result<T::Ack, T::Error>) -> WriteTask<WriteReceipt> {
+    for req in self.0.stealer.take().await {
+      req.tx.send(result.clone()).ok();
+    }
+
+    WriteTask::completion_receipt()
+  }
+}
+
+impl<T> WriteTask<WriteBatch<T>>
+where
+  T: WriteTaskHandler,
+{
+  /// The key and value of every write, in the order queued
+  pub fn writes(&self) -> impl Iterator<Item = (&T::Key, &T::Value)> {
+    self.0.requests.iter().map(|req| (&req.key, &req.value))
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.requests.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.requests.is_empty()
+  }
+
+  /// Acknowledge each write by key, or fail every write of the batch. Writes of keys without an acknowledgement are dropped, failing with [`RecvCancelled`]
+  #[must_use]
+  pub fn resolve(
+    self,
+    results: Result<HashMap<T::Key, T::Ack>, T::Error>,
+  ) -> WriteTask<WriteReceipt> {
+    match results {
+      Ok(acks) => {
+        for req in self.0.requests {
+          if let Some(ack) = acks.get(&req.key) {
+            req.tx.send(Ok(ack.to_owned())).ok();
+          }
+        }
+      }
+      Err(err) => {
+        for req in self.0.requests {
+          req.tx.send(Err(err.clone())).ok();
+        }
+      }
+    }
+
+    WriteTask::completion_receipt()
+  }
+}
+
+/// Queues writes to be coalesced into batches by a [`WriteTaskHandler`]. As with [`crate::loader::DataLoader`], writes are batched per writer and so a writer is typically thread local
+pub struct DataWriter<T: WriteTaskHandler> {
+  queue: Worker<WriteRequest<T>>,
+}
+
+impl<T> DataWriter<T>
+where
+  T: WriteTaskHandler,
+{
+  pub fn new() -> Self {
+    DataWriter {
+      queue: Worker::new(),
+    }
+  }
+
+  /// Queue a write, acknowledged once written as part of a batch. Panics if the handler dropped the write without resolving it; prefer [`DataWriter::try_write`] in production code
+  pub fn write(
+    &self,
+    key: T::Key,
+    value: T::Value,
+  ) -> impl Future<Output = Result<T::Ack, T::Error>> + Send + 'static {
+    let rx = self.try_write(key, value);
+
+    async move { rx.await.unwrap() }
+  }
+
+  pub fn try_write(
+    &self,
+    key: T::Key,
+    value: T::Value,
+  ) -> impl Future<Output = Result<Result<T::Ack, T::Error>, RecvCancelled>> + Send + 'static {
+    let (tx, rx) = oneshot::channel();
+
+    if let Some(stealer) = self.queue.push(WriteRequest { key, value, tx }) {
+      let task = WriteTask(PendingWrites { stealer });
+
+      tokio::task::spawn(async move {
+        T::handle_write_task(task).await;
+      });
+    }
+
+    async move { rx.await.map_err(|_| RecvCancelled) }
+  }
+}
+
+impl<T> Default for DataWriter<T>
+where
+  T: WriteTaskHandler,
+{
+  fn default() -> Self {
+    DataWriter::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestError;
+  use futures_util::future::join_all;
+  use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Mutex,
+  };
+
+  static BATCH_SIZES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+  static NEXT_ID: AtomicI32 = AtomicI32::new(1);
+
+  pub struct InsertWriter;
+
+  #[async_trait::async_trait]
+  impl WriteTaskHandler for InsertWriter {
+    type Key = &'static str;
+    type Value = i32;
+    type Ack = i32;
+    type Error = TestError;
+    const MAX_BATCH_SIZE: Option<usize> = Some(3);
+
+    async fn handle_write_task(task: WriteTask<PendingWrites<Self>>) -> WriteTask<WriteReceipt> {
+      match task.get_assignment().await {
+        WriteAssignment::WriteBatch(batch) => {
+          BATCH_SIZES.lock().unwrap().push(batch.len());
+
+          if batch.writes().any(|(_, value)| value.is_negative()) {
+            return batch.resolve(Err(TestError("negative value")));
+          }
+
+          let acks = batch
+            .writes()
+            .map(|(key, _)| (*key, NEXT_ID.fetch_add(1, Ordering::SeqCst)))
+            .collect();
+
+          batch.resolve(Ok(acks))
+        }
+        WriteAssignment::NoAssignment(receipt) => receipt,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn it_coalesces_writes() {
+    let writer: DataWriter<InsertWriter> = DataWriter::default();
+
+    let writes: Vec<_> = vec!["a", "b", "c", "d", "e"]
+      .into_iter()
+      .zip(0..)
+      .map(|(key, value)| writer.write(key, value))
+      .collect();
+
+    let mut acks: Vec<i32> = join_all(writes)
+      .await
+      .into_iter()
+      .collect::<Result<_, _>>()
+      .unwrap();
+
+    acks.sort_unstable();
+
+    assert_eq!(acks, vec![1, 2, 3, 4, 5]);
+
+    let mut batch_sizes = BATCH_SIZES.lock().unwrap().clone();
+    batch_sizes.sort_unstable();
+
+    assert_eq!(batch_sizes, vec![2, 3]);
+
+    let writes = vec![writer.write("f", 5), writer.write("g", -1)];
+
+    assert_eq!(
+      join_all(writes).await,
+      vec![
+        Err(TestError("negative value")),
+        Err(TestError("negative value"))
+      ]
+    );
+  }
+}
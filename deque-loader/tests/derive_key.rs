@@ -0,0 +1,44 @@
+use deque_loader::Key;
+use std::collections::HashSet;
+
+#[derive(Key, Debug)]
+pub struct Uuid([u8; 16]);
+
+#[derive(Key, Debug)]
+pub struct Coordinate((i32, i32));
+
+#[derive(Key, Debug)]
+pub struct Digest {
+  bytes: Vec<u8>,
+}
+
+fn assert_key<K: Key>(_: &K) {}
+
+#[test]
+fn it_derives_key_for_inner_types_not_implementing_display() {
+  let uuid = Uuid([7; 16]);
+  let coordinate = Coordinate((1, 2));
+  let digest = Digest {
+    bytes: vec![0xde, 0xad],
+  };
+
+  assert_key(&uuid);
+  assert_key(&coordinate);
+  assert_key(&digest);
+
+  assert!(Uuid([1; 16]) < uuid);
+  assert_eq!(
+    vec![coordinate.clone(), coordinate, Coordinate((2, 1))]
+      .into_iter()
+      .collect::<HashSet<_>>()
+      .len(),
+    2
+  );
+  assert_eq!(digest.clone(), digest);
+}
+
+#[test]
+fn it_rejects_keys_of_unhashable_types() {
+  let t = trybuild::TestCases::new();
+  t.compile_fail("tests/ui/key_requires_bounds.rs");
+}
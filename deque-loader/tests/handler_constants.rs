@@ -0,0 +1,6 @@
+#[test]
+fn it_rejects_nonsensical_handler_constants() {
+  let t = trybuild::TestCases::new();
+  t.compile_fail("tests/ui/max_batch_size_must_be_positive.rs");
+  t.compile_fail("tests/ui/cores_per_worker_group_must_be_positive.rs");
+}
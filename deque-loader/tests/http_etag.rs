@@ -0,0 +1,104 @@
+#![cfg(feature = "http-loader")]
+
+use deque_loader::{
+  http::{EtagCache, Fetched, HttpLoader},
+  LoadBy, Loadable, Loader,
+};
+use reqwest::{header, StatusCode};
+use serde::Deserialize;
+use std::sync::{Arc, OnceLock};
+use wiremock::{
+  matchers::{header as has_header, method, path},
+  Mock, MockServer, ResponseTemplate,
+};
+
+static API: OnceLock<String> = OnceLock::new();
+
+#[derive(Loader)]
+#[data_loader(handler = "EtagCache<ArticleLoader>")]
+pub struct ArticleLoader;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Loadable)]
+#[data_loader(handler = "EtagCache<ArticleLoader>")]
+pub struct Article {
+  id: i32,
+  title: String,
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("failed to fetch article")]
+pub struct FetchError;
+
+#[async_trait::async_trait]
+impl HttpLoader for ArticleLoader {
+  type Key = i32;
+  type Value = Article;
+  type Error = FetchError;
+
+  async fn fetch(key: &i32, if_none_match: Option<&str>) -> Result<Fetched<Article>, FetchError> {
+    let mut request =
+      reqwest::Client::new().get(format!("{}/articles/{}", API.get().unwrap(), key));
+
+    if let Some(etag) = if_none_match {
+      request = request.header(header::IF_NONE_MATCH, etag);
+    }
+
+    let response = request.send().await.map_err(|_| FetchError)?;
+
+    match response.status() {
+      StatusCode::NOT_MODIFIED => Ok(Fetched::NotModified),
+      StatusCode::NOT_FOUND => Ok(Fetched::NotFound),
+      StatusCode::OK => {
+        let etag = response
+          .headers()
+          .get(header::ETAG)
+          .and_then(|etag| etag.to_str().ok())
+          .map(String::from);
+
+        let value = response.json::<Article>().await.map_err(|_| FetchError)?;
+
+        Ok(Fetched::Modified { value, etag })
+      }
+      _ => Err(FetchError),
+    }
+  }
+}
+
+#[tokio::test]
+async fn it_reuses_cached_values_upon_not_modified() -> Result<(), FetchError> {
+  let server = MockServer::start().await;
+  API.set(server.uri()).unwrap();
+
+  Mock::given(method("GET"))
+    .and(path("/articles/1"))
+    .and(has_header("If-None-Match", "\"v1\""))
+    .respond_with(ResponseTemplate::new(304))
+    .with_priority(1)
+    .expect(2)
+    .mount(&server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/articles/1"))
+    .respond_with(
+      ResponseTemplate::new(200)
+        .insert_header("ETag", "\"v1\"")
+        .set_body_json(serde_json::json!({ "id": 1, "title": "Work stealing" })),
+    )
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let expected = Arc::new(Article {
+    id: 1,
+    title: "Work stealing".into(),
+  });
+
+  for _ in 0..3 {
+    assert_eq!(Article::load_by(1).await?, Some(expected.clone()));
+  }
+
+  assert_eq!(EtagCache::<ArticleLoader>::etag(&1), Some("\"v1\"".into()));
+
+  Ok(())
+}
@@ -0,0 +1,9 @@
+#![cfg(feature = "std-error")]
+
+#[test]
+fn it_rejects_handler_errors_not_implementing_std_error() {
+  let t = trybuild::TestCases::new();
+  t.compile_fail("tests/ui/error_requires_std_error.rs");
+  #[cfg(feature = "http-loader")]
+  t.compile_fail("tests/ui/http_error_requires_std_error.rs");
+}
@@ -0,0 +1,28 @@
+use deque_loader::{
+  Loader,
+  task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler},
+};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Loader)]
+#[data_loader(handler = "WorkerlessLoader")]
+pub struct WorkerlessLoader;
+
+#[deque_loader::async_trait::async_trait]
+impl TaskHandler for WorkerlessLoader {
+  type Key = i32;
+  type Value = i32;
+  type Error = std::fmt::Error;
+  const CORES_PER_WORKER_GROUP: usize = 0;
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.resolve(Ok(HashMap::<i32, Arc<i32>>::new())),
+      TaskAssignment::NoAssignment(receipt) => receipt,
+    }
+  }
+}
+
+fn main() {}
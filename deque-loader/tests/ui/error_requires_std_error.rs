@@ -0,0 +1,22 @@
+use deque_loader::task::{CompletionReceipt, PendingAssignment, Task, TaskAssignment, TaskHandler};
+use std::collections::HashMap;
+
+pub struct UnitErrorLoader;
+
+#[async_trait::async_trait]
+impl TaskHandler for UnitErrorLoader {
+  type Key = i32;
+  type Value = i32;
+  type Error = ();
+
+  async fn handle_task(
+    task: Task<PendingAssignment<Self::Key, Self::Value, Self::Error>>,
+  ) -> Task<CompletionReceipt> {
+    match task.get_assignment::<Self>().await {
+      TaskAssignment::LoadBatch(task) => task.resolve(Ok(HashMap::new())),
+      TaskAssignment::NoAssignment(receipt) => receipt,
+    }
+  }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use deque_loader::http::{Fetched, HttpLoader};
+
+pub struct UnitErrorLoader;
+
+#[async_trait::async_trait]
+impl HttpLoader for UnitErrorLoader {
+  type Key = i32;
+  type Value = i32;
+  type Error = ();
+
+  async fn fetch(key: &i32, _if_none_match: Option<&str>) -> Result<Fetched<i32>, ()> {
+    Ok(Fetched::Modified {
+      value: *key,
+      etag: None,
+    })
+  }
+}
+
+fn main() {}
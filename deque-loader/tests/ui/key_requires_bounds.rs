@@ -0,0 +1,6 @@
+use deque_loader::Key;
+
+#[derive(Key)]
+pub struct Score(f64);
+
+fn main() {}